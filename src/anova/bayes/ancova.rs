@@ -1 +1,296 @@
+use crate::{
+    core::{
+        dataframe::DataFrame,
+        design::{contrast_matrix, Coding},
+        errors::DatasetError,
+    },
+    regression::{
+        bayes::helper::{gprior_bayes_factor, EffectBlock, SamplingOptions},
+        helper::cholesky,
+    },
+};
 
+use super::standard::factor_codes;
+
+#[derive(Debug, Clone)]
+pub struct BayesAncovaOptions {
+    pub fixed_scale: f64,     // 1/2 ("medium") in BayesFactor and JASP
+    pub covariate_scale: f64, // sqrt(2)/4 ("medium" for continuous predictors)
+    pub sampling: SamplingOptions,
+}
+
+impl Default for BayesAncovaOptions {
+    fn default() -> Self {
+        BayesAncovaOptions {
+            fixed_scale: 0.5,
+            covariate_scale: std::f64::consts::SQRT_2 / 4.0,
+            sampling: SamplingOptions::default(),
+        }
+    }
+}
+
+// Group mean at the grand mean of the covariates
+#[derive(Debug)]
+pub struct AdjustedMean {
+    pub level: String,
+    pub conditional: f64, // posterior mean under the model with the factor
+    pub averaged: f64,    // averaged over both models
+}
+
+#[derive(Debug)]
+pub struct BayesAncovaResult {
+    pub bf10: f64, // factor + covariates against covariates only
+    pub error: f64,
+    pub null_probability: f64,
+    pub alternative_probability: f64,
+    pub adjusted_means: Vec<AdjustedMean>,
+    pub fac_name: String,
+    pub dv_name: String,
+}
+
+impl BayesAncovaResult {
+    pub fn bf01(&self) -> f64 {
+        1.0 / self.bf10
+    }
+}
+
+// Bayesian ANCOVA with default g-priors (Rouder et al., 2012) as lmBF/JASP. The
+// covariates share one Zellner-Siow g and are part of the null model, so the Bayes
+// factor only concerns the factor.
+// Both models get equal prior probability, under the null model all adjusted means
+// equal the grand mean
+pub fn ancova_bf(
+    df: &DataFrame,
+    independent_var: &str,
+    covariates: Vec<&str>,
+    dependent_var: &str,
+    options: &BayesAncovaOptions,
+) -> Result<BayesAncovaResult, DatasetError> {
+    let y = df.get_column(dependent_var)?.get_values_as_f64()?;
+    if y.iter().any(|v| !v.is_finite()) {
+        return Err(DatasetError::InvalidData(
+            "Dependent variable contains NaN or inf".to_string(),
+        ));
+    }
+
+    let (levels, codes) = factor_codes(df, independent_var, y.len())?;
+    let basis = contrast_matrix(Coding::Orthonormal, levels.len(), 0);
+    let factor = EffectBlock {
+        name: independent_var.to_string(),
+        columns: (0..levels.len() - 1)
+            .map(|j| codes.iter().map(|&c| basis[c][j]).collect())
+            .collect(),
+        scale: options.fixed_scale,
+    };
+
+    let mut columns = Vec::with_capacity(covariates.len());
+    for covariate in &covariates {
+        let values = df.get_column(covariate)?.get_values_as_f64()?;
+        if values.len() != y.len() {
+            return Err(DatasetError::InvalidData(format!(
+                "Covariate {} must have {} observations",
+                covariate,
+                y.len()
+            )));
+        }
+        if values.iter().any(|v| !v.is_finite()) {
+            return Err(DatasetError::InvalidData(format!(
+                "Covariate {} contains NaN or inf",
+                covariate
+            )));
+        }
+        columns.push(values);
+    }
+
+    // All covariates share one g, as the continuous predictors in lmBF
+    let nuisance = if columns.is_empty() {
+        None
+    } else {
+        Some(EffectBlock {
+            name: covariates.join(" + "),
+            columns: zellner_siow_columns(&columns)?,
+            scale: options.covariate_scale,
+        })
+    };
+
+    let null_blocks: Vec<&EffectBlock> = nuisance.iter().collect();
+    let full_blocks: Vec<&EffectBlock> = [&factor].into_iter().chain(&nuisance).collect();
+    let null = gprior_bayes_factor(&y, &null_blocks, &options.sampling)?;
+    let full = gprior_bayes_factor(&y, &full_blocks, &options.sampling)?;
+
+    let log_bf = full.log_bf - null.log_bf;
+    let alternative_probability = 1.0 / (1.0 + (-log_bf).exp());
+
+    // The design is centered, so the intercept is the grand mean and each level
+    // deviates from it by its centered contrast codes times the factor coefficients
+    let n = y.len() as f64;
+    let grand_mean = y.iter().sum::<f64>() / n;
+    let column_means: Vec<f64> = factor
+        .columns
+        .iter()
+        .map(|column| column.iter().sum::<f64>() / n)
+        .collect();
+    let adjusted_means = levels
+        .iter()
+        .enumerate()
+        .map(|(l, level)| {
+            let conditional = grand_mean
+                + (0..column_means.len())
+                    .map(|j| (basis[l][j] - column_means[j]) * full.coefficients[j])
+                    .sum::<f64>();
+            AdjustedMean {
+                level: level.clone(),
+                conditional,
+                averaged: alternative_probability * conditional
+                    + (1.0 - alternative_probability) * grand_mean,
+            }
+        })
+        .collect();
+
+    Ok(BayesAncovaResult {
+        bf10: log_bf.exp(),
+        error: (full.error.powi(2) + null.error.powi(2)).sqrt(),
+        null_probability: 1.0 - alternative_probability,
+        alternative_probability,
+        adjusted_means,
+        fac_name: independent_var.to_owned(),
+        dv_name: dependent_var.to_owned(),
+    })
+}
+
+// Centers the covariates and maps them to columns with X'X = nI, for which the
+// N(0, g sigma^2) prior per column is the Zellner-Siow prior N(0, g sigma^2 n (X'X)^-1)
+// on the slopes of the covariates
+fn zellner_siow_columns(columns: &[Vec<f64>]) -> Result<Vec<Vec<f64>>, DatasetError> {
+    let n = columns[0].len() as f64;
+    let centered: Vec<Vec<f64>> = columns
+        .iter()
+        .map(|column| {
+            let mean = column.iter().sum::<f64>() / n;
+            column.iter().map(|v| v - mean).collect()
+        })
+        .collect();
+    let gram: Vec<Vec<f64>> = centered
+        .iter()
+        .map(|a| {
+            centered
+                .iter()
+                .map(|b| a.iter().zip(b).map(|(x, y)| x * y).sum::<f64>() / n)
+                .collect()
+        })
+        .collect();
+    let lower = cholesky(&gram).map_err(|_| {
+        DatasetError::InvalidData("Covariates are constant or collinear".to_string())
+    })?;
+
+    // Row by row L^-1 x by forward substitution
+    let k = centered.len();
+    let mut whitened = vec![vec![0.0; centered[0].len()]; k];
+    for i in 0..centered[0].len() {
+        for a in 0..k {
+            let dot: f64 = (0..a).map(|b| lower[a][b] * whitened[b][i]).sum();
+            whitened[a][i] = (centered[a][i] - dot) / lower[a][a];
+        }
+    }
+    Ok(whitened)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{helper::roughly_equals, reader};
+
+    #[test]
+    fn test_without_covariates() {
+        // Without covariates the Bayes factor is that of anovaBF(len ~ supp, ToothGrowth)
+        let df = reader::import_csv("samples/ToothGrowth.csv").unwrap();
+        let result = ancova_bf(&df, "supp", vec![], "len", &Default::default()).unwrap();
+        assert!(roughly_equals(result.bf10, 1.198757, 1e-6));
+        assert_eq!(result.error, 0.0);
+
+        // The OJ and VC means 20.66333 and 16.96333 are shrunk towards the grand mean
+        let oj = &result.adjusted_means[0];
+        assert_eq!(oj.level, "OJ");
+        assert!(oj.conditional < 20.66333 && oj.conditional > 18.81333);
+        assert!(oj.averaged < oj.conditional);
+    }
+
+    #[test]
+    fn test_ancova_bf() {
+        // The Bayes factor lmBF(y ~ group + x) / lmBF(y ~ x) estimates by sampling, here
+        // integrated over the g of the factor and the Zellner-Siow g of x on a fine grid
+        let mut df = DataFrame::new();
+        let groups = (0..12).map(|i| if i < 6 { "a" } else { "b" }.to_string());
+        df.add_categorical_column("group", groups.collect());
+        df.add_numerical_column(
+            "x",
+            vec![3.1, 4.0, 2.2, 5.3, 4.4, 3.6, 3.9, 4.8, 2.9, 5.0, 4.1, 4.6],
+        );
+        df.add_numerical_column(
+            "y",
+            vec![
+                10.2, 11.9, 9.1, 13.0, 11.1, 10.8, 11.8, 13.9, 10.9, 14.2, 12.4, 13.1,
+            ],
+        );
+
+        let result = ancova_bf(&df, "group", vec!["x"], "y", &Default::default()).unwrap();
+        assert!((result.bf10 / 13.75861 - 1.0).abs() < 0.01);
+        assert!(result.error < 0.02);
+        assert!(roughly_equals(
+            result.alternative_probability + result.null_probability,
+            1.0,
+            1e-12
+        ));
+
+        let a = &result.adjusted_means[0];
+        assert_eq!(a.level, "a");
+        assert!(roughly_equals(a.conditional, 11.39656, 0.01));
+        assert!(a.averaged > a.conditional);
+    }
+
+    #[test]
+    fn test_ancova_data3() {
+        let df = reader::import_csv("samples/data3.csv").unwrap();
+        let result = ancova_bf(
+            &df,
+            "condition",
+            vec!["age", "happiness"],
+            "score",
+            &Default::default(),
+        )
+        .unwrap();
+        // Both covariates share one Zellner-Siow g, log BF10 by the same grid integration
+        assert!(roughly_equals(result.bf10.ln(), 185.730996, 0.01));
+        assert!(result.error < 0.02);
+
+        // With overwhelming evidence the adjusted means are close to the least-squares
+        // ones of lm(score ~ condition + age + happiness)
+        let expected = [11.501136, 25.686350, 18.181487];
+        for (mean, expected) in result.adjusted_means.iter().zip(expected) {
+            assert!(roughly_equals(mean.averaged, expected, 0.05));
+        }
+    }
+
+    #[test]
+    fn test_invalid_covariate() {
+        let mut df = reader::import_csv("samples/data3.csv").unwrap();
+        let age = df.get_column("age").unwrap().get_values_as_f64().unwrap();
+        let mut missing = age.clone();
+        missing[5] = f64::NAN;
+        df.add_numerical_column("age_missing", missing);
+        let options = Default::default();
+        let result = ancova_bf(&df, "condition", vec!["age_missing"], "score", &options);
+        assert!(result.is_err());
+
+        // A rescaled copy of a covariate is collinear with it
+        df.add_numerical_column("age_months", age.iter().map(|a| a * 12.0).collect());
+        let result = ancova_bf(
+            &df,
+            "condition",
+            vec!["age", "age_months"],
+            "score",
+            &options,
+        );
+        assert!(matches!(result, Err(DatasetError::InvalidData(m)) if m.contains("collinear")));
+    }
+}
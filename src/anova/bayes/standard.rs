@@ -1 +1,502 @@
+use crate::{
+    core::{
+        dataframe::DataFrame,
+        design::{contrast_matrix, factor_levels, Coding},
+        errors::DatasetError,
+        formula::{Expression, Factor, Formula},
+    },
+    regression::bayes::{
+        averaging::{average_models, InclusionMethod, ModelFit, ModelPrior},
+        helper::{gprior_bayes_factor, EffectBlock, SamplingOptions},
+    },
+};
 
+pub use crate::regression::bayes::averaging::{
+    AveragedEstimate, InclusionBayesFactor, ModelBayesFactor,
+};
+
+// Which models of a factorial design are compared against the null model
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ModelSpace {
+    // Interactions only enter together with the main effects they are made of
+    WithMain,
+    // Every subset of effects
+    All,
+}
+
+#[derive(Debug, Clone)]
+pub struct BayesAnovaOptions {
+    pub fixed_scale: f64,  // 1/2 ("medium") in BayesFactor and JASP
+    pub random_scale: f64, // 1 ("nuisance")
+    pub models: ModelSpace,
+    pub model_prior: ModelPrior,
+    pub inclusion: InclusionMethod,
+    pub sampling: SamplingOptions,
+}
+
+impl Default for BayesAnovaOptions {
+    fn default() -> Self {
+        BayesAnovaOptions {
+            fixed_scale: 0.5,
+            random_scale: 1.0,
+            models: ModelSpace::WithMain,
+            model_prior: ModelPrior::Uniform,
+            inclusion: InclusionMethod::AllModels,
+            sampling: SamplingOptions::default(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct BayesAnovaResult {
+    pub models: Vec<ModelBayesFactor>, // the null model comes first
+    pub inclusion: Vec<InclusionBayesFactor>,
+    // Model-averaged deviations of the levels (and cells of interactions) from the
+    // grand mean, named "a = x" and "a = x & b = y"
+    pub estimates: Vec<AveragedEstimate>,
+}
+
+// Default Bayes factors for fixed-effects ANOVA designs (Rouder, Morey, Speckman &
+// Province, 2012) as in anovaBF. Every effect (main effects and interactions of the
+// fixed factors) gets its own g-prior, random factors are nuisance effects included
+// in every model, so bf10 is relative to the model with only the random factors.
+// Posterior model probabilities and inclusion Bayes factors follow the model prior
+// and inclusion method of the options, by default as in JASP
+pub fn anova_bf(
+    df: &DataFrame,
+    fixed_factors: Vec<&str>,
+    random_factors: Vec<&str>,
+    dependent_var: &str,
+    options: &BayesAnovaOptions,
+) -> Result<BayesAnovaResult, DatasetError> {
+    compare_models(
+        df,
+        &fixed_factors,
+        None,
+        &random_factors,
+        dependent_var,
+        options,
+    )
+}
+
+// Bayesian ANOVA from a formula such as "score ~ a * b" or "score ~ a + b". Only the
+// effects of the formula are compared, so "a + b" leaves out the interaction. The
+// right-hand side may only contain categorical columns
+pub fn anova_bf_formula(
+    df: &DataFrame,
+    formula: &str,
+    random_factors: Vec<&str>,
+    options: &BayesAnovaOptions,
+) -> Result<BayesAnovaResult, DatasetError> {
+    let formula = Formula::parse(formula)?;
+    let dependent_var = match &formula.response {
+        Some(Expression::Variable(name)) => name,
+        _ => {
+            return Err(DatasetError::InvalidData(
+                "The response of the formula must be a column.".to_string(),
+            ))
+        }
+    };
+
+    let mut terms = Vec::with_capacity(formula.terms.len());
+    for term in &formula.terms {
+        let names = term
+            .factors
+            .iter()
+            .map(|factor| match factor {
+                Factor::Variable(name) => Ok(name.as_str()),
+                _ => Err(DatasetError::InvalidData(format!(
+                    "{} is not a factor",
+                    factor
+                ))),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        terms.push(names);
+    }
+
+    compare_models(
+        df,
+        &formula.variables(),
+        Some(&terms),
+        &random_factors,
+        dependent_var,
+        options,
+    )
+}
+
+// Compares the models of the fixed effects, all main effects and interactions of the
+// fixed factors or only the given terms
+fn compare_models(
+    df: &DataFrame,
+    fixed_factors: &[&str],
+    terms: Option<&[Vec<&str>]>,
+    random_factors: &[&str],
+    dependent_var: &str,
+    options: &BayesAnovaOptions,
+) -> Result<BayesAnovaResult, DatasetError> {
+    if fixed_factors.is_empty() {
+        return Err(DatasetError::InvalidData(
+            "At least one fixed factor is needed.".to_string(),
+        ));
+    }
+
+    let y = df.get_column(dependent_var)?.get_values_as_f64()?;
+    let fixed = fixed_factors
+        .iter()
+        .map(|name| factor_codes(df, name, y.len()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut effects = fixed_effects(fixed_factors, &fixed, options.fixed_scale);
+    if let Some(terms) = terms {
+        effects.retain(|effect| {
+            terms.iter().any(|term| {
+                term.len() == effect.factors.len()
+                    && effect
+                        .factors
+                        .iter()
+                        .all(|&f| term.contains(&fixed_factors[f]))
+            })
+        });
+    }
+    let nuisance = random_factors
+        .iter()
+        .map(|name| {
+            let (levels, codes) = factor_codes(df, name, y.len())?;
+            Ok(EffectBlock {
+                name: name.to_string(),
+                columns: (0..levels.len())
+                    .map(|l| codes.iter().map(|&c| (c == l) as u8 as f64).collect())
+                    .collect(),
+                scale: options.random_scale,
+            })
+        })
+        .collect::<Result<Vec<_>, DatasetError>>()?;
+
+    let models = model_space(&effects, options.models);
+    let null = gprior_bayes_factor(&y, &nuisance.iter().collect::<Vec<_>>(), &options.sampling)?;
+
+    let n = y.len() as f64;
+    let mut fits = Vec::with_capacity(models.len());
+    for model in &models {
+        let names: Vec<String> = model
+            .iter()
+            .map(|&e| effects[e].block.name.clone())
+            .collect();
+        if model.is_empty() {
+            fits.push(ModelFit {
+                effects: names,
+                log_bf: 0.0,
+                error: 0.0,
+                estimates: Vec::new(),
+            });
+            continue;
+        }
+
+        let blocks: Vec<&EffectBlock> = model
+            .iter()
+            .map(|&e| &effects[e].block)
+            .chain(&nuisance)
+            .collect();
+        let fit = gprior_bayes_factor(&y, &blocks, &options.sampling)?;
+
+        // The design is centered, so a cell deviates from the grand mean by its
+        // centered codes times the coefficients of the effect
+        let mut estimates = Vec::new();
+        let mut offset = 0;
+        for &e in model {
+            let block = &effects[e].block;
+            let means: Vec<f64> = block
+                .columns
+                .iter()
+                .map(|column| column.iter().sum::<f64>() / n)
+                .collect();
+            for (label, codes) in &effects[e].cells {
+                let deviation = (0..codes.len())
+                    .map(|j| (codes[j] - means[j]) * fit.coefficients[offset + j])
+                    .sum();
+                estimates.push((label.clone(), deviation));
+            }
+            offset += block.columns.len();
+        }
+
+        fits.push(ModelFit {
+            effects: names,
+            log_bf: fit.log_bf - null.log_bf,
+            error: (fit.error.powi(2) + null.error.powi(2)).sqrt(),
+            estimates,
+        });
+    }
+
+    let names: Vec<String> = effects.iter().map(|e| e.block.name.clone()).collect();
+    let averaging = average_models(fits, &names, options.model_prior, options.inclusion)?;
+    Ok(BayesAnovaResult {
+        models: averaging.models,
+        inclusion: averaging.inclusion,
+        estimates: averaging.estimates,
+    })
+}
+
+struct FixedEffect {
+    factors: Vec<usize>,
+    block: EffectBlock,
+    cells: Vec<(String, Vec<f64>)>, // codes of each level combination
+}
+
+// Levels and level indices of a factor with n observations
+pub(crate) fn factor_codes(
+    df: &DataFrame,
+    name: &str,
+    n: usize,
+) -> Result<(Vec<String>, Vec<usize>), DatasetError> {
+    let (levels, codes) = factor_levels(df, name)?;
+    if codes.len() != n {
+        return Err(DatasetError::InvalidData(format!(
+            "Factor {} must have {} observations",
+            name, n
+        )));
+    }
+    Ok((levels, codes))
+}
+
+// Main effects and all interactions of the fixed factors. A factor with a levels is
+// coded by a - 1 orthonormal sum-to-zero contrasts, so its prior is symmetric in the
+// levels, and interactions use the products of the contrasts of their factors
+fn fixed_effects(
+    names: &[&str],
+    coded: &[(Vec<String>, Vec<usize>)],
+    scale: f64,
+) -> Vec<FixedEffect> {
+    let n = coded[0].1.len();
+    let bases: Vec<Vec<Vec<f64>>> = coded
+        .iter()
+        .map(|(levels, _)| contrast_matrix(Coding::Orthonormal, levels.len(), 0))
+        .collect();
+    let contrasts: Vec<Vec<Vec<f64>>> = coded
+        .iter()
+        .zip(&bases)
+        .map(|((levels, codes), basis)| {
+            (0..levels.len() - 1)
+                .map(|j| codes.iter().map(|&c| basis[c][j]).collect())
+                .collect()
+        })
+        .collect();
+
+    let mut subsets: Vec<Vec<usize>> = (1..1usize << names.len())
+        .map(|mask| (0..names.len()).filter(|i| mask >> i & 1 == 1).collect())
+        .collect();
+    subsets.sort_by_key(|s: &Vec<usize>| s.len());
+
+    subsets
+        .into_iter()
+        .map(|factors| {
+            let mut columns = vec![vec![1.0; n]];
+            for &f in &factors {
+                columns = columns
+                    .iter()
+                    .flat_map(|column| {
+                        contrasts[f].iter().map(move |contrast| {
+                            column.iter().zip(contrast).map(|(a, b)| a * b).collect()
+                        })
+                    })
+                    .collect();
+            }
+            // Level combinations with codes built in the same order as the columns
+            let mut cells = vec![(Vec::new(), vec![1.0])];
+            for &f in &factors {
+                cells = cells
+                    .iter()
+                    .flat_map(|(labels, codes)| {
+                        let (levels, basis) = (&coded[f].0, &bases[f]);
+                        levels.iter().enumerate().map(move |(l, level)| {
+                            let mut labels: Vec<String> = labels.clone();
+                            labels.push(format!("{} = {}", names[f], level));
+                            let codes = codes
+                                .iter()
+                                .flat_map(|c| basis[l].iter().map(move |b| c * b))
+                                .collect();
+                            (labels, codes)
+                        })
+                    })
+                    .collect();
+            }
+
+            let name = factors
+                .iter()
+                .map(|&f| names[f])
+                .collect::<Vec<_>>()
+                .join(":");
+            FixedEffect {
+                factors,
+                block: EffectBlock {
+                    name,
+                    columns,
+                    scale,
+                },
+                cells: cells
+                    .into_iter()
+                    .map(|(labels, codes)| (labels.join(" & "), codes))
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+// Models as lists of effect indices, ordered by size. With ModelSpace::WithMain an
+// interaction requires the main effects of all its factors
+fn model_space(effects: &[FixedEffect], space: ModelSpace) -> Vec<Vec<usize>> {
+    let mut models: Vec<Vec<usize>> = (0..1usize << effects.len())
+        .map(|mask| (0..effects.len()).filter(|i| mask >> i & 1 == 1).collect())
+        .filter(|model: &Vec<usize>| {
+            space == ModelSpace::All
+                || model.iter().all(|&e| {
+                    effects[e].factors.len() == 1
+                        || effects[e].factors.iter().all(|f| {
+                            model.iter().any(|&m| {
+                                effects[m].factors.len() == 1 && effects[m].factors[0] == *f
+                            })
+                        })
+                })
+        })
+        .collect();
+    models.sort_by_key(|m| m.len());
+    models
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{fixtures::sleep, helper::roughly_equals, reader};
+
+    // ToothGrowth with the dose as a factor, as in the BayesFactor examples
+    fn tooth_growth() -> DataFrame {
+        let mut df = reader::import_csv("samples/ToothGrowth.csv").unwrap();
+        let dose = df.get_column("dose").unwrap().get_values_as_f64().unwrap();
+        df.add_categorical_column("dose_level", dose.iter().map(|d| d.to_string()).collect());
+        df
+    }
+
+    #[test]
+    fn test_one_way() {
+        // anovaBF(extra ~ group, data = sleep) equals the two-sample JZS t-test
+        let result = anova_bf(
+            &sleep(),
+            vec!["group"],
+            vec![],
+            "extra",
+            &BayesAnovaOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(result.models.len(), 2);
+        assert!(result.models[0].effects.is_empty());
+        assert!(roughly_equals(result.models[1].bf10, 1.265925, 1e-5));
+        // With two models the inclusion Bayes factor is the model Bayes factor
+        assert!(roughly_equals(
+            result.inclusion[0].bf_inclusion,
+            1.265925,
+            1e-5
+        ));
+    }
+
+    #[test]
+    fn test_factorial() {
+        // anovaBF(len ~ supp * dose, data = ToothGrowth):
+        // [1] supp                    : 1.198757     ±0.01%
+        // [2] dose                    : 4.983636e+12 ±0%
+        // [3] supp + dose             : 2.963312e+14 ±1.59%
+        // [4] supp + dose + supp:dose : 8.067205e+14 ±1.94%
+        // Single effects are integrated exactly, the others are sampled in both packages
+        let df = tooth_growth();
+        let options = BayesAnovaOptions::default();
+        let result = anova_bf(&df, vec!["supp", "dose_level"], vec![], "len", &options).unwrap();
+        let names: Vec<String> = result
+            .models
+            .iter()
+            .map(|m| m.effects.join(" + "))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "",
+                "supp",
+                "dose_level",
+                "supp + dose_level",
+                "supp + dose_level + supp:dose_level"
+            ]
+        );
+
+        assert!(roughly_equals(result.models[1].bf10, 1.198757, 1e-6));
+        assert!((result.models[2].bf10 / 4.983636e12 - 1.0).abs() < 1e-6);
+        assert!((result.models[3].bf10 / 2.963312e14 - 1.0).abs() < 0.05);
+        assert!((result.models[4].bf10 / 8.067205e14 - 1.0).abs() < 0.05);
+        assert!(result.models[3].error < 0.02);
+
+        let total: f64 = result.models.iter().map(|m| m.posterior_probability).sum();
+        assert!(roughly_equals(total, 1.0, 1e-12));
+        assert_eq!(result.inclusion.len(), 3);
+        assert!(roughly_equals(
+            result.inclusion[0].prior_inclusion,
+            0.6,
+            1e-12
+        ));
+        assert!(result.inclusion[1].bf_inclusion > 1e10);
+
+        // Level deviations of a balanced factor sum to zero
+        let dose_levels: Vec<f64> = ["dose_level = 0.5", "dose_level = 1", "dose_level = 2"]
+            .iter()
+            .map(|name| {
+                result
+                    .estimates
+                    .iter()
+                    .find(|e| e.name == *name)
+                    .unwrap()
+                    .mean
+            })
+            .collect();
+        assert!(roughly_equals(dose_levels.iter().sum::<f64>(), 0.0, 1e-9));
+        assert!(dose_levels[0] < 0.0 && dose_levels[2] > 0.0);
+        assert!(result
+            .estimates
+            .iter()
+            .any(|e| e.name == "supp = OJ & dose_level = 1"));
+
+        let options = BayesAnovaOptions {
+            inclusion: InclusionMethod::MatchedModels,
+            ..Default::default()
+        };
+        let matched = anova_bf(&df, vec!["supp", "dose_level"], vec![], "len", &options).unwrap();
+        let interaction = &matched.inclusion[2];
+        assert!(roughly_equals(
+            interaction.bf_inclusion,
+            matched.models[4].bf10 / matched.models[3].bf10,
+            1e-9
+        ));
+
+        let options = BayesAnovaOptions {
+            models: ModelSpace::All,
+            ..Default::default()
+        };
+        let result = anova_bf(&df, vec!["supp", "dose_level"], vec![], "len", &options).unwrap();
+        assert_eq!(result.models.len(), 8);
+    }
+
+    #[test]
+    fn test_formula() {
+        let df = tooth_growth();
+        let options = BayesAnovaOptions::default();
+        let additive = anova_bf_formula(&df, "len ~ supp + dose_level", vec![], &options).unwrap();
+        let names: Vec<String> = additive
+            .models
+            .iter()
+            .map(|m| m.effects.join(" + "))
+            .collect();
+        assert_eq!(names, vec!["", "supp", "dose_level", "supp + dose_level"]);
+        assert!(roughly_equals(additive.models[1].bf10, 1.198757, 1e-6));
+
+        let full = anova_bf_formula(&df, "len ~ dose_level * supp", vec![], &options).unwrap();
+        assert_eq!(full.models.len(), 5);
+        assert_eq!(full.inclusion[2].effect, "dose_level:supp");
+
+        assert!(anova_bf_formula(&df, "len ~ supp + I(len^2)", vec![], &options).is_err());
+        assert!(anova_bf_formula(&df, "I(len * 2) ~ supp", vec![], &options).is_err());
+    }
+}
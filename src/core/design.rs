@@ -0,0 +1,337 @@
+use std::ops::Range;
+
+use super::{
+    dataframe::{ColumnType, DataFrame},
+    errors::DatasetError,
+};
+
+// Contrast coding of a categorical predictor with k levels (k - 1 columns)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Coding {
+    // Dummy variables against the reference level (contr.treatment)
+    Treatment,
+    // Deviations from the grand mean, the reference level is coded -1 (contr.sum)
+    Effect,
+    // Each level against the mean of the preceding ones (contr.helmert)
+    Helmert,
+    // Helmert contrasts scaled to unit length, used for the g-priors of the Bayesian ANOVA
+    Orthonormal,
+}
+
+// A predictor column and, for categorical columns, its coding and reference level.
+// By default the reference is the first level for treatment coding and the last one
+// for effect coding, Helmert and orthonormal coding ignore it
+#[derive(Debug, Clone, Copy)]
+pub struct Predictor<'a> {
+    pub name: &'a str,
+    pub coding: Coding,
+    pub reference: Option<&'a str>,
+}
+
+impl<'a> Predictor<'a> {
+    pub fn new(name: &'a str) -> Predictor<'a> {
+        Predictor {
+            name,
+            coding: Coding::Treatment,
+            reference: None,
+        }
+    }
+}
+
+// The columns a predictor (or a product of predictors) expands to
+#[derive(Debug, Clone)]
+pub struct DesignTerm {
+    pub name: String,
+    pub columns: Range<usize>,
+    pub levels: Option<Vec<String>>, // levels of a categorical predictor
+}
+
+#[derive(Debug, Clone)]
+pub struct DesignMatrix {
+    pub rows: Vec<Vec<f64>>,
+    pub names: Vec<String>,
+    pub terms: Vec<DesignTerm>,
+    pub intercept: bool, // the first column is the intercept
+}
+
+impl DesignMatrix {
+    pub fn n(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn column(&self, index: usize) -> Vec<f64> {
+        self.rows.iter().map(|row| row[index]).collect()
+    }
+}
+
+// Expands the predictors into a design matrix: numerical columns are used as they are,
+// binary columns as 0/1 and categorical columns by their contrasts. Generated columns
+// are named like "condition[placebo]" (the level coded 1, or the contrast number for
+// Helmert and orthonormal coding)
+pub fn design_matrix(
+    df: &DataFrame,
+    predictors: &[Predictor],
+    intercept: bool,
+) -> Result<DesignMatrix, DatasetError> {
+    let terms = predictors
+        .iter()
+        .map(|predictor| predictor_columns(df, predictor))
+        .collect::<Result<Vec<_>, _>>()?;
+    let n = df.columns.first().map_or(0, |c| c.n());
+    assemble(terms, n, intercept)
+}
+
+// Values of a numerical column, or of a binary column as 0/1
+pub fn numeric_values(df: &DataFrame, name: &str) -> Result<Vec<f64>, DatasetError> {
+    let column = df.get_column(name)?;
+    match column.column_type() {
+        ColumnType::Binary => Ok(column
+            .get_values()
+            .iter()
+            .map(|value| {
+                value
+                    .downcast_ref::<bool>()
+                    .map_or(0.0, |&b| b as u8 as f64)
+            })
+            .collect()),
+        _ => column.get_values_as_f64(),
+    }
+}
+
+// Generated columns of one term before the design matrix is put together
+pub(crate) struct TermColumns {
+    pub name: String,
+    pub names: Vec<String>,
+    pub columns: Vec<Vec<f64>>,
+    pub levels: Option<Vec<String>>,
+}
+
+// Puts the terms together by rows, n is only used when there are no terms
+pub(crate) fn assemble(
+    terms: Vec<TermColumns>,
+    n: usize,
+    intercept: bool,
+) -> Result<DesignMatrix, DatasetError> {
+    let n = terms
+        .iter()
+        .find_map(|term| term.columns.first())
+        .map_or(n, |column| column.len());
+    if terms.iter().flat_map(|t| &t.columns).any(|c| c.len() != n) {
+        return Err(DatasetError::InvalidData(
+            "All predictors must have the same number of observations.".to_string(),
+        ));
+    }
+
+    let mut names = Vec::new();
+    let mut columns: Vec<Vec<f64>> = Vec::new();
+    let mut design_terms = Vec::with_capacity(terms.len());
+    if intercept {
+        names.push("(Intercept)".to_string());
+        columns.push(vec![1.0; n]);
+    }
+    for term in terms {
+        design_terms.push(DesignTerm {
+            name: term.name,
+            columns: columns.len()..columns.len() + term.columns.len(),
+            levels: term.levels,
+        });
+        names.extend(term.names);
+        columns.extend(term.columns);
+    }
+
+    let rows = (0..n)
+        .map(|i| columns.iter().map(|c| c[i]).collect())
+        .collect();
+    Ok(DesignMatrix {
+        rows,
+        names,
+        terms: design_terms,
+        intercept,
+    })
+}
+
+// Levels in alphabetical order and the level index of every observation
+pub fn factor_levels(
+    df: &DataFrame,
+    name: &str,
+) -> Result<(Vec<String>, Vec<usize>), DatasetError> {
+    let values = df.get_column(name)?.get_values_as_str()?;
+    let mut levels = values.clone();
+    levels.sort();
+    levels.dedup();
+    if levels.len() < 2 {
+        return Err(DatasetError::InvalidData(format!(
+            "Factor {} needs at least two levels",
+            name
+        )));
+    }
+
+    let codes = values
+        .iter()
+        .map(|v| levels.binary_search(v).unwrap())
+        .collect();
+    Ok((levels, codes))
+}
+
+// Contrast matrix with one row per level and k - 1 columns
+pub fn contrast_matrix(coding: Coding, levels: usize, reference: usize) -> Vec<Vec<f64>> {
+    let mut matrix = vec![vec![0.0; levels - 1]; levels];
+    match coding {
+        Coding::Treatment => {
+            let others = (0..levels).filter(|&l| l != reference);
+            for (j, level) in others.enumerate() {
+                matrix[level][j] = 1.0;
+            }
+        }
+        Coding::Effect => {
+            let others = (0..levels).filter(|&l| l != reference);
+            for (j, level) in others.enumerate() {
+                matrix[level][j] = 1.0;
+                matrix[reference][j] = -1.0;
+            }
+        }
+        Coding::Helmert => {
+            for j in 0..levels - 1 {
+                for row in matrix.iter_mut().take(j + 1) {
+                    row[j] = -1.0;
+                }
+                matrix[j + 1][j] = (j + 1) as f64;
+            }
+        }
+        Coding::Orthonormal => {
+            for j in 0..levels - 1 {
+                let norm = (((j + 1) * (j + 2)) as f64).sqrt();
+                for row in matrix.iter_mut().take(j + 1) {
+                    row[j] = 1.0 / norm;
+                }
+                matrix[j + 1][j] = -((j + 1) as f64) / norm;
+            }
+        }
+    }
+    matrix
+}
+
+pub(crate) fn predictor_columns(
+    df: &DataFrame,
+    predictor: &Predictor,
+) -> Result<TermColumns, DatasetError> {
+    let column = df.get_column(predictor.name)?;
+    match column.column_type() {
+        ColumnType::Numerical | ColumnType::Binary => Ok(TermColumns {
+            name: predictor.name.to_string(),
+            names: vec![predictor.name.to_string()],
+            columns: vec![numeric_values(df, predictor.name)?],
+            levels: None,
+        }),
+        ColumnType::Categorical => {
+            let (levels, codes) = factor_levels(df, predictor.name)?;
+            let reference = match predictor.reference {
+                Some(reference) => levels.iter().position(|l| l == reference).ok_or_else(|| {
+                    DatasetError::InvalidData(format!(
+                        "Reference level {} does not occur in {}",
+                        reference, predictor.name
+                    ))
+                })?,
+                None if predictor.coding == Coding::Effect => levels.len() - 1,
+                None => 0,
+            };
+
+            let contrasts = contrast_matrix(predictor.coding, levels.len(), reference);
+            let names = match predictor.coding {
+                Coding::Treatment | Coding::Effect => (0..levels.len())
+                    .filter(|&l| l != reference)
+                    .map(|l| format!("{}[{}]", predictor.name, levels[l]))
+                    .collect(),
+                Coding::Helmert | Coding::Orthonormal => (1..levels.len())
+                    .map(|j| format!("{}[{}]", predictor.name, j))
+                    .collect(),
+            };
+            let columns = (0..levels.len() - 1)
+                .map(|j| codes.iter().map(|&c| contrasts[c][j]).collect())
+                .collect();
+            Ok(TermColumns {
+                name: predictor.name.to_string(),
+                names,
+                columns,
+                levels: Some(levels),
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> DataFrame {
+        let mut df = DataFrame::new();
+        let groups = ["b", "a", "c", "a", "b", "c"];
+        df.add_categorical_column("group", groups.iter().map(|g| g.to_string()).collect());
+        df.add_numerical_column("age", vec![21.0, 34.0, 25.0, 40.0, 29.0, 31.0]);
+        df.add_binary_column("smoker", vec![true, false, false, true, false, true]);
+        df
+    }
+
+    #[test]
+    fn test_contrast_matrix() {
+        // contr.treatment(3), contr.sum(3) and contr.helmert(3) in R
+        let treatment = contrast_matrix(Coding::Treatment, 3, 0);
+        assert_eq!(
+            treatment,
+            vec![vec![0.0, 0.0], vec![1.0, 0.0], vec![0.0, 1.0]]
+        );
+        let effect = contrast_matrix(Coding::Effect, 3, 2);
+        assert_eq!(
+            effect,
+            vec![vec![1.0, 0.0], vec![0.0, 1.0], vec![-1.0, -1.0]]
+        );
+        let helmert = contrast_matrix(Coding::Helmert, 3, 0);
+        assert_eq!(
+            helmert,
+            vec![vec![-1.0, -1.0], vec![1.0, -1.0], vec![0.0, 2.0]]
+        );
+
+        // Orthonormal columns that sum to zero
+        let orthonormal = contrast_matrix(Coding::Orthonormal, 4, 0);
+        for j in 0..3 {
+            let column: Vec<f64> = orthonormal.iter().map(|row| row[j]).collect();
+            assert!(column.iter().sum::<f64>().abs() < 1e-12);
+            assert!((column.iter().map(|v| v * v).sum::<f64>() - 1.0).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_design_matrix() {
+        let df = sample();
+        let predictors = [
+            Predictor::new("group"),
+            Predictor::new("age"),
+            Predictor::new("smoker"),
+        ];
+        let design = design_matrix(&df, &predictors, true).unwrap();
+        assert_eq!(
+            design.names,
+            vec!["(Intercept)", "group[b]", "group[c]", "age", "smoker"]
+        );
+        assert_eq!(design.rows[0], vec![1.0, 1.0, 0.0, 21.0, 1.0]);
+        assert_eq!(design.terms[0].columns, 1..3);
+        assert_eq!(design.terms[1].columns, 3..4);
+        assert_eq!(design.column(2), vec![0.0, 0.0, 1.0, 0.0, 0.0, 1.0]);
+
+        let predictors = [Predictor {
+            name: "group",
+            coding: Coding::Effect,
+            reference: Some("a"),
+        }];
+        let design = design_matrix(&df, &predictors, false).unwrap();
+        assert_eq!(design.names, vec!["group[b]", "group[c]"]);
+        assert_eq!(design.rows[1], vec![-1.0, -1.0]);
+
+        let invalid = [Predictor {
+            name: "group",
+            coding: Coding::Treatment,
+            reference: Some("d"),
+        }];
+        assert!(design_matrix(&df, &invalid, true).is_err());
+    }
+}
@@ -0,0 +1,34 @@
+// Datasets of R shared by the tests
+use super::dataframe::DataFrame;
+#[cfg(feature = "regression")]
+use super::reader;
+
+// mpg, hp, wt, am (binary) and carb of mtcars, with the car names in model
+#[cfg(feature = "regression")]
+pub fn mtcars() -> DataFrame {
+    reader::import_csv("samples/mtcars.csv").unwrap()
+}
+
+// Extra hours of sleep of ten patients under two drugs, patient by patient
+const EXTRA: [[f64; 10]; 2] = [
+    [0.7, -1.6, -0.2, -1.2, -0.1, 3.4, 3.7, 0.8, 0.0, 2.0],
+    [1.9, 0.8, 1.1, 0.1, -0.1, 4.4, 5.5, 1.6, 4.6, 3.4],
+];
+
+// sleep in long format, extra with the drug as the categorical group "1" or "2"
+pub fn sleep() -> DataFrame {
+    let mut df = DataFrame::new();
+    df.add_numerical_column("extra", EXTRA.concat());
+    let groups = (0..20).map(|i| (i / 10 + 1).to_string()).collect();
+    df.add_categorical_column("group", groups);
+    df
+}
+
+// sleep in wide format, the extra hours of the two drugs as drug_1 and drug_2
+#[cfg(feature = "t_test")]
+pub fn sleep_wide() -> DataFrame {
+    let mut df = DataFrame::new();
+    df.add_numerical_column("drug_1", EXTRA[0].to_vec());
+    df.add_numerical_column("drug_2", EXTRA[1].to_vec());
+    df
+}
@@ -0,0 +1,767 @@
+use std::fmt;
+
+use super::{
+    dataframe::DataFrame,
+    design::{assemble, numeric_values, predictor_columns, DesignMatrix, Predictor, TermColumns},
+    errors::DatasetError,
+};
+
+// A model formula in Wilkinson-Rogers notation as in R, e.g. "score ~ condition * age".
+// The right-hand side supports +, -, : (interaction), * (main effects and interaction),
+// ^ (crossing up to an order), parentheses, 0/1 and -1 for the intercept, I() for
+// arithmetic on numerical columns and poly(x, degree) for orthogonal polynomials
+#[derive(Debug, Clone, PartialEq)]
+pub struct Formula {
+    pub response: Option<Expression>,
+    pub terms: Vec<Term>, // ordered by interaction order as in R
+    pub intercept: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Factor {
+    Variable(String),
+    Identity(Expression),
+    Poly { variable: String, degree: usize },
+}
+
+// A main effect or the interaction of its factors
+#[derive(Debug, Clone, PartialEq)]
+pub struct Term {
+    pub factors: Vec<Factor>,
+}
+
+// Arithmetic inside I() and on the left-hand side
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+    Variable(String),
+    Number(f64),
+    Negate(Box<Expression>),
+    Binary(Operator, Box<Expression>, Box<Expression>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Power,
+}
+
+impl Formula {
+    pub fn parse(text: &str) -> Result<Formula, DatasetError> {
+        let mut parser = Parser {
+            tokens: tokenize(text)?,
+            position: 0,
+        };
+        parser.formula()
+    }
+
+    // Values of the left-hand side
+    pub fn response(&self, df: &DataFrame) -> Result<Vec<f64>, DatasetError> {
+        match &self.response {
+            Some(expression) => expression.evaluate(df),
+            None => Err(DatasetError::InvalidData(
+                "The formula has no response.".to_string(),
+            )),
+        }
+    }
+
+    // Variables of the right-hand side in order of appearance
+    pub fn variables(&self) -> Vec<&str> {
+        let mut variables: Vec<&str> = Vec::new();
+        for factor in self.terms.iter().flat_map(|t| &t.factors) {
+            let mut names = Vec::new();
+            match factor {
+                Factor::Variable(name) | Factor::Poly { variable: name, .. } => names.push(name),
+                Factor::Identity(expression) => expression.variables(&mut names),
+            }
+            for name in names {
+                if !variables.contains(&name.as_str()) {
+                    variables.push(name);
+                }
+            }
+        }
+        variables
+    }
+
+    // Design matrix with categorical variables dummy coded against their first level
+    pub fn design_matrix(&self, df: &DataFrame) -> Result<DesignMatrix, DatasetError> {
+        self.design_matrix_with(df, &[])
+    }
+
+    // Design matrix where the given predictors set the coding of categorical variables.
+    // Every factor of an interaction uses its contrasts, the columns of the first
+    // factor vary fastest and are named like "condition[placebo]:age"
+    pub fn design_matrix_with(
+        &self,
+        df: &DataFrame,
+        predictors: &[Predictor],
+    ) -> Result<DesignMatrix, DatasetError> {
+        let terms = self
+            .terms
+            .iter()
+            .map(|term| term.columns(df, predictors))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        assemble(terms, row_count(df), self.intercept)
+    }
+}
+
+impl Term {
+    fn columns(
+        &self,
+        df: &DataFrame,
+        predictors: &[Predictor],
+    ) -> Result<TermColumns, DatasetError> {
+        let mut names = vec![String::new()];
+        let mut columns = vec![vec![1.0; row_count(df)]];
+        let mut levels = None;
+        for (index, factor) in self.factors.iter().enumerate() {
+            let factor_columns = factor.columns(df, predictors)?;
+            if self.factors.len() == 1 {
+                levels = factor_columns.levels;
+            }
+
+            let separator = if index == 0 { "" } else { ":" };
+            let mut products = Vec::new();
+            let mut product_names = Vec::new();
+            for (name, column) in factor_columns.names.iter().zip(&factor_columns.columns) {
+                for (previous, values) in names.iter().zip(&columns) {
+                    product_names.push(format!("{}{}{}", previous, separator, name));
+                    products.push(values.iter().zip(column).map(|(a, b)| a * b).collect());
+                }
+            }
+            names = product_names;
+            columns = products;
+        }
+
+        Ok(TermColumns {
+            name: self.to_string(),
+            names,
+            columns,
+            levels,
+        })
+    }
+}
+
+impl Factor {
+    fn columns(
+        &self,
+        df: &DataFrame,
+        predictors: &[Predictor],
+    ) -> Result<TermColumns, DatasetError> {
+        match self {
+            Factor::Variable(name) => {
+                let predictor = predictors
+                    .iter()
+                    .find(|p| p.name == name)
+                    .copied()
+                    .unwrap_or(Predictor::new(name));
+                predictor_columns(df, &predictor)
+            }
+            Factor::Identity(expression) => Ok(TermColumns {
+                name: self.to_string(),
+                names: vec![self.to_string()],
+                columns: vec![expression.evaluate(df)?],
+                levels: None,
+            }),
+            Factor::Poly { variable, degree } => {
+                let values = numeric_values(df, variable)?;
+                Ok(TermColumns {
+                    name: self.to_string(),
+                    names: (1..=*degree).map(|d| format!("{}{}", self, d)).collect(),
+                    columns: orthogonal_polynomials(&values, *degree)?,
+                    levels: None,
+                })
+            }
+        }
+    }
+}
+
+impl Expression {
+    pub fn evaluate(&self, df: &DataFrame) -> Result<Vec<f64>, DatasetError> {
+        match self {
+            Expression::Variable(name) => numeric_values(df, name),
+            Expression::Number(value) => Ok(vec![*value; row_count(df)]),
+            Expression::Negate(inner) => Ok(inner.evaluate(df)?.iter().map(|v| -v).collect()),
+            Expression::Binary(operator, left, right) => {
+                let (left, right) = (left.evaluate(df)?, right.evaluate(df)?);
+                if left.len() != right.len() {
+                    return Err(DatasetError::InvalidData(
+                        "Columns in I() must have the same number of observations.".to_string(),
+                    ));
+                }
+                Ok(left
+                    .iter()
+                    .zip(&right)
+                    .map(|(a, b)| match operator {
+                        Operator::Add => a + b,
+                        Operator::Subtract => a - b,
+                        Operator::Multiply => a * b,
+                        Operator::Divide => a / b,
+                        Operator::Power => a.powf(*b),
+                    })
+                    .collect())
+            }
+        }
+    }
+
+    fn variables<'a>(&'a self, names: &mut Vec<&'a String>) {
+        match self {
+            Expression::Variable(name) => names.push(name),
+            Expression::Number(_) => {}
+            Expression::Negate(inner) => inner.variables(names),
+            Expression::Binary(_, left, right) => {
+                left.variables(names);
+                right.variables(names);
+            }
+        }
+    }
+
+    fn precedence(&self) -> u8 {
+        match self {
+            Expression::Binary(Operator::Add | Operator::Subtract, _, _) => 1,
+            Expression::Binary(Operator::Multiply | Operator::Divide, _, _) => 2,
+            Expression::Negate(_) => 3,
+            Expression::Binary(Operator::Power, _, _) => 4,
+            Expression::Variable(_) | Expression::Number(_) => 5,
+        }
+    }
+}
+
+// Labels follow R's deparse, e.g. "I(age^2)", "poly(age, 2)" and "condition:age"
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let wrap = |f: &mut fmt::Formatter<'_>, inner: &Expression, minimum: u8| {
+            if inner.precedence() < minimum {
+                write!(f, "({})", inner)
+            } else {
+                write!(f, "{}", inner)
+            }
+        };
+        match self {
+            Expression::Variable(name) => write!(f, "{}", name),
+            Expression::Number(value) => write!(f, "{}", value),
+            Expression::Negate(inner) => {
+                write!(f, "-")?;
+                wrap(f, inner, 3)
+            }
+            Expression::Binary(operator, left, right) => {
+                let (symbol, precedence) = match operator {
+                    Operator::Add => (" + ", 1),
+                    Operator::Subtract => (" - ", 1),
+                    Operator::Multiply => (" * ", 2),
+                    Operator::Divide => (" / ", 2),
+                    Operator::Power => ("^", 4),
+                };
+                // Left associative except for ^
+                let (left_min, right_min) = if *operator == Operator::Power {
+                    (precedence + 1, precedence)
+                } else {
+                    (precedence, precedence + 1)
+                };
+                wrap(f, left, left_min)?;
+                write!(f, "{}", symbol)?;
+                wrap(f, right, right_min)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Factor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Factor::Variable(name) => write!(f, "{}", name),
+            Factor::Identity(expression) => write!(f, "I({})", expression),
+            Factor::Poly { variable, degree } => write!(f, "poly({}, {})", variable, degree),
+        }
+    }
+}
+
+impl fmt::Display for Term {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let labels: Vec<String> = self.factors.iter().map(|f| f.to_string()).collect();
+        write!(f, "{}", labels.join(":"))
+    }
+}
+
+fn row_count(df: &DataFrame) -> usize {
+    df.columns.first().map_or(0, |c| c.n())
+}
+
+// Orthogonal polynomials of degree 1..=degree with unit length, like poly() in R
+fn orthogonal_polynomials(x: &[f64], degree: usize) -> Result<Vec<Vec<f64>>, DatasetError> {
+    let mut unique = x.to_vec();
+    unique.sort_by(|a, b| a.total_cmp(b));
+    unique.dedup();
+    if degree == 0 || degree >= unique.len() {
+        return Err(DatasetError::InvalidData(
+            "The degree of poly() must be less than the number of unique points.".to_string(),
+        ));
+    }
+
+    // Gram-Schmidt on the powers of the centered values
+    let mean = x.iter().sum::<f64>() / x.len() as f64;
+    let mut basis: Vec<Vec<f64>> = vec![vec![1.0 / (x.len() as f64).sqrt(); x.len()]];
+    for d in 1..=degree {
+        let mut column: Vec<f64> = x.iter().map(|v| (v - mean).powi(d as i32)).collect();
+        for previous in &basis {
+            let projection: f64 = column.iter().zip(previous).map(|(a, b)| a * b).sum();
+            for (value, p) in column.iter_mut().zip(previous) {
+                *value -= projection * p;
+            }
+        }
+        let norm = column.iter().map(|v| v * v).sum::<f64>().sqrt();
+        basis.push(column.iter().map(|v| v / norm).collect());
+    }
+    basis.remove(0);
+    Ok(basis)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Name(String),
+    Number(f64),
+    Symbol(char),
+}
+
+fn invalid(message: &str) -> DatasetError {
+    DatasetError::InvalidData(format!("Invalid formula: {}", message))
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, DatasetError> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit()
+            || (c == '.' && chars.get(i + 1).is_some_and(|d| d.is_ascii_digit()))
+        {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            if i < chars.len() && (chars[i] == 'e' || chars[i] == 'E') {
+                i += 1;
+                if i < chars.len() && (chars[i] == '+' || chars[i] == '-') {
+                    i += 1;
+                }
+                while i < chars.len() && chars[i].is_ascii_digit() {
+                    i += 1;
+                }
+            }
+            let number: String = chars[start..i].iter().collect();
+            let value = number
+                .parse()
+                .map_err(|_| invalid(&format!("{} is not a number", number)))?;
+            tokens.push(Token::Number(value));
+        } else if c.is_alphabetic() || c == '.' {
+            let start = i;
+            while i < chars.len()
+                && (chars[i].is_alphanumeric() || chars[i] == '.' || chars[i] == '_')
+            {
+                i += 1;
+            }
+            tokens.push(Token::Name(chars[start..i].iter().collect()));
+        } else if c == '`' {
+            // Non-syntactic column names are quoted with backticks
+            let end = chars[i + 1..]
+                .iter()
+                .position(|&d| d == '`')
+                .ok_or_else(|| invalid("unterminated backtick"))?;
+            tokens.push(Token::Name(chars[i + 1..i + 1 + end].iter().collect()));
+            i += end + 2;
+        } else if "~+-*/^:(),".contains(c) {
+            tokens.push(Token::Symbol(c));
+            i += 1;
+        } else {
+            return Err(invalid(&format!("unexpected character {}", c)));
+        }
+    }
+    Ok(tokens)
+}
+
+// Terms of a subexpression, the intercept is only set by 0, 1 and their removal
+struct TermSet {
+    terms: Vec<Vec<Factor>>,
+    intercept: Option<bool>,
+}
+
+impl TermSet {
+    fn add(&mut self, other: TermSet) {
+        for term in other.terms {
+            if !self.terms.iter().any(|t| same_term(t, &term)) {
+                self.terms.push(term);
+            }
+        }
+        if other.intercept.is_some() {
+            self.intercept = other.intercept;
+        }
+    }
+
+    fn remove(&mut self, other: TermSet) {
+        self.terms
+            .retain(|term| !other.terms.iter().any(|t| same_term(t, term)));
+        if let Some(intercept) = other.intercept {
+            self.intercept = Some(!intercept);
+        }
+    }
+
+    fn interact(&self, other: &TermSet) -> Result<TermSet, DatasetError> {
+        if self.intercept.is_some() || other.intercept.is_some() {
+            return Err(invalid("0 and 1 cannot be part of an interaction"));
+        }
+        let mut result = TermSet {
+            terms: Vec::new(),
+            intercept: None,
+        };
+        for left in &self.terms {
+            for right in &other.terms {
+                let mut factors = left.clone();
+                for factor in right {
+                    if !factors.contains(factor) {
+                        factors.push(factor.clone());
+                    }
+                }
+                result.add(TermSet {
+                    terms: vec![factors],
+                    intercept: None,
+                });
+            }
+        }
+        Ok(result)
+    }
+
+    // a * b = a + b + a:b
+    fn cross(mut self, other: TermSet) -> Result<TermSet, DatasetError> {
+        let interaction = self.interact(&other)?;
+        self.add(other);
+        self.add(interaction);
+        Ok(self)
+    }
+}
+
+fn same_term(a: &[Factor], b: &[Factor]) -> bool {
+    a.len() == b.len() && a.iter().all(|f| b.contains(f))
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn accept(&mut self, symbol: char) -> bool {
+        if self.peek() == Some(&Token::Symbol(symbol)) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, symbol: char) -> Result<(), DatasetError> {
+        if self.accept(symbol) {
+            Ok(())
+        } else {
+            Err(invalid(&format!("expected {}", symbol)))
+        }
+    }
+
+    fn formula(&mut self) -> Result<Formula, DatasetError> {
+        let response = if self.accept('~') {
+            None
+        } else {
+            let response = self.expression()?;
+            self.expect('~')?;
+            Some(response)
+        };
+
+        let set = self.sum()?;
+        if let Some(token) = self.peek() {
+            return Err(invalid(&format!("unexpected {:?}", token)));
+        }
+        let mut terms: Vec<Term> = set
+            .terms
+            .into_iter()
+            .map(|factors| Term { factors })
+            .collect();
+        terms.sort_by_key(|t| t.factors.len());
+
+        Ok(Formula {
+            response,
+            terms,
+            intercept: set.intercept.unwrap_or(true),
+        })
+    }
+
+    fn sum(&mut self) -> Result<TermSet, DatasetError> {
+        let mut set = TermSet {
+            terms: Vec::new(),
+            intercept: None,
+        };
+        let mut subtract = self.accept('-');
+        if !subtract {
+            set.add(self.product()?);
+        } else {
+            set.remove(self.product()?);
+        }
+        loop {
+            if self.accept('+') {
+                subtract = false;
+            } else if self.accept('-') {
+                subtract = true;
+            } else {
+                return Ok(set);
+            }
+            let operand = self.product()?;
+            if subtract {
+                set.remove(operand);
+            } else {
+                set.add(operand);
+            }
+        }
+    }
+
+    fn product(&mut self) -> Result<TermSet, DatasetError> {
+        let mut set = self.interaction()?;
+        while self.accept('*') {
+            let other = self.interaction()?;
+            set = set.cross(other)?;
+        }
+        Ok(set)
+    }
+
+    fn interaction(&mut self) -> Result<TermSet, DatasetError> {
+        let mut set = self.power()?;
+        while self.accept(':') {
+            let other = self.power()?;
+            set = set.interact(&other)?;
+        }
+        Ok(set)
+    }
+
+    // (a + b + c)^2 crosses the terms up to two-way interactions
+    fn power(&mut self) -> Result<TermSet, DatasetError> {
+        let set = self.atom()?;
+        if !self.accept('^') {
+            return Ok(set);
+        }
+        let order = match self.next() {
+            Some(Token::Number(value)) if value >= 1.0 && value.fract() == 0.0 => value as usize,
+            _ => return Err(invalid("^ needs a positive integer")),
+        };
+
+        let mut result = TermSet {
+            terms: set.terms.clone(),
+            intercept: set.intercept,
+        };
+        for _ in 1..order {
+            let crossed = result.interact(&set)?;
+            result.add(crossed);
+        }
+        result.terms.retain(|t| t.len() <= order);
+        Ok(result)
+    }
+
+    fn atom(&mut self) -> Result<TermSet, DatasetError> {
+        let single = |factor: Factor| TermSet {
+            terms: vec![vec![factor]],
+            intercept: None,
+        };
+        match self.next() {
+            Some(Token::Symbol('(')) => {
+                let set = self.sum()?;
+                self.expect(')')?;
+                Ok(set)
+            }
+            Some(Token::Number(value)) if value == 0.0 || value == 1.0 => Ok(TermSet {
+                terms: Vec::new(),
+                intercept: Some(value == 1.0),
+            }),
+            Some(Token::Name(name)) if self.accept('(') => match name.as_str() {
+                "I" => {
+                    let expression = self.expression()?;
+                    self.expect(')')?;
+                    Ok(single(Factor::Identity(expression)))
+                }
+                "poly" => {
+                    let variable = match self.next() {
+                        Some(Token::Name(variable)) => variable,
+                        _ => return Err(invalid("poly() needs a column name")),
+                    };
+                    self.expect(',')?;
+                    let degree = match self.next() {
+                        Some(Token::Number(value)) if value >= 1.0 && value.fract() == 0.0 => {
+                            value as usize
+                        }
+                        _ => return Err(invalid("poly() needs a positive integer degree")),
+                    };
+                    self.expect(')')?;
+                    Ok(single(Factor::Poly { variable, degree }))
+                }
+                _ => Err(invalid(&format!("unknown function {}", name))),
+            },
+            Some(Token::Name(name)) => Ok(single(Factor::Variable(name))),
+            Some(token) => Err(invalid(&format!("unexpected {:?}", token))),
+            None => Err(invalid("unexpected end")),
+        }
+    }
+
+    fn expression(&mut self) -> Result<Expression, DatasetError> {
+        let mut left = self.multiplicative()?;
+        loop {
+            let operator = if self.accept('+') {
+                Operator::Add
+            } else if self.accept('-') {
+                Operator::Subtract
+            } else {
+                return Ok(left);
+            };
+            let right = self.multiplicative()?;
+            left = Expression::Binary(operator, Box::new(left), Box::new(right));
+        }
+    }
+
+    fn multiplicative(&mut self) -> Result<Expression, DatasetError> {
+        let mut left = self.unary()?;
+        loop {
+            let operator = if self.accept('*') {
+                Operator::Multiply
+            } else if self.accept('/') {
+                Operator::Divide
+            } else {
+                return Ok(left);
+            };
+            let right = self.unary()?;
+            left = Expression::Binary(operator, Box::new(left), Box::new(right));
+        }
+    }
+
+    // As in R, -x^2 is -(x^2) and ^ is right associative
+    fn unary(&mut self) -> Result<Expression, DatasetError> {
+        if self.accept('-') {
+            return Ok(Expression::Negate(Box::new(self.unary()?)));
+        }
+        let base = self.primary()?;
+        if self.accept('^') {
+            let exponent = self.unary()?;
+            return Ok(Expression::Binary(
+                Operator::Power,
+                Box::new(base),
+                Box::new(exponent),
+            ));
+        }
+        Ok(base)
+    }
+
+    fn primary(&mut self) -> Result<Expression, DatasetError> {
+        match self.next() {
+            Some(Token::Number(value)) => Ok(Expression::Number(value)),
+            Some(Token::Name(name)) if name == "I" && self.accept('(') => {
+                let expression = self.expression()?;
+                self.expect(')')?;
+                Ok(expression)
+            }
+            Some(Token::Name(name)) => {
+                if self.peek() == Some(&Token::Symbol('(')) {
+                    return Err(invalid(&format!("unknown function {}", name)));
+                }
+                Ok(Expression::Variable(name))
+            }
+            Some(Token::Symbol('(')) => {
+                let expression = self.expression()?;
+                self.expect(')')?;
+                Ok(expression)
+            }
+            Some(token) => Err(invalid(&format!("unexpected {:?}", token))),
+            None => Err(invalid("unexpected end")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::helper::roughly_equals;
+
+    fn labels(formula: &str) -> Vec<String> {
+        let formula = Formula::parse(formula).unwrap();
+        formula.terms.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_terms() {
+        assert_eq!(
+            labels("score ~ condition * age + happiness"),
+            vec!["condition", "age", "happiness", "condition:age"]
+        );
+        assert_eq!(
+            labels("y ~ (a + b + c)^2 - b:c"),
+            vec!["a", "b", "c", "a:b", "a:c"]
+        );
+        assert_eq!(labels("y ~ a:b + b:a + a"), vec!["a", "a:b"]);
+        assert_eq!(
+            labels("y ~ I(age^2) + I(-x / 2 + 1) + poly(age, 3)"),
+            vec!["I(age^2)", "I(-x / 2 + 1)", "poly(age, 3)"]
+        );
+        assert_eq!(labels("y ~ I((a + b) * c)"), vec!["I((a + b) * c)"]);
+
+        assert!(Formula::parse("y ~ x").unwrap().intercept);
+        assert!(!Formula::parse("y ~ x - 1").unwrap().intercept);
+        assert!(!Formula::parse("y ~ 0 + x").unwrap().intercept);
+        assert!(Formula::parse("~ x").unwrap().response.is_none());
+
+        assert!(Formula::parse("y ~ x +").is_err());
+        assert!(Formula::parse("y ~ log(x)").is_err());
+        assert!(Formula::parse("y ~ x) ").is_err());
+    }
+
+    #[test]
+    fn test_formula_design_matrix() {
+        let mut df = DataFrame::new();
+        let groups = ["b", "a", "a", "b", "a"];
+        df.add_categorical_column("group", groups.iter().map(|g| g.to_string()).collect());
+        df.add_numerical_column("x", vec![1.0, 2.0, 3.0, 4.0, 5.0]);
+        df.add_numerical_column("y", vec![2.0, 3.5, 3.9, 6.1, 7.2]);
+
+        let formula = Formula::parse("I(y * 2) ~ group * x + I(x^2)").unwrap();
+        assert_eq!(formula.response(&df).unwrap()[0], 4.0);
+        assert_eq!(formula.variables(), vec!["group", "x"]);
+
+        let design = formula.design_matrix(&df).unwrap();
+        assert_eq!(
+            design.names,
+            vec!["(Intercept)", "group[b]", "x", "I(x^2)", "group[b]:x"]
+        );
+        assert_eq!(design.rows[3], vec![1.0, 1.0, 4.0, 16.0, 4.0]);
+        assert_eq!(design.terms[3].columns, 4..5);
+
+        // poly(1:5, 2) in R
+        let design = Formula::parse("y ~ poly(x, 2) - 1")
+            .unwrap()
+            .design_matrix(&df)
+            .unwrap();
+        assert_eq!(design.names, vec!["poly(x, 2)1", "poly(x, 2)2"]);
+        assert!(roughly_equals(design.rows[0][0], -0.6324555, 1e-7));
+        assert!(roughly_equals(design.rows[0][1], 0.5345225, 1e-7));
+        assert!(roughly_equals(design.rows[2][1], -0.5345225, 1e-7));
+
+        let missing = Formula::parse("y ~ z").unwrap();
+        assert!(missing.design_matrix(&df).is_err());
+    }
+}
@@ -1,3 +1,103 @@
 pub fn roughly_equals(a: f64, b: f64, epsilon: f64) -> bool {
     (a - b).abs() < epsilon
 }
+
+pub fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+// Unbiased (n - 1) variance, unlike ColumnOps::variance which divides by n
+pub fn sample_variance(values: &[f64]) -> f64 {
+    let mean = mean(values);
+    values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (values.len() - 1) as f64
+}
+
+// Pearson's product-moment correlation of two equally long samples
+pub fn pearson(x: &[f64], y: &[f64]) -> f64 {
+    let (mean_x, mean_y) = (mean(x), mean(y));
+    let (mut sxy, mut sxx, mut syy) = (0.0, 0.0, 0.0);
+    for (a, b) in x.iter().zip(y) {
+        sxy += (a - mean_x) * (b - mean_y);
+        sxx += (a - mean_x).powi(2);
+        syy += (b - mean_y).powi(2);
+    }
+    sxy / (sxx * syy).sqrt()
+}
+
+// Ranks starting at 1, tied values receive the average of the ranks they span. NaN
+// values are not ranked and keep a NaN rank, as rank(na.last = "keep") in R
+pub fn rank(values: &[f64]) -> Vec<f64> {
+    let mut order: Vec<usize> = (0..values.len()).filter(|&i| !values[i].is_nan()).collect();
+    order.sort_by(|&a, &b| values[a].total_cmp(&values[b]));
+
+    let mut ranks = vec![f64::NAN; values.len()];
+    let mut i = 0;
+    while i < order.len() {
+        let mut j = i;
+        while j + 1 < order.len() && values[order[j + 1]] == values[order[i]] {
+            j += 1;
+        }
+
+        let average = (i + j) as f64 / 2.0 + 1.0;
+        for &index in &order[i..=j] {
+            ranks[index] = average;
+        }
+        i = j + 1;
+    }
+
+    ranks
+}
+
+// Sizes of the groups of tied values (only groups with more than one member), NaN
+// values are left out
+pub fn tie_sizes(values: &[f64]) -> Vec<usize> {
+    let mut sorted: Vec<f64> = values.iter().copied().filter(|v| !v.is_nan()).collect();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+
+    let mut sizes = Vec::new();
+    let mut i = 0;
+    while i < sorted.len() {
+        let mut j = i;
+        while j + 1 < sorted.len() && sorted[j + 1] == sorted[i] {
+            j += 1;
+        }
+        if j > i {
+            sizes.push(j - i + 1);
+        }
+        i = j + 1;
+    }
+
+    sizes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample_variance() {
+        let values = [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0];
+        assert_eq!(mean(&values), 5.0);
+        assert!(roughly_equals(sample_variance(&values), 32.0 / 7.0, 1e-12));
+    }
+
+    #[test]
+    fn test_pearson() {
+        // cor(c(1, 2, 3, 4, 5), c(2, 1, 4, 3, 5))
+        let r = pearson(&[1.0, 2.0, 3.0, 4.0, 5.0], &[2.0, 1.0, 4.0, 3.0, 5.0]);
+        assert!(roughly_equals(r, 0.8, 1e-12));
+    }
+
+    #[test]
+    fn test_rank() {
+        let ranks = rank(&[3.0, 1.0, 4.0, 1.0, 5.0]);
+        assert_eq!(ranks, vec![3.0, 1.5, 4.0, 1.5, 5.0]);
+        assert_eq!(tie_sizes(&[2.0, 2.0, 1.0, 2.0, 3.0, 3.0]), vec![3, 2]);
+
+        // NaN is neither ranked nor counted as a tie
+        let ranks = rank(&[2.0, f64::NAN, 1.0, 2.0]);
+        assert!(ranks[1].is_nan());
+        assert_eq!([ranks[0], ranks[2], ranks[3]], [2.5, 1.0, 2.5]);
+        assert_eq!(tie_sizes(&[f64::NAN, 1.0, f64::NAN, 1.0]), vec![2]);
+    }
+}
@@ -0,0 +1,125 @@
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Alternative {
+    TwoSided,
+    Less,
+    Greater,
+}
+
+impl Alternative {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Alternative::TwoSided => "two.sided",
+            Alternative::Less => "less",
+            Alternative::Greater => "greater",
+        }
+    }
+
+    // Combines the lower (P(T <= t)) and upper (P(T >= t)) tail probabilities
+    // of a test statistic into the p-value for this alternative. An undefined tail
+    // probability gives an undefined p-value
+    pub fn p_value(&self, lower: f64, upper: f64) -> f64 {
+        match self {
+            Alternative::TwoSided if lower.is_nan() || upper.is_nan() => f64::NAN,
+            Alternative::TwoSided => (2.0 * lower.min(upper)).min(1.0),
+            Alternative::Less => lower,
+            Alternative::Greater => upper,
+        }
+    }
+}
+
+// Multiple comparison corrections as in p.adjust
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PAdjustment {
+    None,
+    Bonferroni,
+    Holm,
+    FalseDiscoveryRate, // Benjamini-Hochberg
+}
+
+// NaN p-values stay NaN and do not count towards the number of tests, as NA in p.adjust
+pub fn adjust_p_values(p_values: &[f64], adjustment: PAdjustment) -> Vec<f64> {
+    let mut adjusted = p_values.to_vec();
+    if adjustment == PAdjustment::None {
+        return adjusted;
+    }
+    let mut order: Vec<usize> = (0..p_values.len())
+        .filter(|&i| !p_values[i].is_nan())
+        .collect();
+    order.sort_by(|&a, &b| p_values[a].total_cmp(&p_values[b]));
+    let m = order.len() as f64;
+
+    match adjustment {
+        PAdjustment::None => {}
+        PAdjustment::Bonferroni => {
+            for &index in &order {
+                adjusted[index] = (p_values[index] * m).min(1.0);
+            }
+        }
+        PAdjustment::Holm => {
+            // Step-down: the smallest p-value is multiplied by m, the next by m - 1, ...
+            let mut running = 0.0f64;
+            for (i, &index) in order.iter().enumerate() {
+                running = running.max(((m - i as f64) * p_values[index]).min(1.0));
+                adjusted[index] = running;
+            }
+        }
+        PAdjustment::FalseDiscoveryRate => {
+            // Step-up from the largest p-value: m / rank times p, kept monotone
+            let mut running = 1.0f64;
+            for (i, &index) in order.iter().enumerate().rev() {
+                running = running.min(m / (i + 1) as f64 * p_values[index]);
+                adjusted[index] = running;
+            }
+        }
+    }
+    adjusted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alternative_p_value() {
+        assert_eq!(Alternative::TwoSided.p_value(0.3, 0.7), 0.6);
+        assert_eq!(Alternative::TwoSided.p_value(0.8, 0.8), 1.0);
+        assert!(Alternative::TwoSided.p_value(f64::NAN, f64::NAN).is_nan());
+        assert!(Alternative::TwoSided.p_value(0.2, f64::NAN).is_nan());
+        assert!(Alternative::Less.p_value(f64::NAN, 0.5).is_nan());
+    }
+
+    #[test]
+    fn test_adjust_p_values() {
+        let p = [0.01, 0.04, 0.03, 0.005];
+        let close = |a: Vec<f64>, b: [f64; 4]| a.iter().zip(b).all(|(x, y)| (x - y).abs() < 1e-12);
+        assert!(close(
+            adjust_p_values(&p, PAdjustment::Bonferroni),
+            [0.04, 0.16, 0.12, 0.02]
+        ));
+        assert!(close(
+            adjust_p_values(&p, PAdjustment::Holm),
+            [0.03, 0.06, 0.06, 0.02]
+        ));
+        assert!(close(
+            adjust_p_values(&p, PAdjustment::FalseDiscoveryRate),
+            [0.02, 0.04, 0.04, 0.02]
+        ));
+    }
+
+    #[test]
+    fn test_adjust_p_values_nan() {
+        // p.adjust(c(0.01, NA, 0.04, 0.03), "holm") gives 0.03 NA 0.06 0.06
+        let p = [0.01, f64::NAN, 0.04, 0.03];
+        let holm = adjust_p_values(&p, PAdjustment::Holm);
+        assert!(holm[1].is_nan());
+        let expected = [0.03, 0.06, 0.06];
+        for (value, e) in [holm[0], holm[2], holm[3]].iter().zip(expected) {
+            assert!((value - e).abs() < 1e-12);
+        }
+        let bonferroni = adjust_p_values(&p, PAdjustment::Bonferroni);
+        assert!(bonferroni[1].is_nan());
+        assert!((bonferroni[2] - 0.12).abs() < 1e-12);
+        assert!(adjust_p_values(&p, PAdjustment::FalseDiscoveryRate)[1].is_nan());
+        assert!(adjust_p_values(&p, PAdjustment::None)[1].is_nan());
+    }
+}
@@ -0,0 +1,177 @@
+// Gauss-Kronrod 7-15 nodes and weights (QUADPACK), the Gauss weights belong
+// to the odd Kronrod nodes
+const KRONROD_NODES: [f64; 8] = [
+    0.991_455_371_120_812_6,
+    0.949_107_912_342_758_5,
+    0.864_864_423_359_769_1,
+    0.741_531_185_599_394_4,
+    0.586_087_235_467_691_1,
+    0.405_845_151_377_397_2,
+    0.207_784_955_007_898_5,
+    0.0,
+];
+const KRONROD_WEIGHTS: [f64; 8] = [
+    0.022_935_322_010_529_22,
+    0.063_092_092_629_978_55,
+    0.104_790_010_322_250_18,
+    0.140_653_259_715_525_92,
+    0.169_004_726_639_267_9,
+    0.190_350_578_064_785_4,
+    0.204_432_940_075_298_9,
+    0.209_482_141_084_727_83,
+];
+const GAUSS_WEIGHTS: [f64; 4] = [
+    0.129_484_966_168_869_7,
+    0.279_705_391_489_276_7,
+    0.381_830_050_505_118_9,
+    0.417_959_183_673_469_4,
+];
+
+const MAX_INTERVALS: usize = 2000;
+
+// Adaptive Gauss-Kronrod quadrature of f over [a, b], either bound may be infinite.
+// Intervals with the largest error estimate are bisected until the total error
+// drops below the relative tolerance
+pub fn integrate<F: Fn(f64) -> f64>(f: F, a: f64, b: f64, tolerance: f64) -> f64 {
+    integrate_dyn(&f, a, b, tolerance)
+}
+
+fn integrate_dyn(f: &dyn Fn(f64) -> f64, a: f64, b: f64, tolerance: f64) -> f64 {
+    if a == b {
+        return 0.0;
+    }
+    if a > b {
+        return -integrate_dyn(f, b, a, tolerance);
+    }
+
+    match (a.is_infinite(), b.is_infinite()) {
+        (false, false) => adaptive(f, a, b, tolerance),
+        // x = a + s / (1 - s)
+        (false, true) => adaptive(
+            &|s: f64| f(a + s / (1.0 - s)) / (1.0 - s).powi(2),
+            0.0,
+            1.0,
+            tolerance,
+        ),
+        // x = b - s / (1 - s)
+        (true, false) => adaptive(
+            &|s: f64| f(b - s / (1.0 - s)) / (1.0 - s).powi(2),
+            0.0,
+            1.0,
+            tolerance,
+        ),
+        (true, true) => {
+            integrate_dyn(f, f64::NEG_INFINITY, 0.0, tolerance)
+                + integrate_dyn(f, 0.0, f64::INFINITY, tolerance)
+        }
+    }
+}
+
+// Integrates over [a, b] in pieces split at the breakpoints, so narrow peaks
+// (e.g. a likelihood concentrated far from the origin) are never stepped over
+pub fn integrate_with_breakpoints<F: Fn(f64) -> f64>(
+    f: F,
+    a: f64,
+    b: f64,
+    breakpoints: &[f64],
+    tolerance: f64,
+) -> f64 {
+    let mut points: Vec<f64> = breakpoints
+        .iter()
+        .copied()
+        .filter(|&x| x > a && x < b)
+        .collect();
+    points.sort_by(|x, y| x.partial_cmp(y).unwrap());
+    points.dedup();
+
+    let mut bounds = vec![a];
+    bounds.extend(points);
+    bounds.push(b);
+
+    bounds
+        .windows(2)
+        .map(|w| integrate_dyn(&f, w[0], w[1], tolerance))
+        .sum()
+}
+
+fn adaptive(f: &dyn Fn(f64) -> f64, a: f64, b: f64, tolerance: f64) -> f64 {
+    let mut intervals = vec![kronrod(f, a, b)];
+
+    while intervals.len() < MAX_INTERVALS {
+        let total: f64 = intervals.iter().map(|i| i.2).sum();
+        let error: f64 = intervals.iter().map(|i| i.3).sum();
+        if error <= tolerance * total.abs() || error < 1e-300 {
+            break;
+        }
+
+        let worst = intervals
+            .iter()
+            .enumerate()
+            .max_by(|x, y| x.1 .3.partial_cmp(&y.1 .3).unwrap())
+            .map(|(index, _)| index)
+            .unwrap();
+        let (lower, upper, _, _) = intervals.swap_remove(worst);
+        let middle = (lower + upper) / 2.0;
+        intervals.push(kronrod(f, lower, middle));
+        intervals.push(kronrod(f, middle, upper));
+    }
+
+    intervals.iter().map(|i| i.2).sum()
+}
+
+// Returns (a, b, integral estimate, error estimate)
+fn kronrod(f: &dyn Fn(f64) -> f64, a: f64, b: f64) -> (f64, f64, f64, f64) {
+    let center = (a + b) / 2.0;
+    let half = (b - a) / 2.0;
+
+    let f_center = f(center);
+    let mut kronrod_sum = f_center * KRONROD_WEIGHTS[7];
+    let mut gauss_sum = f_center * GAUSS_WEIGHTS[3];
+    for j in 0..7 {
+        let dx = half * KRONROD_NODES[j];
+        let pair = f(center - dx) + f(center + dx);
+        kronrod_sum += KRONROD_WEIGHTS[j] * pair;
+        if j % 2 == 1 {
+            gauss_sum += GAUSS_WEIGHTS[j / 2] * pair;
+        }
+    }
+
+    let integral = kronrod_sum * half;
+    let error = ((kronrod_sum - gauss_sum) * half).abs();
+    (a, b, integral, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::helper::roughly_equals;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn test_integrate() {
+        assert!(roughly_equals(
+            integrate(|x| x.sin(), 0.0, PI, 1e-12),
+            2.0,
+            1e-12
+        ));
+        let gaussian = |x: f64| (-x * x / 2.0).exp();
+        let total = integrate(gaussian, f64::NEG_INFINITY, f64::INFINITY, 1e-12);
+        assert!(roughly_equals(total, (2.0 * PI).sqrt(), 1e-10));
+        let tail = integrate(|x| 1.0 / (1.0 + x * x), 1.0, f64::INFINITY, 1e-12);
+        assert!(roughly_equals(tail, PI / 4.0, 1e-10));
+    }
+
+    #[test]
+    fn test_integrate_with_breakpoints() {
+        // A narrow peak far from the origin
+        let peak = |x: f64| (-(x - 50.0).powi(2) * 1e4).exp();
+        let total = integrate_with_breakpoints(
+            peak,
+            f64::NEG_INFINITY,
+            f64::INFINITY,
+            &[49.9, 50.1],
+            1e-10,
+        );
+        assert!(roughly_equals(total, (PI / 1e4).sqrt(), 1e-10));
+    }
+}
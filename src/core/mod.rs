@@ -1,4 +1,11 @@
 pub mod dataframe;
+pub mod design;
 pub mod errors;
+#[cfg(all(test, any(feature = "regression", feature = "t_test")))]
+pub(crate) mod fixtures;
+pub mod formula;
 pub mod helper;
+pub mod hypothesis;
+pub mod integration;
+pub mod random;
 pub mod reader;
@@ -0,0 +1,87 @@
+use super::{
+    helper::{gamma_ln, gamma_p, gamma_q},
+    normal,
+};
+
+pub fn pdf(x: f64, dof: f64) -> f64 {
+    if x < 0.0 {
+        return 0.0;
+    }
+    let k = dof / 2.0;
+    ((k - 1.0) * x.ln() - x / 2.0 - k * 2f64.ln() - gamma_ln(k)).exp()
+}
+
+pub fn cdf(x: f64, dof: f64) -> f64 {
+    if x.is_nan() {
+        return f64::NAN;
+    }
+    gamma_p(dof / 2.0, x / 2.0)
+}
+
+// Upper tail probability P(X > x), the p-value of likelihood-ratio and Wald tests
+pub fn sf(x: f64, dof: f64) -> f64 {
+    if x.is_nan() {
+        return f64::NAN;
+    }
+    gamma_q(dof / 2.0, x / 2.0)
+}
+
+pub fn quantile(p: f64, dof: f64) -> f64 {
+    if p <= 0.0 {
+        return 0.0;
+    }
+    if p >= 1.0 {
+        return f64::INFINITY;
+    }
+
+    // Newton iterations from the Wilson-Hilferty approximation, falling back to
+    // bisection whenever a step would leave the bracket that contains the root
+    let z = normal::quantile(p);
+    let h = 2.0 / (9.0 * dof);
+    let mut x = (dof * (1.0 - h + z * h.sqrt()).powi(3)).max(1e-8);
+    let mut lower = 0.0;
+    let mut upper = f64::INFINITY;
+    for _ in 0..100 {
+        let error = cdf(x, dof) - p;
+        if error < 0.0 {
+            lower = x;
+        } else {
+            upper = x;
+        }
+
+        let mut next = x - error / pdf(x, dof);
+        if !(next > lower && next < upper) {
+            next = if upper.is_finite() {
+                (lower + upper) / 2.0
+            } else {
+                2.0 * x
+            };
+        }
+        if (next - x).abs() < 1e-12 * x.max(1.0) {
+            return next;
+        }
+        x = next;
+    }
+    x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cdf() {
+        // pchisq() in R
+        assert!((cdf(3.841458820694124, 1.0) - 0.95).abs() < 1e-10);
+        assert!((sf(2.0, 2.0) - (-1.0f64).exp()).abs() < 1e-12);
+        assert!((sf(30.0, 4.0) - 4.894437e-6).abs() < 1e-11);
+        assert!((pdf(2.0, 4.0) - 0.1839397).abs() < 1e-7);
+    }
+
+    #[test]
+    fn test_quantile() {
+        assert!((quantile(0.95, 1.0) - 3.841458820694124).abs() < 1e-8);
+        assert!((quantile(0.05, 10.0) - 3.940299136119645).abs() < 1e-8);
+        assert!((quantile(0.999, 3.0) - 16.2662361591628).abs() < 1e-7);
+    }
+}
@@ -1,60 +1,16 @@
-pub fn p_value(f_value: f64, numerator_dof: usize, denominator_dof: usize) -> f64 {
-    1.0 - f_cdf(f_value, numerator_dof, denominator_dof)
-}
-
-fn f_cdf(f_value: f64, numerator_dof: usize, denominator_dof: usize) -> f64 {
-    let x = (numerator_dof as f64 * f_value)
-        / (numerator_dof as f64 * f_value + denominator_dof as f64);
-    incomplete_beta(x, numerator_dof as f64 / 2.0, denominator_dof as f64 / 2.0)
-}
-
-fn incomplete_beta(x: f64, a: f64, b: f64) -> f64 {
-    if x == 0.0 || x == 1.0 {
-        return x;
-    }
-
-    let max_iterations = 1000;
-    let epsilon = 1e-8;
-
-    let factor =
-        (gamma_ln(a + b) - gamma_ln(a) - gamma_ln(b)).exp() * x.powf(a) * (1.0 - x).powf(b) / a;
-
-    let mut result = 0.0;
-    let mut term = 1.0;
-    let mut n = 1;
+use super::helper::regularized_incomplete_beta;
 
-    while n < max_iterations {
-        term *= (n as f64 - 1.0 + a + b) * x / (n as f64 + a);
-        result += term;
-        if term < epsilon {
-            break;
-        }
-        n += 1;
+// Upper tail probability P(F > f) for real-valued degrees of freedom
+pub fn sf(f_value: f64, numerator_dof: f64, denominator_dof: f64) -> f64 {
+    if f_value <= 0.0 {
+        return 1.0;
     }
-
-    factor * result
+    let x = denominator_dof / (denominator_dof + numerator_dof * f_value);
+    regularized_incomplete_beta(x, denominator_dof / 2.0, numerator_dof / 2.0)
 }
 
-fn gamma_ln(x: f64) -> f64 {
-    let coefficient = [
-        76.18009172947146,
-        -86.50532032941677,
-        24.01409824083091,
-        -1.231739572450155,
-        1.208_650_973_866_179e-3,
-        -0.5395239384953e-5,
-    ];
-
-    let y = x;
-    let temp = x + 5.5;
-    let temp = (x + 0.5) * temp.ln() - temp;
-    let mut sum = 1.000000000190015;
-
-    for (j, &coeff) in coefficient.iter().enumerate() {
-        sum += coeff / (y + (j + 1) as f64);
-    }
-
-    temp + (2.5066282746310005 * sum / x).ln()
+pub fn p_value(f_value: f64, numerator_dof: usize, denominator_dof: usize) -> f64 {
+    sf(f_value, numerator_dof as f64, denominator_dof as f64)
 }
 
 #[cfg(test)]
@@ -67,6 +23,18 @@ mod tests {
         let numerator_dof = 2;
         let denominator_dof = 3;
         let p_value_value = p_value(f_value, numerator_dof, denominator_dof);
-        assert!(p_value_value == 0.38490018281897276);
+        // pf(3, 2, 3, lower.tail = FALSE) = 3^(-3/2)
+        assert!((p_value_value - 3f64.powf(-1.5)).abs() < 1e-12);
+        assert_eq!(p_value(0.0, 2, 3), 1.0);
+    }
+
+    #[test]
+    fn test_sf() {
+        // With two numerator degrees of freedom P(F > f) = (1 + 2f / d2)^(-d2 / 2)
+        let expected = (1.0f64 + 2.0 * 3.0 / 3.0).powf(-1.5);
+        assert!((sf(3.0, 2.0, 3.0) - expected).abs() < 1e-12);
+        // pf(69.21121, 2, 29, lower.tail = FALSE)
+        assert!((sf(69.21121, 2.0, 29.0) / 9.109e-12 - 1.0).abs() < 1e-3);
+        assert_eq!(sf(0.0, 2.0, 3.0), 1.0);
     }
 }
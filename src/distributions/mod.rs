@@ -1 +1,5 @@
+pub mod chisq;
 pub mod fdist;
+pub mod helper;
+pub mod normal;
+pub mod tdist;
@@ -0,0 +1,111 @@
+use std::f64::consts::PI;
+
+use super::helper::gamma_q;
+
+pub fn pdf(z: f64) -> f64 {
+    (-0.5 * z * z).exp() / (2.0 * PI).sqrt()
+}
+
+pub fn cdf(z: f64) -> f64 {
+    if z.is_nan() {
+        return f64::NAN;
+    }
+
+    // erfc(x) = Q(1/2, x^2), evaluated on the tail so small probabilities keep their precision
+    let tail = 0.5 * gamma_q(0.5, z * z / 2.0);
+    if z < 0.0 {
+        tail
+    } else {
+        1.0 - tail
+    }
+}
+
+// Upper tail probability P(Z > z)
+pub fn sf(z: f64) -> f64 {
+    cdf(-z)
+}
+
+pub fn quantile(p: f64) -> f64 {
+    if p <= 0.0 {
+        return f64::NEG_INFINITY;
+    }
+    if p >= 1.0 {
+        return f64::INFINITY;
+    }
+
+    // Acklam's rational approximation, refined with a single Halley step
+    let a = [
+        -3.969683028665376e1,
+        2.209460984245205e2,
+        -2.759285104469687e2,
+        1.383_577_518_672_69e2,
+        -3.066479806614716e1,
+        2.506628277459239,
+    ];
+    let b = [
+        -5.447609879822406e1,
+        1.615858368580409e2,
+        -1.556989798598866e2,
+        6.680131188771972e1,
+        -1.328068155288572e1,
+    ];
+    let c = [
+        -7.784894002430293e-3,
+        -3.223964580411365e-1,
+        -2.400758277161838,
+        -2.549732539343734,
+        4.374664141464968,
+        2.938163982698783,
+    ];
+    let d = [
+        7.784695709041462e-3,
+        3.224671290700398e-1,
+        2.445134137142996,
+        3.754408661907416,
+    ];
+
+    let p_low = 0.02425;
+    let x = if p < p_low {
+        let q = (-2.0 * p.ln()).sqrt();
+        (((((c[0] * q + c[1]) * q + c[2]) * q + c[3]) * q + c[4]) * q + c[5])
+            / ((((d[0] * q + d[1]) * q + d[2]) * q + d[3]) * q + 1.0)
+    } else if p <= 1.0 - p_low {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((a[0] * r + a[1]) * r + a[2]) * r + a[3]) * r + a[4]) * r + a[5]) * q
+            / (((((b[0] * r + b[1]) * r + b[2]) * r + b[3]) * r + b[4]) * r + 1.0)
+    } else {
+        let q = (-2.0 * (1.0 - p).ln()).sqrt();
+        -(((((c[0] * q + c[1]) * q + c[2]) * q + c[3]) * q + c[4]) * q + c[5])
+            / ((((d[0] * q + d[1]) * q + d[2]) * q + d[3]) * q + 1.0)
+    };
+
+    let e = cdf(x) - p;
+    let u = e * (2.0 * PI).sqrt() * (x * x / 2.0).exp();
+    x - u / (1.0 + x * u / 2.0)
+}
+
+// Two-sided p-value for a standard normal test statistic
+pub fn p_value(z: f64) -> f64 {
+    (2.0 * cdf(-z.abs())).min(1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cdf() {
+        assert!((cdf(0.0) - 0.5).abs() < 1e-12);
+        assert!((cdf(1.959963984540054) - 0.975).abs() < 1e-10);
+        assert!((cdf(-3.0) - 0.0013498980316301).abs() < 1e-12);
+        assert!((p_value(2.0) - 0.04550026389635842).abs() < 1e-10);
+    }
+
+    #[test]
+    fn test_quantile() {
+        assert!((quantile(0.975) - 1.959963984540054).abs() < 1e-9);
+        assert!((quantile(0.05) + 1.644853626951472).abs() < 1e-9);
+        assert!((quantile(1e-8) + 5.612001244174789).abs() < 1e-8);
+    }
+}
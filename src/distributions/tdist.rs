@@ -0,0 +1,106 @@
+use std::f64::consts::PI;
+
+use super::{
+    helper::{beta_ln, regularized_incomplete_beta},
+    normal,
+};
+
+// Degrees of freedom are taken as f64 so Welch-Satterthwaite corrections can be used
+
+pub fn pdf(t: f64, dof: f64) -> f64 {
+    (-beta_ln(0.5, dof / 2.0) - 0.5 * (dof.ln()) - (dof + 1.0) / 2.0 * (1.0 + t * t / dof).ln())
+        .exp()
+}
+
+pub fn cdf(t: f64, dof: f64) -> f64 {
+    if t.is_nan() {
+        return f64::NAN;
+    }
+    if dof.is_infinite() {
+        return normal::cdf(t);
+    }
+
+    let tail = 0.5 * regularized_incomplete_beta(dof / (dof + t * t), dof / 2.0, 0.5);
+    if t < 0.0 {
+        tail
+    } else {
+        1.0 - tail
+    }
+}
+
+// Upper tail probability P(T > t)
+pub fn sf(t: f64, dof: f64) -> f64 {
+    cdf(-t, dof)
+}
+
+// Two-sided p-value for a t statistic
+pub fn p_value(t: f64, dof: f64) -> f64 {
+    (2.0 * cdf(-t.abs(), dof)).min(1.0)
+}
+
+pub fn quantile(p: f64, dof: f64) -> f64 {
+    if p <= 0.0 {
+        return f64::NEG_INFINITY;
+    }
+    if p >= 1.0 {
+        return f64::INFINITY;
+    }
+    if dof.is_infinite() {
+        return normal::quantile(p);
+    }
+    if dof == 1.0 {
+        return (PI * (p - 0.5)).tan();
+    }
+
+    // Newton iterations from the normal quantile, falling back to bisection
+    // whenever a step would leave the bracket that contains the root
+    let mut lower = -1e6;
+    let mut upper = 1e6;
+    let mut x = normal::quantile(p);
+    for _ in 0..100 {
+        let error = cdf(x, dof) - p;
+        if error.abs() < 1e-14 {
+            break;
+        }
+        if error < 0.0 {
+            lower = x;
+        } else {
+            upper = x;
+        }
+
+        let step = error / pdf(x, dof);
+        let next = x - step;
+        x = if next > lower && next < upper && step.is_finite() {
+            next
+        } else {
+            (lower + upper) / 2.0
+        };
+        if step.abs() < 1e-13 * x.abs().max(1.0) {
+            break;
+        }
+    }
+
+    x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cdf() {
+        assert!((cdf(0.0, 5.0) - 0.5).abs() < 1e-12);
+        assert!((cdf(2.0, 10.0) - 0.96330598).abs() < 1e-8);
+        assert!((p_value(2.0, 10.0) - 0.07338803).abs() < 1e-7);
+        // Cauchy distribution
+        assert!((cdf(1.0, 1.0) - 0.75).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_quantile() {
+        assert!((quantile(0.975, 9.0) - 2.262157).abs() < 1e-6);
+        assert!((quantile(0.95, 18.0) - 1.734064).abs() < 1e-6);
+        let x = quantile(0.025, 2.5);
+        assert!((cdf(x, 2.5) - 0.025).abs() < 1e-12);
+    }
+}
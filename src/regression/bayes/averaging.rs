@@ -0,0 +1,326 @@
+use crate::{core::errors::DatasetError, distributions::helper::beta_ln};
+
+// Prior probabilities of the compared models
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ModelPrior {
+    Uniform,
+    // Beta(alpha, beta) prior on the proportion of included effects, Beta(1, 1) gives
+    // every model size the same total probability
+    BetaBinomial { alpha: f64, beta: f64 },
+}
+
+// Which models inclusion Bayes factors compare
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InclusionMethod {
+    // All models with the effect against all models without it
+    AllModels,
+    // Models with the effect against the same models without it, leaving out models
+    // with interactions that contain the effect (the "Baws factor")
+    MatchedModels,
+}
+
+// A fitted model: its effects, Bayes factor against the common null model and the
+// posterior means of its named parameters
+#[derive(Debug, Clone)]
+pub struct ModelFit {
+    pub effects: Vec<String>,
+    pub log_bf: f64,
+    pub error: f64,
+    pub estimates: Vec<(String, f64)>,
+}
+
+#[derive(Debug)]
+pub struct ModelBayesFactor {
+    pub effects: Vec<String>,
+    pub bf10: f64,
+    pub error: f64, // proportional error of bf10
+    pub prior_probability: f64,
+    pub posterior_probability: f64,
+}
+
+#[derive(Debug)]
+pub struct InclusionBayesFactor {
+    pub effect: String,
+    pub prior_inclusion: f64,
+    pub posterior_inclusion: f64,
+    pub bf_inclusion: f64,
+}
+
+// Posterior mean averaged over all models, parameters missing from a model count as zero
+#[derive(Debug)]
+pub struct AveragedEstimate {
+    pub name: String,
+    pub mean: f64,
+}
+
+#[derive(Debug)]
+pub struct ModelAveraging {
+    pub models: Vec<ModelBayesFactor>,
+    pub inclusion: Vec<InclusionBayesFactor>,
+    pub estimates: Vec<AveragedEstimate>,
+}
+
+// Bayesian model averaging over the fitted models. Model sizes for the beta-binomial
+// prior count the candidate effects only, nuisance terms shared by all models are
+// left out of the effect names. Interactions are named by their terms joined with ':'
+pub fn average_models(
+    fits: Vec<ModelFit>,
+    effects: &[String],
+    prior: ModelPrior,
+    method: InclusionMethod,
+) -> Result<ModelAveraging, DatasetError> {
+    if fits.is_empty() {
+        return Err(DatasetError::InvalidData(
+            "At least one model is needed for model averaging.".to_string(),
+        ));
+    }
+
+    let log_priors: Vec<f64> = match prior {
+        ModelPrior::Uniform => vec![0.0; fits.len()],
+        ModelPrior::BetaBinomial { alpha, beta } => {
+            if !(alpha > 0.0 && beta > 0.0) {
+                return Err(DatasetError::InvalidData(
+                    "The beta-binomial model prior needs positive parameters.".to_string(),
+                ));
+            }
+            let k = effects.len() as f64;
+            fits.iter()
+                .map(|fit| {
+                    let size = fit.effects.len() as f64;
+                    beta_ln(size + alpha, k - size + beta) - beta_ln(alpha, beta)
+                })
+                .collect()
+        }
+    };
+    let prior_probabilities = normalize(&log_priors);
+    let log_posteriors: Vec<f64> = fits
+        .iter()
+        .zip(&log_priors)
+        .map(|(fit, log_prior)| fit.log_bf + log_prior)
+        .collect();
+    let posterior_probabilities = normalize(&log_posteriors);
+
+    let inclusion = effects
+        .iter()
+        .map(|effect| {
+            let (with, without): (Vec<usize>, Vec<usize>) = match method {
+                InclusionMethod::AllModels => {
+                    (0..fits.len()).partition(|&i| fits[i].effects.contains(effect))
+                }
+                InclusionMethod::MatchedModels => matched_models(&fits, effect),
+            };
+            let sum = |models: &[usize], probabilities: &[f64]| {
+                models.iter().map(|&i| probabilities[i]).sum::<f64>()
+            };
+
+            let prior_odds = sum(&with, &prior_probabilities) / sum(&without, &prior_probabilities);
+            let posterior_odds =
+                sum(&with, &posterior_probabilities) / sum(&without, &posterior_probabilities);
+            InclusionBayesFactor {
+                effect: effect.clone(),
+                prior_inclusion: sum(&with, &prior_probabilities),
+                posterior_inclusion: sum(&with, &posterior_probabilities),
+                bf_inclusion: posterior_odds / prior_odds,
+            }
+        })
+        .collect();
+
+    let mut estimates: Vec<AveragedEstimate> = Vec::new();
+    for (fit, probability) in fits.iter().zip(&posterior_probabilities) {
+        for (name, value) in &fit.estimates {
+            match estimates.iter_mut().find(|e| e.name == *name) {
+                Some(estimate) => estimate.mean += probability * value,
+                None => estimates.push(AveragedEstimate {
+                    name: name.clone(),
+                    mean: probability * value,
+                }),
+            }
+        }
+    }
+
+    let models = fits
+        .into_iter()
+        .zip(prior_probabilities.into_iter().zip(posterior_probabilities))
+        .map(
+            |(fit, (prior_probability, posterior_probability))| ModelBayesFactor {
+                effects: fit.effects,
+                bf10: fit.log_bf.exp(),
+                error: fit.error,
+                prior_probability,
+                posterior_probability,
+            },
+        )
+        .collect();
+
+    Ok(ModelAveraging {
+        models,
+        inclusion,
+        estimates,
+    })
+}
+
+fn normalize(log_weights: &[f64]) -> Vec<f64> {
+    let max = log_weights
+        .iter()
+        .cloned()
+        .fold(f64::NEG_INFINITY, f64::max);
+    let total: f64 = log_weights.iter().map(|w| (w - max).exp()).sum();
+    log_weights
+        .iter()
+        .map(|w| (w - max).exp() / total)
+        .collect()
+}
+
+// Models with the effect whose counterpart without it was fitted, and those
+// counterparts. Models with a higher-order term of the effect are excluded
+fn matched_models(fits: &[ModelFit], effect: &str) -> (Vec<usize>, Vec<usize>) {
+    let terms = |name: &str| name.split(':').map(str::to_string).collect::<Vec<_>>();
+    let effect_terms = terms(effect);
+    let higher_order = |name: &String| {
+        let name_terms = terms(name);
+        name_terms.len() > effect_terms.len() && effect_terms.iter().all(|t| name_terms.contains(t))
+    };
+
+    let same_effects =
+        |a: &[&String], b: &[&String]| a.len() == b.len() && a.iter().all(|e| b.contains(e));
+
+    let (mut with, mut without) = (Vec::new(), Vec::new());
+    for (i, fit) in fits.iter().enumerate() {
+        if fit.effects.iter().any(higher_order) {
+            continue;
+        }
+
+        let reduced = without_effect(fit, effect);
+        if fit.effects.iter().any(|e| e == effect) {
+            let matched = fits.iter().any(|other| {
+                !other.effects.iter().any(|e| e == effect)
+                    && same_effects(&other.effects.iter().collect::<Vec<_>>(), &reduced)
+            });
+            if matched {
+                with.push(i);
+            }
+        } else {
+            let matched = fits.iter().any(|other| {
+                other.effects.iter().any(|e| e == effect)
+                    && same_effects(&without_effect(other, effect), &reduced)
+                    && !other.effects.iter().any(higher_order)
+            });
+            if matched {
+                without.push(i);
+            }
+        }
+    }
+
+    (with, without)
+}
+
+fn without_effect<'a>(fit: &'a ModelFit, effect: &str) -> Vec<&'a String> {
+    fit.effects
+        .iter()
+        .filter(|e| e.as_str() != effect)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::helper::roughly_equals;
+
+    fn fits() -> Vec<ModelFit> {
+        let fit = |effects: &[&str], bf: f64, estimate: f64| ModelFit {
+            effects: effects.iter().map(|e| e.to_string()).collect(),
+            log_bf: f64::ln(bf),
+            error: 0.0,
+            estimates: if effects.contains(&"a") {
+                vec![("a".to_string(), estimate)]
+            } else {
+                Vec::new()
+            },
+        };
+        vec![
+            fit(&[], 1.0, 0.0),
+            fit(&["a"], 4.0, 0.5),
+            fit(&["b"], 2.0, 0.0),
+            fit(&["a", "b"], 8.0, 0.3),
+            fit(&["a", "b", "a:b"], 2.0, 0.1),
+        ]
+    }
+
+    fn effects() -> Vec<String> {
+        vec!["a".to_string(), "b".to_string(), "a:b".to_string()]
+    }
+
+    #[test]
+    fn test_uniform_prior() {
+        let result = average_models(
+            fits(),
+            &effects(),
+            ModelPrior::Uniform,
+            InclusionMethod::AllModels,
+        )
+        .unwrap();
+
+        // Posterior probabilities are proportional to the Bayes factors (sum 17)
+        assert!(roughly_equals(
+            result.models[1].posterior_probability,
+            4.0 / 17.0,
+            1e-12
+        ));
+        assert!(roughly_equals(
+            result.inclusion[0].prior_inclusion,
+            0.6,
+            1e-12
+        ));
+        assert!(roughly_equals(
+            result.inclusion[0].bf_inclusion,
+            (14.0 / 3.0) / (3.0 / 2.0),
+            1e-12
+        ));
+        let averaged = (4.0 * 0.5 + 8.0 * 0.3 + 2.0 * 0.1) / 17.0;
+        assert!(roughly_equals(result.estimates[0].mean, averaged, 1e-12));
+    }
+
+    #[test]
+    fn test_beta_binomial_prior() {
+        let prior = ModelPrior::BetaBinomial {
+            alpha: 1.0,
+            beta: 1.0,
+        };
+        let result = average_models(fits(), &effects(), prior, InclusionMethod::AllModels).unwrap();
+        // With three candidate effects the model sizes 0..=3 get 1/4 each before the
+        // restriction to the five fitted models
+        let weights = [1.0 / 4.0, 1.0 / 12.0, 1.0 / 12.0, 1.0 / 12.0, 1.0 / 4.0];
+        let total: f64 = weights.iter().sum();
+        for (model, weight) in result.models.iter().zip(weights) {
+            assert!(roughly_equals(
+                model.prior_probability,
+                weight / total,
+                1e-12
+            ));
+        }
+    }
+
+    #[test]
+    fn test_matched_models() {
+        let result = average_models(
+            fits(),
+            &effects(),
+            ModelPrior::Uniform,
+            InclusionMethod::MatchedModels,
+        )
+        .unwrap();
+
+        // a: {a} and {a, b} against {} and {b}, the interaction model is left out
+        assert!(roughly_equals(
+            result.inclusion[0].bf_inclusion,
+            12.0 / 3.0,
+            1e-12
+        ));
+        // a:b: {a, b, a:b} against {a, b}
+        assert!(roughly_equals(
+            result.inclusion[2].bf_inclusion,
+            2.0 / 8.0,
+            1e-12
+        ));
+    }
+}
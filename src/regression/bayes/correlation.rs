@@ -0,0 +1,284 @@
+use crate::{
+    core::{
+        dataframe::DataFrame, errors::DatasetError, hypothesis::Alternative,
+        integration::integrate_with_breakpoints,
+    },
+    distributions::helper::beta_ln,
+    regression::classical::correlation::{correlation_test, CorrelationMethod},
+};
+
+const TOLERANCE: f64 = 1e-10;
+const POSTERIOR_POINTS: usize = 4000;
+
+#[derive(Debug)]
+pub struct RhoPosterior {
+    pub median: f64,
+    pub ci_lower: f64, // 95% credible interval
+    pub ci_upper: f64,
+}
+
+#[derive(Debug)]
+pub struct BayesCorrelationResult {
+    pub method: CorrelationMethod,
+    pub estimate: f64, // sample r or tau-b
+    pub n: usize,
+    pub bf10: f64,
+    pub width: f64, // kappa of the stretched beta prior
+    pub alternative: Alternative,
+    pub posterior: RhoPosterior, // of rho, or of tau for Kendall's tau
+}
+
+impl BayesCorrelationResult {
+    pub fn bf01(&self) -> f64 {
+        1.0 / self.bf10
+    }
+}
+
+// Bayes factor of a correlation against rho = 0 as in JASP. The prior on rho is a beta
+// distribution stretched to (-1, 1) with both parameters 1 / width, so width 1 is uniform
+// (the JASP default) and width 1/3 matches rscale = "medium" of correlationBF.
+// Directional alternatives truncate the prior to one sign. Spearman is not supported
+pub fn correlation_bf(
+    df: &DataFrame,
+    first_var: &str,
+    second_var: &str,
+    method: CorrelationMethod,
+    width: f64,
+    alternative: Alternative,
+) -> Result<BayesCorrelationResult, DatasetError> {
+    let x = df.get_column(first_var)?.get_values_as_f64()?;
+    let y = df.get_column(second_var)?.get_values_as_f64()?;
+    if method == CorrelationMethod::Spearman {
+        return Err(DatasetError::InvalidData(
+            "Bayes factors are available for Pearson's r and Kendall's tau.".to_string(),
+        ));
+    }
+    let classical = correlation_test(&x, &y, method, Alternative::TwoSided)?;
+    match method {
+        CorrelationMethod::Kendall => {
+            kendall_bf_tau(classical.estimate, classical.n, width, alternative)
+        }
+        _ => correlation_bf_r(classical.estimate, classical.n, width, alternative),
+    }
+}
+
+// Pearson correlation with the exact likelihood of rho given r, after integrating
+// out the means and variances under Jeffreys priors (Ly, Verhagen & Wagenmakers, 2016)
+pub fn correlation_bf_r(
+    r: f64,
+    n: usize,
+    width: f64,
+    alternative: Alternative,
+) -> Result<BayesCorrelationResult, DatasetError> {
+    validate(r, n, width)?;
+    let spread = ((1.0 - r * r) / (n as f64).sqrt()).max(1e-3);
+    let likelihood = |rho: f64| pearson_log_likelihood(rho, r, n);
+    let prior = |rho: f64| stretched_beta_pdf(rho, width);
+    let (bf10, posterior) = posterior(likelihood, prior, r, spread, alternative);
+
+    Ok(BayesCorrelationResult {
+        method: CorrelationMethod::Pearson,
+        estimate: r,
+        n,
+        bf10,
+        width,
+        alternative,
+        posterior,
+    })
+}
+
+// Kendall's tau with the normal approximation of the standardized number of concordant
+// minus discordant pairs, T* ~ N(3/2 tau sqrt(n), 1), and the prior on tau implied by
+// the stretched beta prior on rho = sin(pi tau / 2) (van Doorn, Ly, Marsman &
+// Wagenmakers, 2018)
+pub fn kendall_bf_tau(
+    tau: f64,
+    n: usize,
+    width: f64,
+    alternative: Alternative,
+) -> Result<BayesCorrelationResult, DatasetError> {
+    validate(tau, n, width)?;
+    let nf = n as f64;
+    let s = tau * nf * (nf - 1.0) / 2.0;
+    let t_star = s / (nf * (nf - 1.0) * (2.0 * nf + 5.0) / 18.0).sqrt();
+    let likelihood = |t: f64| -0.5 * (t_star - 1.5 * t * nf.sqrt()).powi(2);
+    let prior = |t: f64| {
+        let half_pi = std::f64::consts::FRAC_PI_2;
+        stretched_beta_pdf((half_pi * t).sin(), width) * half_pi * (half_pi * t).cos()
+    };
+    let estimate = (t_star / (1.5 * nf.sqrt())).clamp(-1.0, 1.0);
+    let (bf10, posterior) = posterior(likelihood, prior, estimate, 1.0 / nf.sqrt(), alternative);
+
+    Ok(BayesCorrelationResult {
+        method: CorrelationMethod::Kendall,
+        estimate: tau,
+        n,
+        bf10,
+        width,
+        alternative,
+        posterior,
+    })
+}
+
+fn validate(estimate: f64, n: usize, width: f64) -> Result<(), DatasetError> {
+    if n < 3 {
+        return Err(DatasetError::InvalidData(
+            "At least three complete pairs are required.".to_string(),
+        ));
+    }
+    if !(width > 0.0 && width.is_finite()) {
+        return Err(DatasetError::InvalidData(
+            "The prior width must be positive.".to_string(),
+        ));
+    }
+    if !(-1.0..=1.0).contains(&estimate) {
+        return Err(DatasetError::InvalidData(
+            "The correlation must lie between -1 and 1.".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+// Density of rho when (rho + 1) / 2 ~ Beta(1 / width, 1 / width)
+fn stretched_beta_pdf(rho: f64, width: f64) -> f64 {
+    if rho.abs() >= 1.0 {
+        return 0.0;
+    }
+    let a = 1.0 / width;
+    ((a - 1.0) * (1.0 - rho * rho).ln() + (1.0 - 2.0 * a) * 2f64.ln() - beta_ln(a, a)).exp()
+}
+
+// Log likelihood of rho up to a constant, from the density of r in Fisher's integral
+// form: (1 - rho^2)^((n - 1)/2) times the integral over w of (cosh w - rho r)^-(n - 1).
+// The integrand is divided by its value at w = 0 and w rescaled to its width
+fn pearson_log_likelihood(rho: f64, r: f64, n: usize) -> f64 {
+    if rho.abs() >= 1.0 {
+        return f64::NEG_INFINITY;
+    }
+    let m = n as f64 - 1.0;
+    let base = 1.0 - rho * r;
+    let scale = (base / m).sqrt();
+    let integrand = |u: f64| {
+        let half = (u * scale / 2.0).sinh();
+        (-m * (2.0 * half * half / base).ln_1p()).exp()
+    };
+    let integral = integrate_with_breakpoints(integrand, 0.0, f64::INFINITY, &[5.0], TOLERANCE);
+    m / 2.0 * (1.0 - rho * rho).ln() - m * base.ln() + scale.ln() + integral.ln()
+}
+
+// Bayes factor against the point null at zero and the posterior median and 95% credible
+// interval, with the likelihood scaled by its value near the estimate to avoid overflow
+fn posterior(
+    log_likelihood: impl Fn(f64) -> f64,
+    prior: impl Fn(f64) -> f64,
+    estimate: f64,
+    spread: f64,
+    alternative: Alternative,
+) -> (f64, RhoPosterior) {
+    let (lower, upper) = match alternative {
+        Alternative::TwoSided => (-1.0, 1.0),
+        Alternative::Less => (-1.0, 0.0),
+        Alternative::Greater => (0.0, 1.0),
+    };
+    let peak = estimate.clamp(lower + spread.min(0.5) / 2.0, upper - spread.min(0.5) / 2.0);
+    let log_max = log_likelihood(peak).max(log_likelihood(0.0));
+    let density = |rho: f64| (log_likelihood(rho) - log_max).exp() * prior(rho);
+
+    // The stretched beta prior is symmetric, so each sign carries half of its mass
+    let mass = if alternative == Alternative::TwoSided {
+        1.0
+    } else {
+        0.5
+    };
+    let breakpoints: Vec<f64> = (-8..=8).map(|k| peak + k as f64 * spread).collect();
+    let marginal = integrate_with_breakpoints(density, lower, upper, &breakpoints, TOLERANCE);
+    let bf10 = marginal / mass * (log_max - log_likelihood(0.0)).exp();
+
+    // Cumulative posterior over the midpoints of a grid spanning the likelihood
+    let mut from = (peak - 12.0 * spread).max(lower);
+    let mut to = (peak + 12.0 * spread).min(upper);
+    if to - from < spread {
+        (from, to) = (lower, upper);
+    }
+    let h = (to - from) / POSTERIOR_POINTS as f64;
+    let mut cumulative = vec![0.0; POSTERIOR_POINTS + 1];
+    for i in 0..POSTERIOR_POINTS {
+        cumulative[i + 1] = cumulative[i] + density(from + (i as f64 + 0.5) * h) * h;
+    }
+    let total = cumulative[POSTERIOR_POINTS];
+    let quantile = |p: f64| {
+        let target = p * total;
+        let i = cumulative
+            .partition_point(|&c| c < target)
+            .clamp(1, POSTERIOR_POINTS);
+        let step = cumulative[i] - cumulative[i - 1];
+        let fraction = if step > 0.0 {
+            (target - cumulative[i - 1]) / step
+        } else {
+            0.0
+        };
+        from + (i as f64 - 1.0 + fraction) * h
+    };
+
+    let posterior = RhoPosterior {
+        median: quantile(0.5),
+        ci_lower: quantile(0.025),
+        ci_upper: quantile(0.975),
+    };
+    (bf10, posterior)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::helper::roughly_equals;
+
+    #[test]
+    fn test_pearson_bf() {
+        // cor(iris$Sepal.Length, iris$Sepal.Width), correlationBF gives 0.5090175
+        let iris = correlation_bf_r(-0.1175698, 150, 1.0 / 3.0, Alternative::TwoSided).unwrap();
+        assert!(roughly_equals(iris.bf10, 0.5090175, 1e-6));
+
+        // Example data of cor.test, r = 0.5711816 with a uniform prior
+        let result = correlation_bf_r(0.5711816, 9, 1.0, Alternative::TwoSided).unwrap();
+        assert!(roughly_equals(result.bf10, 1.263996, 1e-6));
+        let greater = correlation_bf_r(0.5711816, 9, 1.0, Alternative::Greater).unwrap();
+        assert!(roughly_equals(greater.bf10, 2.341890, 1e-6));
+        let less = correlation_bf_r(0.5711816, 9, 1.0, Alternative::Less).unwrap();
+        assert!(roughly_equals(less.bf10, 0.1861023, 1e-6));
+        assert!(roughly_equals(result.posterior.median, 0.45798, 1e-3));
+        assert!(roughly_equals(result.posterior.ci_lower, -0.18145, 1e-3));
+        assert!(roughly_equals(result.posterior.ci_upper, 0.82378, 1e-3));
+    }
+
+    #[test]
+    fn test_kendall_bf() {
+        let mut df = DataFrame::new();
+        df.add_numerical_column(
+            "x",
+            vec![44.4, 45.9, 41.9, 53.3, 44.7, 44.1, 50.7, 45.2, 60.1],
+        );
+        df.add_numerical_column("y", vec![2.6, 3.1, 2.5, 5.0, 3.6, 4.0, 5.2, 2.8, 3.8]);
+        let bf = |alternative| {
+            correlation_bf(&df, "x", "y", CorrelationMethod::Kendall, 1.0, alternative).unwrap()
+        };
+        let result = bf(Alternative::TwoSided);
+        assert!(roughly_equals(result.estimate, 0.4444444, 1e-7));
+        assert!(roughly_equals(result.bf10, 1.382518, 1e-6));
+        assert!(roughly_equals(
+            bf(Alternative::Greater).bf10,
+            2.600546,
+            1e-6
+        ));
+        assert!(roughly_equals(bf(Alternative::Less).bf10, 0.1644891, 1e-6));
+        assert!(correlation_bf(
+            &df,
+            "x",
+            "y",
+            CorrelationMethod::Spearman,
+            1.0,
+            Alternative::TwoSided
+        )
+        .is_err());
+    }
+}
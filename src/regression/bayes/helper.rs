@@ -0,0 +1,424 @@
+use crate::{
+    core::{errors::DatasetError, integration::integrate_with_breakpoints, random::Rng},
+    distributions::helper::gamma_ln,
+    regression::helper::{cholesky, cholesky_solve},
+};
+
+const TOLERANCE: f64 = 1e-10;
+const STUDENT_DOF: f64 = 4.0;
+
+// Design columns that share a single g parameter, e.g. the contrasts of one factor.
+// The scale r sets the g ~ InverseGamma(1/2, r^2 / 2) prior on the effect variance
+#[derive(Debug, Clone)]
+pub struct EffectBlock {
+    pub name: String,
+    pub columns: Vec<Vec<f64>>,
+    pub scale: f64,
+}
+
+// Settings of the importance sampler used when a model has more than one g parameter
+#[derive(Debug, Clone, Copy)]
+pub struct SamplingOptions {
+    pub iterations: usize,
+    pub seed: u64,
+}
+
+impl Default for SamplingOptions {
+    fn default() -> Self {
+        SamplingOptions {
+            iterations: 10_000,
+            seed: 1,
+        }
+    }
+}
+
+// Log Bayes factor against the intercept-only model and its proportional error
+// (zero when the integral is computed by quadrature), with the posterior means of the
+// coefficients of the centered design columns in block order
+#[derive(Debug, Clone)]
+pub struct MarginalLikelihood {
+    pub log_bf: f64,
+    pub error: f64,
+    pub coefficients: Vec<f64>,
+}
+
+// Default Bayes factor of a linear model with independent g-priors per effect block
+// against the intercept-only model (Rouder, Morey, Speckman & Province, 2012). The
+// intercept and error variance get the Jeffreys prior, the coefficients of block k
+// are N(0, g_k sigma^2). The integral over log g is done by quadrature for a single
+// block and by importance sampling around the Laplace approximation otherwise
+pub fn gprior_bayes_factor(
+    y: &[f64],
+    blocks: &[&EffectBlock],
+    sampling: &SamplingOptions,
+) -> Result<MarginalLikelihood, DatasetError> {
+    if blocks.is_empty() {
+        return Ok(MarginalLikelihood {
+            log_bf: 0.0,
+            error: 0.0,
+            coefficients: Vec::new(),
+        });
+    }
+
+    let model = GPriorModel::new(y, blocks)?;
+    let mode = model.mode();
+
+    if blocks.len() == 1 {
+        let peak = model.log_integrand(&mode.location);
+        let spread = (-1.0 / mode.hessian[0][0]).sqrt().clamp(1e-3, 1e3);
+        let breakpoints: Vec<f64> = [-8.0, -4.0, -2.0, -1.0, 0.0, 1.0, 2.0, 4.0, 8.0]
+            .iter()
+            .map(|k| mode.location[0] + k * spread)
+            .collect();
+        let weighted = |f: &dyn Fn(f64) -> f64| {
+            integrate_with_breakpoints(
+                |tau| f(tau) * (model.log_integrand(&[tau]) - peak).exp(),
+                f64::NEG_INFINITY,
+                f64::INFINITY,
+                &breakpoints,
+                TOLERANCE,
+            )
+        };
+
+        // Given g the coefficients are normal around the shrunken least-squares estimate
+        let integral = weighted(&|_| 1.0);
+        let coefficients = (0..model.xty.len())
+            .map(|j| {
+                weighted(&|tau| model.conditional_mean(&[tau]).map_or(0.0, |b| b[j])) / integral
+            })
+            .collect();
+        return Ok(MarginalLikelihood {
+            log_bf: peak + integral.ln(),
+            error: 0.0,
+            coefficients,
+        });
+    }
+
+    model.importance_sample(&mode, sampling)
+}
+
+struct Mode {
+    location: Vec<f64>,
+    hessian: Vec<Vec<f64>>,
+}
+
+// Sufficient statistics of the centered data, with the intercept integrated out
+struct GPriorModel {
+    n: usize,
+    xtx: Vec<Vec<f64>>,
+    xty: Vec<f64>,
+    yty: f64,
+    block_of: Vec<usize>,
+    sizes: Vec<usize>,
+    scales: Vec<f64>,
+}
+
+impl GPriorModel {
+    fn new(y: &[f64], blocks: &[&EffectBlock]) -> Result<GPriorModel, DatasetError> {
+        let n = y.len();
+        if n < 3 {
+            return Err(DatasetError::InvalidData(
+                "At least three observations are needed for a Bayes factor.".to_string(),
+            ));
+        }
+        if blocks
+            .iter()
+            .any(|b| !(b.scale > 0.0 && b.scale.is_finite()))
+        {
+            return Err(DatasetError::InvalidData(
+                "Prior scales must be positive and finite.".to_string(),
+            ));
+        }
+
+        let center = |values: &[f64]| {
+            let mean = values.iter().sum::<f64>() / values.len() as f64;
+            values.iter().map(|v| v - mean).collect::<Vec<f64>>()
+        };
+
+        let y = center(y);
+        let mut columns = Vec::new();
+        let mut block_of = Vec::new();
+        for (index, block) in blocks.iter().enumerate() {
+            for column in &block.columns {
+                if column.len() != n {
+                    return Err(DatasetError::InvalidData(format!(
+                        "Design columns of {} must have {} observations",
+                        block.name, n
+                    )));
+                }
+                columns.push(center(column));
+                block_of.push(index);
+            }
+        }
+
+        let dot = |a: &[f64], b: &[f64]| a.iter().zip(b).map(|(x, y)| x * y).sum::<f64>();
+        let xtx = columns
+            .iter()
+            .map(|a| columns.iter().map(|b| dot(a, b)).collect())
+            .collect();
+        let xty = columns.iter().map(|a| dot(a, &y)).collect();
+        let yty = dot(&y, &y);
+        if yty <= 0.0 {
+            return Err(DatasetError::InvalidData(
+                "The dependent variable has no variance.".to_string(),
+            ));
+        }
+
+        Ok(GPriorModel {
+            n,
+            xtx,
+            xty,
+            yty,
+            block_of,
+            sizes: blocks.iter().map(|b| b.columns.len()).collect(),
+            scales: blocks.iter().map(|b| b.scale).collect(),
+        })
+    }
+
+    // Log of BF(g) times the prior density of tau = log g (including the Jacobian)
+    fn log_integrand(&self, tau: &[f64]) -> f64 {
+        let lower = match self.factor(tau) {
+            Some(lower) => lower,
+            None => return f64::NEG_INFINITY,
+        };
+
+        let log_det: f64 = (0..lower.len()).map(|i| 2.0 * lower[i][i].ln()).sum();
+        let b = cholesky_solve(&lower, &self.xty);
+        let explained: f64 = b.iter().zip(&self.xty).map(|(a, c)| a * c).sum();
+        let residual = (1.0 - explained / self.yty).max(1e-300);
+
+        let mut log_bf = -0.5 * log_det - 0.5 * (self.n - 1) as f64 * residual.ln();
+        for (k, &t) in tau.iter().enumerate() {
+            let rate = self.scales[k].powi(2) / 2.0;
+            log_bf += -0.5 * self.sizes[k] as f64 * t;
+            log_bf += 0.5 * rate.ln() - gamma_ln(0.5) - 0.5 * t - rate * (-t).exp();
+        }
+
+        if log_bf.is_nan() {
+            f64::NEG_INFINITY
+        } else {
+            log_bf
+        }
+    }
+
+    // Posterior mean of the coefficients given g, (X'X + G^-1)^-1 X'y
+    fn conditional_mean(&self, tau: &[f64]) -> Option<Vec<f64>> {
+        self.factor(tau)
+            .map(|lower| cholesky_solve(&lower, &self.xty))
+    }
+
+    // Cholesky factor of X'X + G^-1
+    fn factor(&self, tau: &[f64]) -> Option<Vec<Vec<f64>>> {
+        let mut v = self.xtx.clone();
+        for (i, row) in v.iter_mut().enumerate() {
+            row[i] += (-tau[self.block_of[i]]).exp();
+        }
+        cholesky(&v).ok()
+    }
+
+    // Maximizes the log integrand by damped Newton steps with numerical derivatives
+    fn mode(&self) -> Mode {
+        let d = self.sizes.len();
+        let mut location: Vec<f64> = self.scales.iter().map(|r| 2.0 * r.ln()).collect();
+        let mut value = self.log_integrand(&location);
+
+        for _ in 0..100 {
+            let (gradient, hessian) = self.derivatives(&location);
+            let negative: Vec<Vec<f64>> = hessian
+                .iter()
+                .map(|row| row.iter().map(|h| -h).collect())
+                .collect();
+            let step = match cholesky(&negative) {
+                Ok(lower) => cholesky_solve(&lower, &gradient),
+                Err(_) => gradient.clone(),
+            };
+
+            let mut scale = 1.0;
+            let mut improved = false;
+            while scale > 1e-8 {
+                let candidate: Vec<f64> = (0..d)
+                    .map(|i| location[i] + scale * step[i].clamp(-5.0, 5.0))
+                    .collect();
+                let candidate_value = self.log_integrand(&candidate);
+                if candidate_value > value {
+                    improved = candidate_value - value > 1e-10;
+                    location = candidate;
+                    value = candidate_value;
+                    break;
+                }
+                scale /= 2.0;
+            }
+            if !improved {
+                break;
+            }
+        }
+
+        let (_, hessian) = self.derivatives(&location);
+        Mode { location, hessian }
+    }
+
+    #[allow(clippy::needless_range_loop)]
+    fn derivatives(&self, tau: &[f64]) -> (Vec<f64>, Vec<Vec<f64>>) {
+        let d = tau.len();
+        let h = 1e-3;
+        let at = |shifts: &[(usize, f64)]| {
+            let mut point = tau.to_vec();
+            for &(i, s) in shifts {
+                point[i] += s;
+            }
+            self.log_integrand(&point)
+        };
+
+        let center = at(&[]);
+        let mut gradient = vec![0.0; d];
+        let mut hessian = vec![vec![0.0; d]; d];
+        for i in 0..d {
+            let (up, down) = (at(&[(i, h)]), at(&[(i, -h)]));
+            gradient[i] = (up - down) / (2.0 * h);
+            hessian[i][i] = (up - 2.0 * center + down) / (h * h);
+            for j in 0..i {
+                let cross =
+                    (at(&[(i, h), (j, h)]) - at(&[(i, h), (j, -h)]) - at(&[(i, -h), (j, h)])
+                        + at(&[(i, -h), (j, -h)]))
+                        / (4.0 * h * h);
+                hessian[i][j] = cross;
+                hessian[j][i] = cross;
+            }
+        }
+        (gradient, hessian)
+    }
+
+    // Importance sampling with a multivariate Student t proposal centered at the mode,
+    // with the inverse negative Hessian as scale matrix
+    fn importance_sample(
+        &self,
+        mode: &Mode,
+        sampling: &SamplingOptions,
+    ) -> Result<MarginalLikelihood, DatasetError> {
+        if sampling.iterations < 2 {
+            return Err(DatasetError::InvalidData(
+                "Importance sampling needs at least two iterations.".to_string(),
+            ));
+        }
+
+        let d = mode.location.len();
+        let negative: Vec<Vec<f64>> = mode
+            .hessian
+            .iter()
+            .map(|row| row.iter().map(|h| -h).collect())
+            .collect();
+        // Fall back to a diagonal proposal when the Hessian is not negative definite
+        let precision = cholesky(&negative)
+            .map(|_| negative.clone())
+            .unwrap_or_else(|_| {
+                (0..d)
+                    .map(|i| {
+                        let mut row = vec![0.0; d];
+                        row[i] = negative[i][i].max(1.0);
+                        row
+                    })
+                    .collect()
+            });
+        let precision_lower = cholesky(&precision)?;
+        let log_det_precision: f64 = (0..d).map(|i| 2.0 * precision_lower[i][i].ln()).sum();
+
+        let log_normalizer = gamma_ln((STUDENT_DOF + d as f64) / 2.0)
+            - gamma_ln(STUDENT_DOF / 2.0)
+            - d as f64 / 2.0 * (STUDENT_DOF * std::f64::consts::PI).ln()
+            + 0.5 * log_det_precision;
+
+        let mut rng = Rng::new(sampling.seed);
+        let mut points = Vec::with_capacity(sampling.iterations);
+        let mut log_weights = Vec::with_capacity(sampling.iterations);
+        for _ in 0..sampling.iterations {
+            // x = mode + L'^{-1} z * sqrt(nu / chi^2) has precision LL'
+            let z: Vec<f64> = (0..d).map(|_| rng.normal()).collect();
+            let stretch = (STUDENT_DOF / rng.chi_square(STUDENT_DOF)).sqrt();
+            let mut offset = vec![0.0; d];
+            for i in (0..d).rev() {
+                let dot: f64 = (i + 1..d).map(|k| precision_lower[k][i] * offset[k]).sum();
+                offset[i] = (z[i] * stretch - dot) / precision_lower[i][i];
+            }
+
+            let point: Vec<f64> = (0..d).map(|i| mode.location[i] + offset[i]).collect();
+            let distance: f64 = z.iter().map(|v| (v * stretch).powi(2)).sum();
+            let log_proposal = log_normalizer
+                - (STUDENT_DOF + d as f64) / 2.0 * (1.0 + distance / STUDENT_DOF).ln();
+            log_weights.push(self.log_integrand(&point) - log_proposal);
+            points.push(point);
+        }
+
+        let max = log_weights
+            .iter()
+            .cloned()
+            .fold(f64::NEG_INFINITY, f64::max);
+        let weights: Vec<f64> = log_weights.iter().map(|w| (w - max).exp()).collect();
+        let m = weights.len() as f64;
+        let mean = weights.iter().sum::<f64>() / m;
+        let variance = weights.iter().map(|w| (w - mean).powi(2)).sum::<f64>() / (m - 1.0);
+
+        let mut coefficients = vec![0.0; self.xty.len()];
+        for (point, weight) in points.iter().zip(&weights) {
+            if *weight > 0.0 {
+                if let Some(b) = self.conditional_mean(point) {
+                    for (c, value) in coefficients.iter_mut().zip(b) {
+                        *c += weight * value / (m * mean);
+                    }
+                }
+            }
+        }
+
+        Ok(MarginalLikelihood {
+            log_bf: max + mean.ln(),
+            error: (variance / m).sqrt() / mean,
+            coefficients,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{fixtures::sleep, helper::roughly_equals};
+
+    #[test]
+    fn test_single_block() {
+        // Two groups coded +-1/sqrt(2) with r = 1/2 is the JZS t-test with r = sqrt(2)/2,
+        // ttestBF(formula = extra ~ group, data = sleep) gives 1.265925
+        let extra = sleep()
+            .get_column("extra")
+            .unwrap()
+            .get_values_as_f64()
+            .unwrap();
+        let code = std::f64::consts::FRAC_1_SQRT_2;
+        let block = EffectBlock {
+            name: "group".to_string(),
+            columns: vec![(0..20).map(|i| if i < 10 { code } else { -code }).collect()],
+            scale: 0.5,
+        };
+        let result = gprior_bayes_factor(&extra, &[&block], &SamplingOptions::default()).unwrap();
+        assert!(roughly_equals(result.log_bf.exp(), 1.265925, 1e-5));
+        assert_eq!(result.error, 0.0);
+    }
+
+    #[test]
+    fn test_importance_sampling() {
+        // Two blocks that are exactly orthogonal with the second one irrelevant
+        let y = vec![1.2, 2.9, 0.8, 3.4, 1.5, 3.1, 0.9, 2.6];
+        let first = EffectBlock {
+            name: "a".to_string(),
+            columns: vec![vec![-1.0, 1.0, -1.0, 1.0, -1.0, 1.0, -1.0, 1.0]],
+            scale: 0.5,
+        };
+        let second = EffectBlock {
+            name: "b".to_string(),
+            columns: vec![vec![-1.0, -1.0, 1.0, 1.0, -1.0, -1.0, 1.0, 1.0]],
+            scale: 0.5,
+        };
+        let sampling = SamplingOptions::default();
+        let single = gprior_bayes_factor(&y, &[&first], &sampling).unwrap();
+        let both = gprior_bayes_factor(&y, &[&first, &second], &sampling).unwrap();
+        assert!(both.error > 0.0 && both.error < 0.02);
+        assert!(both.log_bf < single.log_bf);
+    }
+}
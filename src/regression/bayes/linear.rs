@@ -0,0 +1,535 @@
+use std::collections::HashMap;
+
+use crate::{
+    core::{
+        dataframe::DataFrame,
+        design::{design_matrix, DesignMatrix, Predictor},
+        errors::DatasetError,
+        formula::Formula,
+        integration::integrate_with_breakpoints,
+        random::Rng,
+    },
+    distributions::helper::{beta_ln, gamma_ln},
+    regression::{
+        bayes::{
+            averaging::{average_models, InclusionMethod, ModelFit, ModelPrior},
+            helper::SamplingOptions,
+        },
+        helper::QrDecomposition,
+    },
+};
+
+pub use crate::regression::bayes::averaging::{InclusionBayesFactor, ModelBayesFactor};
+
+const TOLERANCE: f64 = 1e-10;
+
+// Prior on g in the g-prior beta ~ N(0, g sigma^2 (X'X)^-1) of the centered predictors
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CoefficientPrior {
+    // Zellner-Siow (JZS) Cauchy prior, g ~ InverseGamma(1/2, r^2 n / 2). regressionBF
+    // uses r = sqrt(2)/4 ("medium"), the "JZS" prior of BAS r = 1
+    ZellnerSiow { scale: f64 },
+    // Hyper-g prior (Liang et al., 2008), g / (1 + g) ~ Beta(1, alpha / 2 - 1), with
+    // alpha = 3 in BAS
+    HyperG { alpha: f64 },
+}
+
+#[derive(Debug, Clone)]
+pub struct BayesRegressionOptions {
+    pub prior: CoefficientPrior,
+    pub model_prior: ModelPrior,
+    pub inclusion: InclusionMethod,
+    // All subsets of the terms are fitted up to this many terms, beyond it the model
+    // space is sampled by MC3 with the given number of iterations and seed
+    pub max_enumerated: usize,
+    pub sampling: SamplingOptions,
+}
+
+impl Default for BayesRegressionOptions {
+    fn default() -> Self {
+        BayesRegressionOptions {
+            prior: CoefficientPrior::ZellnerSiow {
+                scale: std::f64::consts::SQRT_2 / 4.0,
+            },
+            model_prior: ModelPrior::BetaBinomial {
+                alpha: 1.0,
+                beta: 1.0,
+            },
+            inclusion: InclusionMethod::AllModels,
+            max_enumerated: 12,
+            sampling: SamplingOptions::default(),
+        }
+    }
+}
+
+// Model-averaged posterior of a coefficient, it is zero in models without its term
+#[derive(Debug)]
+pub struct BayesCoefficient {
+    pub name: String,
+    pub mean: f64,
+    pub sd: f64,
+    pub inclusion: f64, // posterior probability that the coefficient is not zero
+}
+
+#[derive(Debug)]
+pub struct BayesRegressionResult {
+    pub models: Vec<ModelBayesFactor>, // the null model comes first
+    pub r_squared: Vec<f64>,           // of each model
+    pub inclusion: Vec<InclusionBayesFactor>,
+    pub intercept: f64, // model-averaged posterior mean
+    pub coefficients: Vec<BayesCoefficient>,
+    pub n: usize,
+    pub sampled: bool, // models were visited by MC3 instead of enumerated
+}
+
+// Bayesian linear regression with model averaging over subsets of the predictors,
+// similar to bas.lm in BAS and regressionBF in BayesFactor. Categorical predictors
+// enter and leave the models with all their contrast columns
+pub fn bayes_linear_regression(
+    df: &DataFrame,
+    dependent_var: &str,
+    predictors: Vec<&str>,
+    options: &BayesRegressionOptions,
+) -> Result<BayesRegressionResult, DatasetError> {
+    let predictors: Vec<Predictor> = predictors.into_iter().map(Predictor::new).collect();
+    let y = df.get_column(dependent_var)?.get_values_as_f64()?;
+    let design = design_matrix(df, &predictors, false)?;
+    compare_models(&design, &y, options)
+}
+
+// Bayesian linear regression from a model formula such as "score ~ age + condition",
+// every term of the formula is a candidate for inclusion
+pub fn bayes_linear_regression_formula(
+    df: &DataFrame,
+    formula: &str,
+    options: &BayesRegressionOptions,
+) -> Result<BayesRegressionResult, DatasetError> {
+    let formula = Formula::parse(formula)?;
+    if !formula.intercept {
+        return Err(DatasetError::InvalidData(
+            "Bayesian linear regression needs a model with an intercept.".to_string(),
+        ));
+    }
+    let y = formula.response(df)?;
+    let design = formula.design_matrix(df)?;
+    compare_models(&design, &y, options)
+}
+
+// Sufficient statistics of the centered data, with the intercept integrated out under
+// a flat prior and sigma^2 under the Jeffreys prior
+struct Centered {
+    columns: Vec<Vec<f64>>,
+    means: Vec<f64>,
+    y: Vec<f64>,
+    y_mean: f64,
+    ssy: f64,
+}
+
+// A fitted model with the posterior mean and variance of its design columns
+struct Fitted {
+    log_bf: f64,
+    r_squared: f64,
+    moments: Vec<(usize, f64, f64)>, // column, mean, variance
+}
+
+fn compare_models(
+    design: &DesignMatrix,
+    y: &[f64],
+    options: &BayesRegressionOptions,
+) -> Result<BayesRegressionResult, DatasetError> {
+    if design.n() != y.len() {
+        return Err(DatasetError::InvalidData(
+            "The predictors and the response must have the same number of observations."
+                .to_string(),
+        ));
+    }
+    match options.prior {
+        CoefficientPrior::ZellnerSiow { scale } if !(scale > 0.0 && scale.is_finite()) => {
+            return Err(DatasetError::InvalidData(
+                "The prior scale must be positive and finite.".to_string(),
+            ))
+        }
+        CoefficientPrior::HyperG { alpha } if !(alpha > 2.0 && alpha.is_finite()) => {
+            return Err(DatasetError::InvalidData(
+                "The hyper-g prior needs alpha > 2.".to_string(),
+            ))
+        }
+        _ => {}
+    }
+
+    // Terms without the intercept column, each with the range of its design columns
+    let offset = design.intercept as usize;
+    let terms: Vec<(String, Vec<usize>)> = design
+        .terms
+        .iter()
+        .map(|term| {
+            let columns = (term.columns.start - offset..term.columns.end - offset).collect();
+            (term.name.clone(), columns)
+        })
+        .collect();
+    let names: Vec<String> = design.names[offset..].to_vec();
+    if terms.is_empty() {
+        return Err(DatasetError::InvalidData(
+            "At least one predictor is required.".to_string(),
+        ));
+    }
+
+    // Complete cases only
+    let rows: Vec<usize> = (0..y.len())
+        .filter(|&i| !y[i].is_nan() && design.rows[i].iter().all(|v| !v.is_nan()))
+        .collect();
+    let n = rows.len();
+    if n < names.len() + 3 {
+        return Err(DatasetError::InvalidData(format!(
+            "At least {} complete observations are needed for {} columns",
+            names.len() + 3,
+            names.len()
+        )));
+    }
+    let center = |values: Vec<f64>| {
+        let mean = values.iter().sum::<f64>() / values.len() as f64;
+        (values.iter().map(|v| v - mean).collect::<Vec<f64>>(), mean)
+    };
+    let (columns, means): (Vec<Vec<f64>>, Vec<f64>) = (offset..design.names.len())
+        .map(|j| center(rows.iter().map(|&i| design.rows[i][j]).collect()))
+        .unzip();
+    let (y, y_mean) = center(rows.iter().map(|&i| y[i]).collect());
+    let ssy: f64 = y.iter().map(|v| v * v).sum();
+    if ssy <= 0.0 {
+        return Err(DatasetError::InvalidData(
+            "The dependent variable has no variance.".to_string(),
+        ));
+    }
+    let data = Centered {
+        columns,
+        means,
+        y,
+        y_mean,
+        ssy,
+    };
+
+    let sampled = terms.len() > options.max_enumerated;
+    let models: Vec<Vec<usize>> = if sampled {
+        sample_models(&data, &terms, options)?
+    } else {
+        (0..1usize << terms.len())
+            .map(|mask| (0..terms.len()).filter(|t| mask >> t & 1 == 1).collect())
+            .collect()
+    };
+
+    let mut fits = Vec::with_capacity(models.len());
+    let mut fitted = Vec::with_capacity(models.len());
+    for model in &models {
+        let fit = fit_model(&data, &terms, model, options.prior)?;
+        fits.push(ModelFit {
+            effects: model.iter().map(|&t| terms[t].0.clone()).collect(),
+            log_bf: fit.log_bf,
+            error: 0.0,
+            estimates: fit
+                .moments
+                .iter()
+                .map(|&(j, mean, _)| (names[j].clone(), mean))
+                .collect(),
+        });
+        fitted.push(fit);
+    }
+
+    let term_names: Vec<String> = terms.iter().map(|(name, _)| name.clone()).collect();
+    let averaging = average_models(fits, &term_names, options.model_prior, options.inclusion)?;
+    let probabilities: Vec<f64> = averaging
+        .models
+        .iter()
+        .map(|m| m.posterior_probability)
+        .collect();
+
+    // Second moments E[b^2] = sum over models of p (Var + mean^2)
+    let mut second = vec![0.0; names.len()];
+    let mut inclusion = vec![0.0; names.len()];
+    let mut intercept = 0.0;
+    for (fit, probability) in fitted.iter().zip(&probabilities) {
+        let mut model_intercept = data.y_mean;
+        for &(j, mean, variance) in &fit.moments {
+            second[j] += probability * (variance + mean * mean);
+            inclusion[j] += probability;
+            model_intercept -= data.means[j] * mean;
+        }
+        intercept += probability * model_intercept;
+    }
+    let coefficients = names
+        .iter()
+        .enumerate()
+        .map(|(j, name)| {
+            let mean = averaging
+                .estimates
+                .iter()
+                .find(|e| e.name == *name)
+                .map_or(0.0, |e| e.mean);
+            BayesCoefficient {
+                name: name.clone(),
+                mean,
+                sd: (second[j] - mean * mean).max(0.0).sqrt(),
+                inclusion: inclusion[j],
+            }
+        })
+        .collect();
+
+    Ok(BayesRegressionResult {
+        models: averaging.models,
+        r_squared: fitted.iter().map(|fit| fit.r_squared).collect(),
+        inclusion: averaging.inclusion,
+        intercept,
+        coefficients,
+        n,
+        sampled,
+    })
+}
+
+// Given g the coefficients are the least-squares estimates shrunk by s = g / (1 + g),
+// with E[sigma^2 | g] = SSY (1 - s R^2) / (n - 3). Aliased columns are left out
+fn fit_model(
+    data: &Centered,
+    terms: &[(String, Vec<usize>)],
+    model: &[usize],
+    prior: CoefficientPrior,
+) -> Result<Fitted, DatasetError> {
+    let columns: Vec<usize> = model
+        .iter()
+        .flat_map(|&t| terms[t].1.iter().copied())
+        .collect();
+    if columns.is_empty() {
+        return Ok(Fitted {
+            log_bf: 0.0,
+            r_squared: 0.0,
+            moments: Vec::new(),
+        });
+    }
+
+    let rows: Vec<Vec<f64>> = (0..data.y.len())
+        .map(|i| columns.iter().map(|&j| data.columns[j][i]).collect())
+        .collect();
+    let qr = QrDecomposition::new(&rows)?;
+    let explained: f64 = qr.qty(&data.y)[..qr.rank].iter().map(|v| v * v).sum();
+    let r_squared = (explained / data.ssy).min(1.0);
+    let n = data.y.len() as f64;
+    let shrinkage = g_integral(r_squared, n, qr.rank as f64, prior);
+
+    let estimates = qr.coefficients(&data.y);
+    let unscaled = qr.unscaled_covariance();
+    let scale = (shrinkage.mean - r_squared * shrinkage.second) * data.ssy / (n - 3.0);
+    let spread = shrinkage.second - shrinkage.mean.powi(2);
+    let moments = (0..qr.rank)
+        .map(|k| {
+            let b = estimates[k];
+            let variance = scale * unscaled[k][k] + spread * b * b;
+            (columns[qr.pivot[k]], shrinkage.mean * b, variance)
+        })
+        .collect();
+
+    Ok(Fitted {
+        log_bf: shrinkage.log_bf,
+        r_squared,
+        moments,
+    })
+}
+
+// Log Bayes factor against the intercept-only model with the posterior moments of the
+// shrinkage factor s = g / (1 + g)
+struct Shrinkage {
+    log_bf: f64,
+    mean: f64,
+    second: f64,
+}
+
+// Integrates BF(g) = (1 + g)^((n - 1 - p)/2) (1 + g (1 - R^2))^(-(n - 1)/2) against the
+// prior on g, over tau = log g
+fn g_integral(r_squared: f64, n: f64, p: f64, prior: CoefficientPrior) -> Shrinkage {
+    let log_integrand = |tau: f64| {
+        let g = tau.exp();
+        let log_bf =
+            (n - 1.0 - p) / 2.0 * g.ln_1p() - (n - 1.0) / 2.0 * (g * (1.0 - r_squared)).ln_1p();
+        let log_prior = match prior {
+            CoefficientPrior::ZellnerSiow { scale } => {
+                let rate = scale * scale * n / 2.0;
+                0.5 * rate.ln() - gamma_ln(0.5) - 1.5 * tau - rate / g
+            }
+            CoefficientPrior::HyperG { alpha } => {
+                ((alpha - 2.0) / 2.0).ln() - alpha / 2.0 * g.ln_1p()
+            }
+        };
+        let value = log_bf + log_prior + tau;
+        if value.is_nan() {
+            f64::NEG_INFINITY
+        } else {
+            value
+        }
+    };
+
+    // Peak of the integrand on a coarse grid, then quadrature around it
+    let peak = (-120..=240)
+        .map(|k| k as f64 / 4.0)
+        .max_by(|a, b| log_integrand(*a).total_cmp(&log_integrand(*b)))
+        .unwrap();
+    let log_max = log_integrand(peak);
+    let breakpoints: Vec<f64> = (-8..=8).map(|k| peak + k as f64).collect();
+    let weighted = |f: &dyn Fn(f64) -> f64| {
+        integrate_with_breakpoints(
+            |tau| f(tau) * (log_integrand(tau) - log_max).exp(),
+            f64::NEG_INFINITY,
+            f64::INFINITY,
+            &breakpoints,
+            TOLERANCE,
+        )
+    };
+
+    let shrink = |tau: f64| 1.0 / (1.0 + (-tau).exp());
+    let integral = weighted(&|_| 1.0);
+    Shrinkage {
+        log_bf: log_max + integral.ln(),
+        mean: weighted(&shrink) / integral,
+        second: weighted(&|tau| shrink(tau).powi(2)) / integral,
+    }
+}
+
+// Markov chain Monte Carlo model composition (MC3): starting from the null model, each
+// step proposes to add or drop one term and accepts by the posterior odds. Returns the
+// distinct visited models, whose exact Bayes factors are then renormalized
+fn sample_models(
+    data: &Centered,
+    terms: &[(String, Vec<usize>)],
+    options: &BayesRegressionOptions,
+) -> Result<Vec<Vec<usize>>, DatasetError> {
+    let k = terms.len() as f64;
+    let log_prior = |size: usize| match options.model_prior {
+        ModelPrior::Uniform => 0.0,
+        ModelPrior::BetaBinomial { alpha, beta } => {
+            beta_ln(size as f64 + alpha, k - size as f64 + beta) - beta_ln(alpha, beta)
+        }
+    };
+
+    let mut rng = Rng::new(options.sampling.seed);
+    let mut visited: HashMap<Vec<bool>, f64> = HashMap::new();
+    let mut order: Vec<Vec<bool>> = Vec::new();
+    let mut log_posterior = |state: &Vec<bool>| -> Result<f64, DatasetError> {
+        if let Some(&value) = visited.get(state) {
+            return Ok(value);
+        }
+        let model: Vec<usize> = (0..state.len()).filter(|&t| state[t]).collect();
+        let value = fit_model(data, terms, &model, options.prior)?.log_bf + log_prior(model.len());
+        visited.insert(state.clone(), value);
+        order.push(state.clone());
+        Ok(value)
+    };
+
+    let mut state = vec![false; terms.len()];
+    let mut current = log_posterior(&state)?;
+    for _ in 0..options.sampling.iterations {
+        let flip = ((rng.uniform() * k) as usize).min(terms.len() - 1);
+        let mut proposal = state.clone();
+        proposal[flip] = !proposal[flip];
+        let candidate = log_posterior(&proposal)?;
+        if rng.uniform().ln() < candidate - current {
+            state = proposal;
+            current = candidate;
+        }
+    }
+
+    Ok(order
+        .iter()
+        .map(|state| (0..state.len()).filter(|&t| state[t]).collect())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{
+        fixtures::{mtcars, sleep},
+        helper::roughly_equals,
+    };
+
+    #[test]
+    fn test_single_predictor() {
+        // With a 0/1 group column and r = sqrt(2)/4 this is the JZS t-test with
+        // r = sqrt(2)/2, ttestBF(formula = extra ~ group, data = sleep) gives 1.265925
+        let mut df = sleep();
+        df.add_numerical_column("drug", (0..20).map(|i| (i >= 10) as u8 as f64).collect());
+        let options = BayesRegressionOptions {
+            model_prior: ModelPrior::Uniform,
+            ..Default::default()
+        };
+        let result = bayes_linear_regression(&df, "extra", vec!["drug"], &options).unwrap();
+        assert!(roughly_equals(result.models[1].bf10, 1.265925, 1e-5));
+        assert!(roughly_equals(
+            result.models[1].posterior_probability,
+            1.265925 / 2.265925,
+            1e-5
+        ));
+    }
+
+    #[test]
+    fn test_model_averaging() {
+        let df = mtcars();
+        let options = BayesRegressionOptions::default();
+        let result = bayes_linear_regression(&df, "mpg", vec!["wt", "hp"], &options).unwrap();
+        assert!(!result.sampled);
+        assert_eq!(result.models[3].effects, vec!["wt", "hp"]);
+        assert!(roughly_equals(result.models[3].bf10.ln(), 20.485703, 1e-5));
+        assert!(roughly_equals(result.models[1].bf10.ln(), 17.636689, 1e-5));
+        assert!(roughly_equals(result.r_squared[3], 0.8267855, 1e-7));
+        assert!(roughly_equals(
+            result.models[3].posterior_probability,
+            0.9718298,
+            1e-6
+        ));
+
+        let wt = &result.coefficients[0];
+        assert!(roughly_equals(wt.inclusion, 0.9999649, 1e-6));
+        assert!(roughly_equals(wt.mean, -3.825378, 1e-5));
+        assert!(roughly_equals(wt.sd, 0.7038946, 1e-5));
+        assert!(roughly_equals(result.coefficients[1].sd, 0.01062004, 1e-7));
+        assert!(roughly_equals(result.intercept, 36.81940, 1e-4));
+
+        // Hyper-g, BF of the full model has the closed form of Liang et al. (2008)
+        let hyper = BayesRegressionOptions {
+            prior: CoefficientPrior::HyperG { alpha: 3.0 },
+            ..Default::default()
+        };
+        let result = bayes_linear_regression_formula(&df, "mpg ~ wt + hp", &hyper).unwrap();
+        assert!(roughly_equals(result.models[3].bf10.ln(), 20.031775, 1e-5));
+
+        // A categorical predictor enters with all of its dummy columns
+        let df = crate::core::reader::import_csv("samples/data3.csv").unwrap();
+        let result = bayes_linear_regression(
+            &df,
+            "score",
+            vec!["condition", "age", "happiness"],
+            &BayesRegressionOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(result.models.len(), 8);
+        assert_eq!(result.coefficients.len(), 4);
+        assert_eq!(
+            result.coefficients[0].inclusion,
+            result.inclusion[0].posterior_inclusion
+        );
+    }
+
+    #[test]
+    fn test_sampled_models() {
+        // MC3 visits all four models, so the renormalized result is the enumerated one
+        let df = mtcars();
+        let sampled = BayesRegressionOptions {
+            max_enumerated: 1,
+            ..Default::default()
+        };
+        let result = bayes_linear_regression(&df, "mpg", vec!["wt", "hp"], &sampled).unwrap();
+        assert!(result.sampled);
+        assert_eq!(result.models.len(), 4);
+        assert!(result.models[0].effects.is_empty());
+        assert!(roughly_equals(
+            result.inclusion[1].posterior_inclusion,
+            0.9718649,
+            1e-6
+        ));
+    }
+}
@@ -0,0 +1,592 @@
+use crate::{
+    core::{
+        dataframe::DataFrame,
+        design::{design_matrix, Predictor},
+        errors::DatasetError,
+        formula::{Expression, Formula},
+        helper::{mean, rank, sample_variance},
+        random::Rng,
+    },
+    distributions::normal,
+    regression::{
+        bayes::helper::SamplingOptions,
+        classical::logistic::{binary_outcome, inverse, maximize},
+        helper::cholesky,
+    },
+};
+
+// Target acceptance rate of the random walk Metropolis sampler during warmup
+const TARGET_ACCEPTANCE: f64 = 0.234;
+const ADAPTATION_WINDOW: usize = 50;
+
+// Prior centered at zero on a coefficient
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CoefficientPrior {
+    Normal { scale: f64 },
+    Cauchy { scale: f64 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FitMethod {
+    // Normal approximation at the posterior mode
+    Laplace,
+    // Random walk Metropolis with the Laplace covariance as proposal, several chains
+    Metropolis,
+}
+
+#[derive(Debug, Clone)]
+pub struct BayesLogisticOptions {
+    pub prior: CoefficientPrior,
+    pub intercept_prior: CoefficientPrior,
+    // Divide the scale of the coefficient prior by the standard deviation of the
+    // predictor, as the autoscaled priors of rstanarm
+    pub autoscale: bool,
+    pub method: FitMethod,
+    pub chains: usize,
+    pub warmup: usize,             // iterations per chain that tune the proposal
+    pub sampling: SamplingOptions, // draws kept per chain after warmup and the seed
+}
+
+impl Default for BayesLogisticOptions {
+    fn default() -> Self {
+        BayesLogisticOptions {
+            prior: CoefficientPrior::Cauchy { scale: 2.5 },
+            intercept_prior: CoefficientPrior::Normal { scale: 10.0 },
+            autoscale: true,
+            method: FitMethod::Laplace,
+            chains: 4,
+            warmup: 1000,
+            sampling: SamplingOptions {
+                iterations: 2000,
+                seed: 1,
+            },
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct BayesLogisticCoefficient {
+    pub name: String,
+    pub mean: f64,
+    pub sd: f64,
+    pub ci_lower: f64, // 95% credible interval
+    pub ci_upper: f64,
+    pub r_hat: Option<f64>, // split R-hat and bulk effective sample size of the draws
+    pub ess: Option<f64>,
+}
+
+#[derive(Debug)]
+pub struct BayesLogisticResult {
+    pub coefficients: Vec<BayesLogisticCoefficient>,
+    pub event: Option<String>, // level coded 1 of a categorical outcome
+    pub method: FitMethod,
+    pub mode: Vec<f64>, // posterior mode of the coefficients
+    pub converged: bool,
+    pub acceptance_rate: Option<f64>,
+    pub n: usize,
+}
+
+// Bayesian logistic regression with an intercept, the outcome is coded as in
+// logistic_regression. By default coefficients get Cauchy(0, 2.5 / sd(x)) priors and the
+// intercept a Normal(0, 10) prior, so the posterior stays proper under separation
+pub fn bayes_logistic_regression(
+    df: &DataFrame,
+    dependent_var: &str,
+    predictors: Vec<&str>,
+    options: &BayesLogisticOptions,
+) -> Result<BayesLogisticResult, DatasetError> {
+    let predictors: Vec<Predictor> = predictors.into_iter().map(Predictor::new).collect();
+    let (y, event) = binary_outcome(df, dependent_var)?;
+    let design = design_matrix(df, &predictors, true)?;
+    let mut result = bayes_logistic(&design.rows, &y, &design.names, true, options)?;
+    result.event = event;
+    Ok(result)
+}
+
+pub fn bayes_logistic_regression_formula(
+    df: &DataFrame,
+    formula: &str,
+    options: &BayesLogisticOptions,
+) -> Result<BayesLogisticResult, DatasetError> {
+    let formula = Formula::parse(formula)?;
+    let (y, event) = match &formula.response {
+        Some(Expression::Variable(name)) => binary_outcome(df, name)?,
+        _ => (formula.response(df)?, None),
+    };
+    let design = formula.design_matrix(df)?;
+    let mut result = bayes_logistic(&design.rows, &y, &design.names, formula.intercept, options)?;
+    result.event = event;
+    Ok(result)
+}
+
+// Posterior of the coefficients on a design matrix given by rows. With an intercept it
+// must be the first column and gets the intercept prior
+pub fn bayes_logistic(
+    design: &[Vec<f64>],
+    y: &[f64],
+    names: &[String],
+    intercept: bool,
+    options: &BayesLogisticOptions,
+) -> Result<BayesLogisticResult, DatasetError> {
+    let n = y.len();
+    let p = names.len();
+    if design.len() != n || design.iter().any(|row| row.len() != p) {
+        return Err(DatasetError::InvalidData(
+            "The design matrix does not match the observations.".to_string(),
+        ));
+    }
+    if y.iter().any(|&v| v != 0.0 && v != 1.0) {
+        return Err(DatasetError::InvalidData(
+            "The outcome must be coded 0/1.".to_string(),
+        ));
+    }
+    if p == 0 || n < 2 {
+        return Err(DatasetError::InvalidData(
+            "At least one coefficient and two observations are required.".to_string(),
+        ));
+    }
+
+    let priors: Vec<CoefficientPrior> = (0..p)
+        .map(|j| {
+            if intercept && j == 0 {
+                return options.intercept_prior;
+            }
+            let column: Vec<f64> = design.iter().map(|row| row[j]).collect();
+            let sd = sample_variance(&column).sqrt();
+            let divisor = if options.autoscale && sd > 0.0 {
+                sd
+            } else {
+                1.0
+            };
+            match options.prior {
+                CoefficientPrior::Normal { scale } => CoefficientPrior::Normal {
+                    scale: scale / divisor,
+                },
+                CoefficientPrior::Cauchy { scale } => CoefficientPrior::Cauchy {
+                    scale: scale / divisor,
+                },
+            }
+        })
+        .collect();
+    if priors.iter().any(|prior| {
+        let (CoefficientPrior::Normal { scale } | CoefficientPrior::Cauchy { scale }) = *prior;
+        !(scale > 0.0 && scale.is_finite())
+    }) {
+        return Err(DatasetError::InvalidData(
+            "Prior scales must be positive and finite.".to_string(),
+        ));
+    }
+
+    let posterior = Posterior {
+        design,
+        y,
+        priors: &priors,
+    };
+    let optimum = maximize(vec![0.0; p], |beta| Some(posterior.derivatives(beta, true)))?;
+    let mode = optimum.parameters;
+    // The exact curvature of a Cauchy prior may be negative, the Newton steps used a
+    // bounded one that still gives a valid covariance
+    let (_, _, information) = posterior.derivatives(&mode, false);
+    let covariance = inverse(&information).unwrap_or(optimum.covariance);
+
+    let coefficients: Vec<BayesLogisticCoefficient>;
+    let mut acceptance_rate = None;
+    match options.method {
+        FitMethod::Laplace => {
+            let z = normal::quantile(0.975);
+            coefficients = (0..p)
+                .map(|j| {
+                    let sd = covariance[j][j].sqrt();
+                    BayesLogisticCoefficient {
+                        name: names[j].clone(),
+                        mean: mode[j],
+                        sd,
+                        ci_lower: mode[j] - z * sd,
+                        ci_upper: mode[j] + z * sd,
+                        r_hat: None,
+                        ess: None,
+                    }
+                })
+                .collect();
+        }
+        FitMethod::Metropolis => {
+            let (draws, rate) = posterior.sample(&mode, &covariance, options)?;
+            acceptance_rate = Some(rate);
+            coefficients = (0..p)
+                .map(|j| {
+                    let chains: Vec<Vec<f64>> = draws
+                        .iter()
+                        .map(|chain| chain.iter().map(|draw| draw[j]).collect())
+                        .collect();
+                    let mut pooled: Vec<f64> = chains.concat();
+                    pooled.sort_by(|a, b| a.total_cmp(b));
+                    BayesLogisticCoefficient {
+                        name: names[j].clone(),
+                        mean: mean(&pooled),
+                        sd: sample_variance(&pooled).sqrt(),
+                        ci_lower: quantile(&pooled, 0.025),
+                        ci_upper: quantile(&pooled, 0.975),
+                        r_hat: Some(r_hat(&chains)),
+                        ess: Some(bulk_effective_sample_size(&chains)),
+                    }
+                })
+                .collect();
+        }
+    }
+
+    Ok(BayesLogisticResult {
+        coefficients,
+        event: None,
+        method: options.method,
+        mode,
+        converged: optimum.converged,
+        acceptance_rate,
+        n,
+    })
+}
+
+struct Posterior<'a> {
+    design: &'a [Vec<f64>],
+    y: &'a [f64],
+    priors: &'a [CoefficientPrior],
+}
+
+impl Posterior<'_> {
+    // Unnormalized log posterior density
+    fn log_density(&self, beta: &[f64]) -> f64 {
+        let mut value = 0.0;
+        for (row, &y) in self.design.iter().zip(self.y) {
+            let eta: f64 = row.iter().zip(beta).map(|(x, b)| x * b).sum();
+            // log(1 + e^eta) without overflow
+            value += y * eta - (eta.max(0.0) + (-eta.abs()).exp().ln_1p());
+        }
+        for (prior, &b) in self.priors.iter().zip(beta) {
+            value += match *prior {
+                CoefficientPrior::Normal { scale } => -0.5 * (b / scale).powi(2),
+                CoefficientPrior::Cauchy { scale } => -(b / scale).powi(2).ln_1p(),
+            };
+        }
+        value
+    }
+
+    // Log posterior, gradient and negative Hessian. With `bounded` the negative
+    // curvature of a Cauchy prior in its tails is left out
+    fn derivatives(&self, beta: &[f64], bounded: bool) -> (f64, Vec<f64>, Vec<Vec<f64>>) {
+        let p = beta.len();
+        let mut gradient = vec![0.0; p];
+        let mut information = vec![vec![0.0; p]; p];
+        for (row, &y) in self.design.iter().zip(self.y) {
+            let eta: f64 = row.iter().zip(beta).map(|(x, b)| x * b).sum();
+            let probability = 1.0 / (1.0 + (-eta).exp());
+            let weight = probability * (1.0 - probability);
+            for j in 0..p {
+                gradient[j] += (y - probability) * row[j];
+                for k in 0..p {
+                    information[j][k] += weight * row[j] * row[k];
+                }
+            }
+        }
+        for (j, (prior, &b)) in self.priors.iter().zip(beta).enumerate() {
+            let (slope, curvature) = match *prior {
+                CoefficientPrior::Normal { scale } => (-b / scale.powi(2), 1.0 / scale.powi(2)),
+                CoefficientPrior::Cauchy { scale } => {
+                    let s2 = scale * scale;
+                    let curvature = 2.0 * (s2 - b * b) / (s2 + b * b).powi(2);
+                    (
+                        -2.0 * b / (s2 + b * b),
+                        if bounded {
+                            curvature.max(0.0)
+                        } else {
+                            curvature
+                        },
+                    )
+                }
+            };
+            gradient[j] += slope;
+            information[j][j] += curvature;
+        }
+        (self.log_density(beta), gradient, information)
+    }
+
+    // Chains of random walk Metropolis draws with multivariate normal proposals shaped
+    // by the Laplace covariance. Chains start overdispersed around the mode and the
+    // proposal scale is tuned during warmup. Returns the draws and acceptance rate
+    #[allow(clippy::type_complexity)]
+    fn sample(
+        &self,
+        mode: &[f64],
+        covariance: &[Vec<f64>],
+        options: &BayesLogisticOptions,
+    ) -> Result<(Vec<Vec<Vec<f64>>>, f64), DatasetError> {
+        if options.chains < 2 || options.sampling.iterations < 4 {
+            return Err(DatasetError::InvalidData(
+                "Sampling needs at least two chains and four iterations.".to_string(),
+            ));
+        }
+        let d = mode.len();
+        let lower = cholesky(covariance)?;
+        let mut rng = Rng::new(options.sampling.seed);
+        let correlated = |rng: &mut Rng, factor: f64| -> Vec<f64> {
+            let z: Vec<f64> = (0..d).map(|_| rng.normal()).collect();
+            (0..d)
+                .map(|i| factor * (0..=i).map(|k| lower[i][k] * z[k]).sum::<f64>())
+                .collect()
+        };
+
+        let mut chains = Vec::with_capacity(options.chains);
+        let mut accepted = 0;
+        for _ in 0..options.chains {
+            let offset = correlated(&mut rng, 2.0);
+            let mut current: Vec<f64> = mode.iter().zip(&offset).map(|(m, o)| m + o).collect();
+            let mut density = self.log_density(&current);
+            let mut factor = 2.38 / (d as f64).sqrt();
+            let mut window = 0;
+            let mut draws = Vec::with_capacity(options.sampling.iterations);
+
+            for iteration in 0..options.warmup + options.sampling.iterations {
+                let step = correlated(&mut rng, factor);
+                let proposal: Vec<f64> = current.iter().zip(&step).map(|(c, s)| c + s).collect();
+                let proposal_density = self.log_density(&proposal);
+                let accept = rng.uniform().ln() < proposal_density - density;
+                if accept {
+                    current = proposal;
+                    density = proposal_density;
+                }
+
+                if iteration < options.warmup {
+                    window += accept as usize;
+                    if (iteration + 1) % ADAPTATION_WINDOW == 0 {
+                        let rate = window as f64 / ADAPTATION_WINDOW as f64;
+                        factor *= (rate - TARGET_ACCEPTANCE).exp();
+                        window = 0;
+                    }
+                } else {
+                    accepted += accept as usize;
+                    draws.push(current.clone());
+                }
+            }
+            chains.push(draws);
+        }
+
+        let total = (options.chains * options.sampling.iterations) as f64;
+        Ok((chains, accepted as f64 / total))
+    }
+}
+
+// Sample quantile of sorted values with linear interpolation (type 7 in R)
+fn quantile(sorted: &[f64], p: f64) -> f64 {
+    let position = p * (sorted.len() - 1) as f64;
+    let below = position.floor() as usize;
+    let above = (below + 1).min(sorted.len() - 1);
+    sorted[below] + (position - below as f64) * (sorted[above] - sorted[below])
+}
+
+// Every chain split in halves, so trends within a chain also raise R-hat
+fn split_chains(chains: &[Vec<f64>]) -> Vec<&[f64]> {
+    chains
+        .iter()
+        .flat_map(|chain| {
+            let half = chain.len() / 2;
+            [&chain[..half], &chain[chain.len() - half..]]
+        })
+        .collect()
+}
+
+// Within-chain variance W and the pooled estimate of the posterior variance
+fn variances(chains: &[&[f64]]) -> (f64, f64) {
+    let n = chains[0].len() as f64;
+    let means: Vec<f64> = chains.iter().map(|chain| mean(chain)).collect();
+    let within = chains
+        .iter()
+        .map(|chain| sample_variance(chain))
+        .sum::<f64>()
+        / chains.len() as f64;
+    let between = n * sample_variance(&means);
+    (within, (n - 1.0) / n * within + between / n)
+}
+
+// Potential scale reduction factor of split chains (Gelman et al., 2013)
+pub fn r_hat(chains: &[Vec<f64>]) -> f64 {
+    let (within, pooled) = variances(&split_chains(chains));
+    (pooled / within).sqrt()
+}
+
+// Effective sample size of split chains from the combined autocorrelations, summed
+// over Geyer's initial positive sequence as in Stan
+pub fn effective_sample_size(chains: &[Vec<f64>]) -> f64 {
+    let chains = split_chains(chains);
+    let m = chains.len() as f64;
+    let n = chains[0].len();
+    let (within, pooled) = variances(&chains);
+    if pooled <= 0.0 {
+        return f64::NAN;
+    }
+
+    let means: Vec<f64> = chains.iter().map(|chain| mean(chain)).collect();
+    let autocorrelation = |lag: usize| {
+        let autocovariance = chains
+            .iter()
+            .zip(&means)
+            .map(|(chain, mean)| {
+                (0..n - lag)
+                    .map(|i| (chain[i] - mean) * (chain[i + lag] - mean))
+                    .sum::<f64>()
+                    / n as f64
+            })
+            .sum::<f64>()
+            / m;
+        1.0 - (within - autocovariance) / pooled
+    };
+
+    let mut tau = -1.0;
+    let mut lag = 0;
+    while lag + 1 < n {
+        let pair = autocorrelation(lag) + autocorrelation(lag + 1);
+        if pair <= 0.0 {
+            break;
+        }
+        tau += 2.0 * pair;
+        lag += 2;
+    }
+    m * n as f64 / tau.max(1.0 / (m * n as f64).log10())
+}
+
+// Bulk effective sample size (Vehtari et al., 2021), that of the split chains after
+// replacing the pooled draws by the normal scores of their ranks, so heavy tails do
+// not distort it
+pub fn bulk_effective_sample_size(chains: &[Vec<f64>]) -> f64 {
+    let pooled = chains.concat();
+    let total = pooled.len() as f64;
+    let mut scores = rank(&pooled)
+        .into_iter()
+        .map(|r| normal::quantile((r - 0.375) / (total + 0.25)));
+    let normalized: Vec<Vec<f64>> = chains
+        .iter()
+        .map(|chain| scores.by_ref().take(chain.len()).collect())
+        .collect();
+    effective_sample_size(&normalized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{fixtures::mtcars, helper::roughly_equals};
+
+    // With a flat prior the logit of the event probability of an intercept-only model
+    // is the logit of a Beta(k, n - k) variable, k = 13 cars with a manual transmission
+    // out of 32, with mean digamma(k) - digamma(n - k) and variance
+    // trigamma(k) + trigamma(n - k)
+    fn intercept_only(method: FitMethod) -> BayesLogisticResult {
+        let y = binary_outcome(&mtcars(), "am").unwrap().0;
+        let design = vec![vec![1.0]; y.len()];
+        let options = BayesLogisticOptions {
+            intercept_prior: CoefficientPrior::Normal { scale: 1e6 },
+            method,
+            sampling: SamplingOptions {
+                iterations: 5000,
+                seed: 7,
+            },
+            ..Default::default()
+        };
+        bayes_logistic(&design, &y, &["(Intercept)".to_string()], true, &options).unwrap()
+    }
+
+    #[test]
+    fn test_laplace() {
+        // Flat priors give the maximum likelihood fit of glm(am ~ wt + hp)
+        let flat = BayesLogisticOptions {
+            prior: CoefficientPrior::Normal { scale: 1e6 },
+            intercept_prior: CoefficientPrior::Normal { scale: 1e6 },
+            autoscale: false,
+            ..Default::default()
+        };
+        let mle = bayes_logistic_regression(&mtcars(), "am", vec!["wt", "hp"], &flat).unwrap();
+        assert!(mle.converged);
+        assert!(roughly_equals(mle.coefficients[0].mean, 18.86630, 1e-4));
+        assert!(roughly_equals(mle.coefficients[1].sd, 3.06868, 1e-4));
+
+        // The mode log(13 / 19) and curvature n p (1 - p) of the intercept-only model
+        let result = intercept_only(FitMethod::Laplace);
+        let intercept = &result.coefficients[0];
+        assert!(roughly_equals(intercept.mean, (13.0f64 / 19.0).ln(), 1e-6));
+        assert!(roughly_equals(
+            intercept.sd,
+            (32.0f64 / (13.0 * 19.0)).sqrt(),
+            1e-6
+        ));
+        assert!(intercept.r_hat.is_none());
+
+        // Default autoscaled Cauchy priors shrink the coefficients and their uncertainty
+        let options = BayesLogisticOptions::default();
+        let result =
+            bayes_logistic_regression(&mtcars(), "am", vec!["wt", "hp"], &options).unwrap();
+        for (shrunk, coefficient) in result.coefficients.iter().zip(&mle.coefficients) {
+            assert!(shrunk.mean.abs() < coefficient.mean.abs());
+            assert!(shrunk.sd < coefficient.sd);
+        }
+    }
+
+    #[test]
+    fn test_metropolis() {
+        // Posterior mean -0.3918974 and sd 0.3660578, skewed away from the mode
+        let result = intercept_only(FitMethod::Metropolis);
+        let intercept = &result.coefficients[0];
+        let ess = intercept.ess.unwrap();
+        assert!(ess > 2000.0);
+        // Within four Monte Carlo standard errors
+        let se = 0.3660578 / ess.sqrt();
+        assert!(roughly_equals(intercept.mean, -0.3918974, 4.0 * se));
+        assert!(roughly_equals(intercept.sd, 0.3660578, 0.02));
+        assert!(intercept.r_hat.unwrap() < 1.01);
+
+        let options = BayesLogisticOptions {
+            method: FitMethod::Metropolis,
+            sampling: SamplingOptions {
+                iterations: 5000,
+                seed: 7,
+            },
+            ..Default::default()
+        };
+        let result =
+            bayes_logistic_regression(&mtcars(), "am", vec!["wt", "hp"], &options).unwrap();
+        for coefficient in &result.coefficients {
+            assert!(coefficient.ci_lower < coefficient.mean);
+            assert!(coefficient.mean < coefficient.ci_upper);
+            assert!(coefficient.r_hat.unwrap() < 1.02);
+            assert!(coefficient.ess.unwrap() > 500.0);
+        }
+        let rate = result.acceptance_rate.unwrap();
+        assert!((0.1..0.5).contains(&rate));
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let mut rng = Rng::new(3);
+        let mut chains: Vec<Vec<f64>> = (0..4)
+            .map(|_| (0..1000).map(|_| rng.normal()).collect())
+            .collect();
+        assert!(roughly_equals(r_hat(&chains), 1.0, 0.01));
+        assert!(roughly_equals(
+            effective_sample_size(&chains),
+            4000.0,
+            400.0
+        ));
+
+        // The bulk effective sample size only depends on the ranks of the draws
+        let bulk = bulk_effective_sample_size(&chains);
+        assert!(roughly_equals(bulk, 4000.0, 400.0));
+        let cubed: Vec<Vec<f64>> = chains
+            .iter()
+            .map(|chain| chain.iter().map(|v| v.powi(3)).collect())
+            .collect();
+        assert!(roughly_equals(
+            bulk_effective_sample_size(&cubed),
+            bulk,
+            1e-9
+        ));
+
+        // A chain stuck elsewhere
+        chains[0].iter_mut().for_each(|v| *v += 3.0);
+        assert!(r_hat(&chains) > 1.5);
+    }
+}
@@ -1,3 +1,5 @@
+pub mod averaging;
 pub mod correlation;
+pub mod helper;
 pub mod linear;
 pub mod logistic;
@@ -0,0 +1,1053 @@
+use crate::{
+    core::{
+        dataframe::DataFrame,
+        design::factor_levels,
+        errors::DatasetError,
+        helper::{pearson, rank, tie_sizes},
+        hypothesis::{adjust_p_values, Alternative, PAdjustment},
+    },
+    distributions::{normal, tdist},
+    regression::helper::{cholesky, cholesky_solve, QrDecomposition},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CorrelationMethod {
+    Pearson,
+    Spearman,
+    Kendall, // tau-b
+}
+
+#[derive(Debug)]
+pub struct CorrelationResult {
+    pub method: CorrelationMethod,
+    pub estimate: f64,
+    pub statistic: f64, // t with n - 2 degrees of freedom, z for Kendall's tau
+    pub p_value: f64,
+    pub ci_lower: f64, // 95% confidence interval, one-sided for directional alternatives
+    pub ci_upper: f64,
+    pub n: usize, // complete pairs
+    pub exact: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MissingData {
+    Pairwise, // every pair uses all rows where both variables are observed
+    Listwise, // only rows where all variables are observed
+}
+
+#[derive(Debug)]
+pub struct CorrelationPair {
+    pub first: String,
+    pub second: String,
+    pub result: CorrelationResult,
+    pub adjusted_p_value: f64,
+}
+
+#[derive(Debug)]
+pub struct CorrelationMatrix {
+    pub variables: Vec<String>,
+    pub method: CorrelationMethod,
+    pub adjustment: PAdjustment,
+    pub pairs: Vec<CorrelationPair>, // lower triangle, row by row
+}
+
+impl CorrelationMatrix {
+    // Pair of the i-th and j-th variable in either order, None on the diagonal
+    pub fn pair(&self, i: usize, j: usize) -> Option<&CorrelationPair> {
+        let (row, column) = if i > j { (i, j) } else { (j, i) };
+        (row != column).then(|| &self.pairs[row * (row - 1) / 2 + column])
+    }
+
+    // Rows of the lower triangle as in an APA correlation table: row i holds
+    // the correlations of variable i with the variables before it
+    pub fn lower_triangle(&self) -> Vec<&[CorrelationPair]> {
+        (0..self.variables.len())
+            .map(|row| &self.pairs[row * row.saturating_sub(1) / 2..row * (row + 1) / 2])
+            .collect()
+    }
+
+    pub fn estimates(&self) -> Vec<Vec<f64>> {
+        self.square(|pair| pair.result.estimate, 1.0)
+    }
+
+    pub fn p_values(&self) -> Vec<Vec<f64>> {
+        self.square(|pair| pair.adjusted_p_value, 0.0)
+    }
+
+    fn square(&self, value: impl Fn(&CorrelationPair) -> f64, diagonal: f64) -> Vec<Vec<f64>> {
+        let k = self.variables.len();
+        (0..k)
+            .map(|i| {
+                (0..k)
+                    .map(|j| self.pair(i, j).map_or(diagonal, &value))
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug)]
+pub struct PartialCorrelationResult {
+    pub estimate: f64,
+    pub statistic: f64, // t with n - 2 - covariates degrees of freedom
+    pub df: f64,
+    pub p_value: f64,
+    pub n: usize, // complete cases
+    pub covariates: usize,
+}
+
+#[derive(Debug)]
+pub struct PartialCorrelationMatrix {
+    pub variables: Vec<String>,
+    pub estimates: Vec<Vec<f64>>, // each pair controlling for all other variables
+    pub statistics: Vec<Vec<f64>>,
+    pub p_values: Vec<Vec<f64>>,
+    pub df: f64,
+    pub n: usize,
+}
+
+#[derive(Debug)]
+pub struct CorrelationComparison {
+    pub first: f64, // the two correlations being compared
+    pub second: f64,
+    pub statistic: f64, // z, or t for Williams' test
+    pub df: Option<f64>,
+    pub p_value: f64,
+}
+
+#[derive(Debug)]
+pub struct OverlappingComparison {
+    pub between: f64, // correlation of the two non-shared variables
+    pub n: usize,
+    pub williams: CorrelationComparison,
+    pub steiger: CorrelationComparison,
+}
+
+// Kendall's tau uses its exact null distribution below this size when there are no ties
+const EXACT_LIMIT: usize = 50;
+
+pub fn correlation(
+    df: &DataFrame,
+    first_var: &str,
+    second_var: &str,
+    method: CorrelationMethod,
+    alternative: Alternative,
+) -> Result<CorrelationResult, DatasetError> {
+    let x = df.get_column(first_var)?.get_values_as_f64()?;
+    let y = df.get_column(second_var)?.get_values_as_f64()?;
+    correlation_test(&x, &y, method, alternative)
+}
+
+// Correlation test as cor.test in R, pairs with a missing (NaN) value are left out.
+// Spearman's rho is tested with the t approximation. Confidence intervals use Fisher's
+// z with standard error 1/sqrt(n - 3) for r, sqrt((1 + rho^2/2)/(n - 3)) for rho
+// (Bonett & Wright, 2000) and sqrt(0.437/(n - 4)) for tau (Fieller et al., 1957)
+pub fn correlation_test(
+    x: &[f64],
+    y: &[f64],
+    method: CorrelationMethod,
+    alternative: Alternative,
+) -> Result<CorrelationResult, DatasetError> {
+    if x.len() != y.len() {
+        return Err(DatasetError::InvalidData(
+            "Both variables must have the same number of observations.".to_string(),
+        ));
+    }
+    let (x, y): (Vec<f64>, Vec<f64>) = x
+        .iter()
+        .zip(y)
+        .filter(|(a, b)| !a.is_nan() && !b.is_nan())
+        .unzip();
+    let n = x.len();
+    if n < 3 {
+        return Err(DatasetError::InvalidData(
+            "At least three complete pairs are required.".to_string(),
+        ));
+    }
+    // Constant values also leave every rank tied
+    if x.iter().all(|&v| v == x[0]) || y.iter().all(|&v| v == y[0]) {
+        return Err(DatasetError::InvalidData(
+            "A variable without variance has no correlation.".to_string(),
+        ));
+    }
+
+    let nf = n as f64;
+    let (estimate, statistic, p_value, exact, std_error) = match method {
+        CorrelationMethod::Pearson | CorrelationMethod::Spearman => {
+            let r = if method == CorrelationMethod::Pearson {
+                pearson(&x, &y)
+            } else {
+                pearson(&rank(&x), &rank(&y))
+            };
+            let t = r * ((nf - 2.0) / (1.0 - r * r)).sqrt();
+            let p_value = alternative.p_value(tdist::cdf(t, nf - 2.0), tdist::sf(t, nf - 2.0));
+            let std_error = if method == CorrelationMethod::Pearson {
+                1.0 / (nf - 3.0).sqrt()
+            } else {
+                ((1.0 + r * r / 2.0) / (nf - 3.0)).sqrt()
+            };
+            (r, t, p_value, false, std_error)
+        }
+        CorrelationMethod::Kendall => {
+            let kendall = kendall(&x, &y);
+            let exact = n < EXACT_LIMIT && !kendall.ties;
+            let p_value = if !exact {
+                alternative.p_value(normal::cdf(kendall.z), normal::sf(kendall.z))
+            } else {
+                // Number of concordant pairs against the distribution of inversions
+                let distribution = kendall_distribution(n);
+                let q = ((kendall.tau + 1.0) * nf * (nf - 1.0) / 4.0).round() as usize;
+                let lower: f64 = distribution[..=q].iter().sum();
+                let upper: f64 = distribution[q..].iter().sum();
+                alternative.p_value(lower, upper)
+            };
+            let std_error = (0.437 / (nf - 4.0)).sqrt();
+            (kendall.tau, kendall.z, p_value, exact, std_error)
+        }
+    };
+
+    let (ci_lower, ci_upper) = fisher_interval(estimate, std_error, alternative);
+    Ok(CorrelationResult {
+        method,
+        estimate,
+        statistic,
+        p_value,
+        ci_lower,
+        ci_upper,
+        n,
+        exact,
+    })
+}
+
+// Two-sided tests of all pairs of variables, the adjustment is applied over the
+// pairs in the lower triangle and confidence intervals are left unadjusted
+pub fn correlation_matrix(
+    df: &DataFrame,
+    variables: Vec<&str>,
+    method: CorrelationMethod,
+    missing: MissingData,
+    adjustment: PAdjustment,
+) -> Result<CorrelationMatrix, DatasetError> {
+    if variables.len() < 2 {
+        return Err(DatasetError::InvalidData(
+            "At least two variables are required.".to_string(),
+        ));
+    }
+    let mut columns = variables
+        .iter()
+        .map(|name| df.get_column(name)?.get_values_as_f64())
+        .collect::<Result<Vec<_>, _>>()?;
+    if missing == MissingData::Listwise {
+        let complete: Vec<bool> = (0..columns[0].len())
+            .map(|row| columns.iter().all(|column| !column[row].is_nan()))
+            .collect();
+        for column in columns.iter_mut() {
+            *column = column
+                .iter()
+                .zip(&complete)
+                .filter(|(_, &keep)| keep)
+                .map(|(&value, _)| value)
+                .collect();
+        }
+    }
+
+    let mut results = Vec::new();
+    for row in 1..variables.len() {
+        for column in 0..row {
+            let result = correlation_test(
+                &columns[row],
+                &columns[column],
+                method,
+                Alternative::TwoSided,
+            )?;
+            results.push((row, column, result));
+        }
+    }
+
+    let p_values: Vec<f64> = results
+        .iter()
+        .map(|(_, _, result)| result.p_value)
+        .collect();
+    let adjusted = adjust_p_values(&p_values, adjustment);
+    let pairs = results
+        .into_iter()
+        .zip(adjusted)
+        .map(
+            |((row, column, result), adjusted_p_value)| CorrelationPair {
+                first: variables[row].to_string(),
+                second: variables[column].to_string(),
+                result,
+                adjusted_p_value,
+            },
+        )
+        .collect();
+
+    Ok(CorrelationMatrix {
+        variables: variables.iter().map(|name| name.to_string()).collect(),
+        method,
+        adjustment,
+        pairs,
+    })
+}
+
+// Correlation of the residuals of both variables after regressing each on the
+// covariates, as pcor.test in ppcor. Spearman works on ranks throughout
+pub fn partial_correlation(
+    df: &DataFrame,
+    first_var: &str,
+    second_var: &str,
+    covariates: Vec<&str>,
+    method: CorrelationMethod,
+    alternative: Alternative,
+) -> Result<PartialCorrelationResult, DatasetError> {
+    let columns = complete_cases(df, first_var, second_var, &covariates, method)?;
+    let controls = &columns[2..];
+    let r = pearson(
+        &residuals(&columns[0], controls)?,
+        &residuals(&columns[1], controls)?,
+    );
+    Ok(partial_test(
+        r,
+        columns[0].len(),
+        controls.len(),
+        alternative,
+    ))
+}
+
+// Correlation of the first variable with the part of the second variable that is
+// not explained by the covariates, as spcor.test in ppcor
+pub fn semipartial_correlation(
+    df: &DataFrame,
+    first_var: &str,
+    second_var: &str,
+    covariates: Vec<&str>,
+    method: CorrelationMethod,
+    alternative: Alternative,
+) -> Result<PartialCorrelationResult, DatasetError> {
+    let columns = complete_cases(df, first_var, second_var, &covariates, method)?;
+    let controls = &columns[2..];
+    let r = pearson(&columns[0], &residuals(&columns[1], controls)?);
+    Ok(partial_test(
+        r,
+        columns[0].len(),
+        controls.len(),
+        alternative,
+    ))
+}
+
+// Partial correlations of every pair given all other variables, read off the
+// precision matrix P (the inverse correlation matrix) as -P_ij / sqrt(P_ii P_jj).
+// Rows with a missing value are dropped listwise, tests are two-sided
+pub fn partial_correlation_matrix(
+    df: &DataFrame,
+    variables: Vec<&str>,
+    method: CorrelationMethod,
+) -> Result<PartialCorrelationMatrix, DatasetError> {
+    if variables.len() < 3 {
+        return Err(DatasetError::InvalidData(
+            "At least three variables are required.".to_string(),
+        ));
+    }
+    let columns = complete_cases(df, variables[0], variables[1], &variables[2..], method)?;
+    let k = columns.len();
+    let n = columns[0].len();
+    if n <= k {
+        return Err(DatasetError::InvalidData(format!(
+            "At least {} complete cases are required.",
+            k + 1
+        )));
+    }
+
+    let correlations: Vec<Vec<f64>> = (0..k)
+        .map(|i| (0..k).map(|j| pearson(&columns[i], &columns[j])).collect())
+        .collect();
+    let lower = cholesky(&correlations).map_err(|_| {
+        DatasetError::InvalidData("The correlation matrix is singular.".to_string())
+    })?;
+    let precision: Vec<Vec<f64>> = (0..k)
+        .map(|j| {
+            let mut unit = vec![0.0; k];
+            unit[j] = 1.0;
+            cholesky_solve(&lower, &unit)
+        })
+        .collect();
+
+    let mut estimates = vec![vec![1.0; k]; k];
+    let mut statistics = vec![vec![f64::NAN; k]; k];
+    let mut p_values = vec![vec![0.0; k]; k];
+    for i in 0..k {
+        for j in 0..k {
+            if i != j {
+                let r = -precision[i][j] / (precision[i][i] * precision[j][j]).sqrt();
+                let test = partial_test(r, n, k - 2, Alternative::TwoSided);
+                estimates[i][j] = r;
+                statistics[i][j] = test.statistic;
+                p_values[i][j] = test.p_value;
+            }
+        }
+    }
+
+    Ok(PartialCorrelationMatrix {
+        variables: variables.iter().map(|name| name.to_string()).collect(),
+        estimates,
+        statistics,
+        p_values,
+        df: (n - k) as f64,
+        n,
+    })
+}
+
+// Fisher's z test of Pearson correlations from two independent samples
+pub fn compare_independent_correlations(
+    first: f64,
+    first_n: usize,
+    second: f64,
+    second_n: usize,
+    alternative: Alternative,
+) -> Result<CorrelationComparison, DatasetError> {
+    if first_n < 4 || second_n < 4 {
+        return Err(DatasetError::InvalidData(
+            "Each sample needs at least four observations.".to_string(),
+        ));
+    }
+    let std_error = (1.0 / (first_n as f64 - 3.0) + 1.0 / (second_n as f64 - 3.0)).sqrt();
+    let z = (first.atanh() - second.atanh()) / std_error;
+    Ok(CorrelationComparison {
+        first,
+        second,
+        statistic: z,
+        df: None,
+        p_value: alternative.p_value(normal::cdf(z), normal::sf(z)),
+    })
+}
+
+// Correlation of two variables in the first against the second level of a grouping
+// variable with two levels
+pub fn compare_group_correlations(
+    df: &DataFrame,
+    first_var: &str,
+    second_var: &str,
+    group_var: &str,
+    alternative: Alternative,
+) -> Result<CorrelationComparison, DatasetError> {
+    let (levels, codes) = factor_levels(df, group_var)?;
+    if levels.len() != 2 {
+        return Err(DatasetError::InvalidData(format!(
+            "Grouping variable {} must have exactly two levels",
+            group_var
+        )));
+    }
+    let x = df.get_column(first_var)?.get_values_as_f64()?;
+    let y = df.get_column(second_var)?.get_values_as_f64()?;
+    let group = |level: usize| -> Result<CorrelationResult, DatasetError> {
+        let (x, y): (Vec<f64>, Vec<f64>) = codes
+            .iter()
+            .zip(x.iter().zip(&y))
+            .filter(|(&code, _)| code == level)
+            .map(|(_, (&a, &b))| (a, b))
+            .unzip();
+        correlation_test(&x, &y, CorrelationMethod::Pearson, Alternative::TwoSided)
+    };
+    let (first, second) = (group(0)?, group(1)?);
+    compare_independent_correlations(
+        first.estimate,
+        first.n,
+        second.estimate,
+        second.n,
+        alternative,
+    )
+}
+
+// Whether the shared variable correlates differently with the first and the second
+// variable in one sample, using complete cases of all three
+pub fn compare_overlapping_correlations(
+    df: &DataFrame,
+    shared_var: &str,
+    first_var: &str,
+    second_var: &str,
+    alternative: Alternative,
+) -> Result<OverlappingComparison, DatasetError> {
+    let columns = complete_cases(
+        df,
+        shared_var,
+        first_var,
+        &[second_var],
+        CorrelationMethod::Pearson,
+    )?;
+    overlapping_comparison(
+        pearson(&columns[0], &columns[1]),
+        pearson(&columns[0], &columns[2]),
+        pearson(&columns[1], &columns[2]),
+        columns[0].len(),
+        alternative,
+    )
+}
+
+// Tests of r_jk = r_jh given r_kh: Williams' t (1959) with n - 3 degrees of freedom
+// and Steiger's z (1980) with the pooled correlation in the covariance of the two
+pub fn overlapping_comparison(
+    r_jk: f64,
+    r_jh: f64,
+    r_kh: f64,
+    n: usize,
+    alternative: Alternative,
+) -> Result<OverlappingComparison, DatasetError> {
+    if n < 4 {
+        return Err(DatasetError::InvalidData(
+            "At least four observations are required.".to_string(),
+        ));
+    }
+    let nf = n as f64;
+    let pooled = (r_jk + r_jh) / 2.0;
+
+    let determinant = 1.0 - r_jk * r_jk - r_jh * r_jh - r_kh * r_kh + 2.0 * r_jk * r_jh * r_kh;
+    let t = (r_jk - r_jh) * ((nf - 1.0) * (1.0 + r_kh)).sqrt()
+        / (2.0 * (nf - 1.0) / (nf - 3.0) * determinant + pooled.powi(2) * (1.0 - r_kh).powi(3))
+            .sqrt();
+    let williams = CorrelationComparison {
+        first: r_jk,
+        second: r_jh,
+        statistic: t,
+        df: Some(nf - 3.0),
+        p_value: alternative.p_value(tdist::cdf(t, nf - 3.0), tdist::sf(t, nf - 3.0)),
+    };
+
+    let psi = r_kh * (1.0 - 2.0 * pooled.powi(2))
+        - 0.5 * pooled.powi(2) * (1.0 - 2.0 * pooled.powi(2) - r_kh * r_kh);
+    let steiger = steiger_z(
+        r_jk,
+        r_jh,
+        psi / (1.0 - pooled.powi(2)).powi(2),
+        n,
+        alternative,
+    );
+
+    Ok(OverlappingComparison {
+        between: r_kh,
+        n,
+        williams,
+        steiger,
+    })
+}
+
+// Whether r_jk differs from r_hm when all four variables are measured in one sample,
+// using complete cases of all four
+pub fn compare_nonoverlapping_correlations(
+    df: &DataFrame,
+    first_pair: (&str, &str),
+    second_pair: (&str, &str),
+    alternative: Alternative,
+) -> Result<CorrelationComparison, DatasetError> {
+    let columns = complete_cases(
+        df,
+        first_pair.0,
+        first_pair.1,
+        &[second_pair.0, second_pair.1],
+        CorrelationMethod::Pearson,
+    )?;
+    let correlations: Vec<Vec<f64>> = columns
+        .iter()
+        .map(|a| columns.iter().map(|b| pearson(a, b)).collect())
+        .collect();
+    nonoverlapping_comparison(&correlations, columns[0].len(), alternative)
+}
+
+// Steiger's z (1980) for r_jk = r_hm from the correlation matrix of j, k, h and m,
+// with the Pearson-Filon covariance evaluated at the pooled correlation
+pub fn nonoverlapping_comparison(
+    correlations: &[Vec<f64>],
+    n: usize,
+    alternative: Alternative,
+) -> Result<CorrelationComparison, DatasetError> {
+    if correlations.len() != 4 || correlations.iter().any(|row| row.len() != 4) {
+        return Err(DatasetError::InvalidData(
+            "A 4 x 4 correlation matrix is required.".to_string(),
+        ));
+    }
+    if n < 4 {
+        return Err(DatasetError::InvalidData(
+            "At least four observations are required.".to_string(),
+        ));
+    }
+    let r = |a: usize, b: usize| correlations[a][b];
+    let (j, k, h, m) = (0, 1, 2, 3);
+    let pooled = (r(j, k) + r(h, m)) / 2.0;
+    let psi = 0.5
+        * ((r(j, h) - pooled * r(k, h)) * (r(k, m) - r(k, h) * pooled)
+            + (r(j, m) - r(j, h) * pooled) * (r(k, h) - pooled * r(j, h))
+            + (r(j, h) - r(j, m) * pooled) * (r(k, m) - pooled * r(j, m))
+            + (r(j, m) - pooled * r(k, m)) * (r(k, h) - r(k, m) * pooled));
+    Ok(steiger_z(
+        r(j, k),
+        r(h, m),
+        psi / (1.0 - pooled.powi(2)).powi(2),
+        n,
+        alternative,
+    ))
+}
+
+// Difference of Fisher's z values whose sampling covariance is c / (n - 3)
+fn steiger_z(
+    first: f64,
+    second: f64,
+    c: f64,
+    n: usize,
+    alternative: Alternative,
+) -> CorrelationComparison {
+    let z = (first.atanh() - second.atanh()) * (n as f64 - 3.0).sqrt() / (2.0 - 2.0 * c).sqrt();
+    CorrelationComparison {
+        first,
+        second,
+        statistic: z,
+        df: None,
+        p_value: alternative.p_value(normal::cdf(z), normal::sf(z)),
+    }
+}
+
+// Both variables followed by the covariates, restricted to rows where all are observed
+fn complete_cases(
+    df: &DataFrame,
+    first_var: &str,
+    second_var: &str,
+    covariates: &[&str],
+    method: CorrelationMethod,
+) -> Result<Vec<Vec<f64>>, DatasetError> {
+    if method == CorrelationMethod::Kendall {
+        return Err(DatasetError::InvalidData(
+            "Partial correlations support the Pearson and Spearman methods.".to_string(),
+        ));
+    }
+    let columns = [first_var, second_var]
+        .iter()
+        .chain(covariates)
+        .map(|name| df.get_column(name)?.get_values_as_f64())
+        .collect::<Result<Vec<_>, _>>()?;
+    let complete: Vec<usize> = (0..columns[0].len())
+        .filter(|&row| columns.iter().all(|column| !column[row].is_nan()))
+        .collect();
+    if complete.len() < covariates.len() + 3 {
+        return Err(DatasetError::InvalidData(format!(
+            "At least {} complete cases are required.",
+            covariates.len() + 3
+        )));
+    }
+
+    Ok(columns
+        .iter()
+        .map(|column| {
+            let values: Vec<f64> = complete.iter().map(|&row| column[row]).collect();
+            if method == CorrelationMethod::Spearman {
+                rank(&values)
+            } else {
+                values
+            }
+        })
+        .collect())
+}
+
+// Residuals of a least squares fit on the covariates and an intercept
+fn residuals(values: &[f64], covariates: &[Vec<f64>]) -> Result<Vec<f64>, DatasetError> {
+    let design: Vec<Vec<f64>> = (0..values.len())
+        .map(|row| {
+            std::iter::once(1.0)
+                .chain(covariates.iter().map(|column| column[row]))
+                .collect()
+        })
+        .collect();
+    let qr = QrDecomposition::new(&design)?;
+    let coefficients = qr.coefficients(values);
+    Ok(design
+        .iter()
+        .zip(values)
+        .map(|(row, value)| {
+            let fitted: f64 = qr.pivot[..qr.rank]
+                .iter()
+                .zip(&coefficients)
+                .map(|(&column, b)| row[column] * b)
+                .sum();
+            value - fitted
+        })
+        .collect())
+}
+
+fn partial_test(
+    r: f64,
+    n: usize,
+    covariates: usize,
+    alternative: Alternative,
+) -> PartialCorrelationResult {
+    let dof = n as f64 - 2.0 - covariates as f64;
+    let t = r * (dof / (1.0 - r * r)).sqrt();
+    PartialCorrelationResult {
+        estimate: r,
+        statistic: t,
+        df: dof,
+        p_value: alternative.p_value(tdist::cdf(t, dof), tdist::sf(t, dof)),
+        n,
+        covariates,
+    }
+}
+
+// Confidence interval on Fisher's z scale, NaN when the standard error is undefined
+pub(crate) fn fisher_interval(r: f64, std_error: f64, alternative: Alternative) -> (f64, f64) {
+    if !std_error.is_finite() || std_error <= 0.0 {
+        return (f64::NAN, f64::NAN);
+    }
+    let z = r.atanh();
+    match alternative {
+        Alternative::TwoSided => {
+            let margin = normal::quantile(0.975) * std_error;
+            ((z - margin).tanh(), (z + margin).tanh())
+        }
+        Alternative::Less => (-1.0, (z + normal::quantile(0.95) * std_error).tanh()),
+        Alternative::Greater => ((z - normal::quantile(0.95) * std_error).tanh(), 1.0),
+    }
+}
+
+pub(crate) struct Kendall {
+    pub tau: f64, // tau-b
+    pub z: f64,   // S over its standard deviation with tie corrections
+    pub ties: bool,
+}
+
+// Kendall's tau-b by counting all pairs, the variance of S follows Kendall (1976)
+// as in cor.test
+pub(crate) fn kendall(x: &[f64], y: &[f64]) -> Kendall {
+    let n = x.len();
+    let mut s = 0.0;
+    for i in 0..n {
+        for j in i + 1..n {
+            if x[i] != x[j] && y[i] != y[j] {
+                s += ((x[i] - x[j]) * (y[i] - y[j])).signum();
+            }
+        }
+    }
+
+    let nf = n as f64;
+    let (x_ties, y_ties) = (tie_sizes(x), tie_sizes(y));
+    let pairs = |ties: &[usize]| {
+        ties.iter()
+            .map(|&t| (t * (t - 1)) as f64 / 2.0)
+            .sum::<f64>()
+    };
+    let total = nf * (nf - 1.0) / 2.0;
+    let tau = s / ((total - pairs(&x_ties)) * (total - pairs(&y_ties))).sqrt();
+
+    let sum = |ties: &[usize], f: fn(f64) -> f64| ties.iter().map(|&t| f(t as f64)).sum::<f64>();
+    let v0 = nf * (nf - 1.0) * (2.0 * nf + 5.0);
+    let vt = sum(&x_ties, |t| t * (t - 1.0) * (2.0 * t + 5.0));
+    let vu = sum(&y_ties, |t| t * (t - 1.0) * (2.0 * t + 5.0));
+    let v1 = sum(&x_ties, |t| t * (t - 1.0)) * sum(&y_ties, |t| t * (t - 1.0));
+    let v2 =
+        sum(&x_ties, |t| t * (t - 1.0) * (t - 2.0)) * sum(&y_ties, |t| t * (t - 1.0) * (t - 2.0));
+    let variance = (v0 - vt - vu) / 18.0
+        + v1 / (2.0 * nf * (nf - 1.0))
+        + v2 / (9.0 * nf * (nf - 1.0) * (nf - 2.0));
+
+    Kendall {
+        tau,
+        z: s / variance.sqrt(),
+        ties: !x_ties.is_empty() || !y_ties.is_empty(),
+    }
+}
+
+// Null distribution of the number of inversions of a random permutation of n values,
+// built up one value at a time: inserting value k adds 0..k inversions
+fn kendall_distribution(n: usize) -> Vec<f64> {
+    let max = n * (n - 1) / 2;
+    let mut distribution = vec![0.0; max + 1];
+    distribution[0] = 1.0;
+    for k in 2..=n {
+        let previous = distribution.clone();
+        let reach = k * (k - 1) / 2;
+        for (q, value) in distribution.iter_mut().enumerate().take(reach + 1) {
+            let first = q.saturating_sub(k - 1);
+            *value = previous[first..=q].iter().sum::<f64>() / k as f64;
+        }
+    }
+    distribution
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{helper::roughly_equals, reader};
+
+    // Example from R's cor.test documentation
+    const X: [f64; 9] = [44.4, 45.9, 41.9, 53.3, 44.7, 44.1, 50.7, 45.2, 60.1];
+    const Y: [f64; 9] = [2.6, 3.1, 2.5, 5.0, 3.6, 4.0, 5.2, 2.8, 3.8];
+
+    #[test]
+    fn test_pearson() {
+        let result =
+            correlation_test(&X, &Y, CorrelationMethod::Pearson, Alternative::TwoSided).unwrap();
+        assert!(roughly_equals(result.estimate, 0.5711816, 1e-7));
+        assert!(roughly_equals(result.statistic, 1.8411, 1e-4));
+        assert!(roughly_equals(result.p_value, 0.1082, 1e-4));
+        assert!(roughly_equals(result.ci_lower, -0.1497426, 1e-7));
+        assert!(roughly_equals(result.ci_upper, 0.8955795, 1e-7));
+
+        let greater =
+            correlation_test(&X, &Y, CorrelationMethod::Pearson, Alternative::Greater).unwrap();
+        assert!(roughly_equals(greater.p_value, 0.1082 / 2.0, 1e-4));
+        assert_eq!(greater.ci_upper, 1.0);
+    }
+
+    #[test]
+    fn test_rank_correlations() {
+        let spearman =
+            correlation_test(&X, &Y, CorrelationMethod::Spearman, Alternative::Greater).unwrap();
+        assert!(roughly_equals(spearman.estimate, 0.6, 1e-12));
+        assert!(roughly_equals(spearman.statistic, 1.984313, 1e-6));
+
+        let kendall =
+            correlation_test(&X, &Y, CorrelationMethod::Kendall, Alternative::Greater).unwrap();
+        assert!(kendall.exact);
+        assert!(roughly_equals(kendall.estimate, 0.4444444, 1e-7));
+        assert!(roughly_equals(kendall.p_value, 0.05971947, 1e-8));
+
+        // Ties: tau-b with the tie-corrected normal approximation
+        let x = [1.0, 2.0, 2.0, 3.0, 4.0, 4.0, 4.0, 5.0, 6.0, 7.0, 7.0, 8.0];
+        let y = [2.0, 1.0, 3.0, 3.0, 5.0, 4.0, 6.0, 6.0, 5.0, 8.0, 9.0, 9.0];
+        let ties =
+            correlation_test(&x, &y, CorrelationMethod::Kendall, Alternative::TwoSided).unwrap();
+        assert!(!ties.exact);
+        assert!(roughly_equals(ties.estimate, 0.8292957, 1e-7));
+        assert!(roughly_equals(ties.statistic, 3.576830, 1e-6));
+        assert!(roughly_equals(ties.p_value, 0.0003477867, 1e-9));
+    }
+
+    #[test]
+    fn test_missing_pairs() {
+        let mut x = X.to_vec();
+        x[0] = f64::NAN;
+        let result =
+            correlation_test(&x, &Y, CorrelationMethod::Pearson, Alternative::TwoSided).unwrap();
+        assert_eq!(result.n, 8);
+        assert!(correlation_test(
+            &X[..2],
+            &Y[..2],
+            CorrelationMethod::Pearson,
+            Alternative::TwoSided
+        )
+        .is_err());
+
+        // A constant variable is an error rather than r = NaN with p = 1
+        let constant = [2.0; 9];
+        for method in [
+            CorrelationMethod::Pearson,
+            CorrelationMethod::Spearman,
+            CorrelationMethod::Kendall,
+        ] {
+            assert!(correlation_test(&X, &constant, method, Alternative::TwoSided).is_err());
+        }
+    }
+
+    #[test]
+    fn test_correlation_matrix() {
+        let df = reader::import_csv("samples/data3.csv").unwrap();
+        let matrix = correlation_matrix(
+            &df,
+            vec!["score", "age", "happiness"],
+            CorrelationMethod::Pearson,
+            MissingData::Pairwise,
+            PAdjustment::Holm,
+        )
+        .unwrap();
+        let rows = matrix.lower_triangle();
+        assert_eq!(
+            rows.iter().map(|row| row.len()).collect::<Vec<_>>(),
+            vec![0, 1, 2]
+        );
+        assert_eq!(
+            (rows[2][0].first.as_str(), rows[2][0].second.as_str()),
+            ("happiness", "score")
+        );
+        assert!(roughly_equals(rows[1][0].result.estimate, 0.05747841, 1e-8));
+        assert!(roughly_equals(rows[2][0].result.estimate, 0.2495743, 1e-7));
+        assert!(roughly_equals(rows[2][1].result.statistic, 0.2757285, 1e-7));
+        assert_eq!(matrix.estimates()[0][2], matrix.estimates()[2][0]);
+
+        // Holm multiplies the smallest p-value by the number of pairs
+        let smallest = matrix.pair(0, 2).unwrap();
+        assert!(roughly_equals(
+            smallest.adjusted_p_value,
+            3.0 * smallest.result.p_value,
+            1e-12
+        ));
+        assert!(matrix.p_values()[1][0] >= matrix.pair(1, 0).unwrap().result.p_value);
+    }
+
+    #[test]
+    fn test_missing_data() {
+        let mut df = DataFrame::new();
+        df.add_numerical_column("x", vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0]);
+        df.add_numerical_column("y", vec![2.0, 1.0, 4.0, 3.0, 7.0, 8.0, 6.0, 9.0]);
+        df.add_numerical_column("z", vec![5.0, 3.0, f64::NAN, 4.0, 2.0, 1.0, 3.0, 0.0]);
+        let estimate = |missing| {
+            let matrix = correlation_matrix(
+                &df,
+                vec!["x", "y", "z"],
+                CorrelationMethod::Pearson,
+                missing,
+                PAdjustment::None,
+            )
+            .unwrap();
+            let pair = matrix.pair(0, 1).unwrap();
+            (pair.result.estimate, pair.result.n)
+        };
+        let (pairwise, n) = estimate(MissingData::Pairwise);
+        assert!(roughly_equals(pairwise, 0.8964215, 1e-7));
+        assert_eq!(n, 8);
+        let (listwise, n) = estimate(MissingData::Listwise);
+        assert!(roughly_equals(listwise, 0.8985443, 1e-7));
+        assert_eq!(n, 7);
+    }
+
+    #[test]
+    fn test_partial_correlation() {
+        let df = reader::import_csv("samples/data3.csv").unwrap();
+        let partial = |method| {
+            partial_correlation(
+                &df,
+                "happiness",
+                "score",
+                vec!["age"],
+                method,
+                Alternative::TwoSided,
+            )
+            .unwrap()
+        };
+        let pearson = partial(CorrelationMethod::Pearson);
+        assert!(roughly_equals(pearson.estimate, 0.2484809, 1e-7));
+        assert!(roughly_equals(pearson.statistic, 2.526492, 1e-6));
+        assert_eq!(pearson.df, 97.0);
+        assert!(roughly_equals(
+            partial(CorrelationMethod::Spearman).estimate,
+            0.3373679,
+            1e-7
+        ));
+
+        let semipartial = semipartial_correlation(
+            &df,
+            "happiness",
+            "score",
+            vec!["age"],
+            CorrelationMethod::Pearson,
+            Alternative::TwoSided,
+        )
+        .unwrap();
+        assert!(roughly_equals(semipartial.estimate, 0.2483846, 1e-7));
+        assert!(roughly_equals(semipartial.statistic, 2.525448, 1e-6));
+
+        // With three variables the matrix entries control for the remaining one
+        let matrix = partial_correlation_matrix(
+            &df,
+            vec!["score", "age", "happiness"],
+            CorrelationMethod::Pearson,
+        )
+        .unwrap();
+        assert!(roughly_equals(
+            matrix.estimates[0][2],
+            pearson.estimate,
+            1e-10
+        ));
+        assert!(roughly_equals(
+            matrix.p_values[2][0],
+            pearson.p_value,
+            1e-10
+        ));
+        let age = partial_correlation(
+            &df,
+            "score",
+            "age",
+            vec!["happiness"],
+            CorrelationMethod::Pearson,
+            Alternative::TwoSided,
+        )
+        .unwrap();
+        assert!(roughly_equals(matrix.estimates[1][0], age.estimate, 1e-10));
+    }
+
+    #[test]
+    fn test_compare_correlations() {
+        let independent =
+            compare_independent_correlations(0.5, 100, 0.3, 120, Alternative::TwoSided).unwrap();
+        assert!(roughly_equals(independent.statistic, 1.746211, 1e-6));
+        assert!(roughly_equals(independent.p_value, 0.08077430, 1e-8));
+
+        let overlapping = overlapping_comparison(0.5, 0.3, 0.4, 80, Alternative::TwoSided).unwrap();
+        assert!(roughly_equals(
+            overlapping.williams.statistic,
+            1.839960,
+            1e-6
+        ));
+        assert!(roughly_equals(
+            overlapping.williams.p_value,
+            0.06962776,
+            1e-7
+        ));
+        assert!(roughly_equals(
+            overlapping.steiger.statistic,
+            1.812993,
+            1e-6
+        ));
+        assert!(roughly_equals(
+            overlapping.steiger.p_value,
+            0.06983283,
+            1e-8
+        ));
+
+        let correlations = vec![
+            vec![1.0, 0.5, 0.2, 0.25],
+            vec![0.5, 1.0, 0.1, 0.15],
+            vec![0.2, 0.1, 1.0, 0.3],
+            vec![0.25, 0.15, 0.3, 1.0],
+        ];
+        let nonoverlapping =
+            nonoverlapping_comparison(&correlations, 100, Alternative::Greater).unwrap();
+        assert!(roughly_equals(nonoverlapping.statistic, 1.690162, 1e-6));
+        assert!(roughly_equals(
+            nonoverlapping.p_value,
+            0.09099693 / 2.0,
+            1e-8
+        ));
+
+        let mut df = DataFrame::new();
+        let x = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0];
+        df.add_numerical_column("x", [x, x].concat());
+        df.add_numerical_column(
+            "y",
+            vec![
+                2.0, 1.0, 4.0, 3.0, 6.0, 5.0, 8.0, 7.0, 5.0, 3.0, 6.0, 2.0, 7.0, 4.0, 3.0, 6.0,
+            ],
+        );
+        let groups = ["a", "b"]
+            .iter()
+            .flat_map(|g| vec![g.to_string(); 8])
+            .collect();
+        df.add_categorical_column("group", groups);
+        let groups =
+            compare_group_correlations(&df, "x", "y", "group", Alternative::TwoSided).unwrap();
+        assert!(roughly_equals(groups.first, 0.9047619, 1e-7));
+        assert!(roughly_equals(groups.statistic, 2.211778, 1e-6));
+        assert!(roughly_equals(groups.p_value, 0.02698203, 1e-8));
+
+        let df = reader::import_csv("samples/data3.csv").unwrap();
+        let overlapping = compare_overlapping_correlations(
+            &df,
+            "score",
+            "happiness",
+            "age",
+            Alternative::TwoSided,
+        )
+        .unwrap();
+        assert!(roughly_equals(overlapping.williams.first, 0.2495743, 1e-7));
+        assert!(roughly_equals(
+            overlapping.williams.second,
+            0.05747841,
+            1e-8
+        ));
+        assert_eq!(overlapping.n, 100);
+    }
+}
@@ -0,0 +1,109 @@
+use crate::{
+    core::{dataframe::DataFrame, errors::DatasetError},
+    distributions::fdist,
+};
+
+use super::linear::{linear_regression, LinearRegressionResult};
+
+#[derive(Debug)]
+pub struct HierarchicalStep {
+    pub predictors: Vec<String>, // all predictors up to and including this block
+    pub model: LinearRegressionResult,
+    pub r_squared_change: f64,
+    pub f_change: f64,
+    pub df_change: usize,
+    pub df_residual: usize,
+    pub p_change: f64,
+}
+
+// Enters the predictor blocks one after another and tests the R-squared change of every
+// step against the previous model (the intercept-only model for the first block), like
+// the "R Square Change" statistics of SPSS
+pub fn hierarchical_regression(
+    df: &DataFrame,
+    dependent_var: &str,
+    blocks: Vec<Vec<&str>>,
+) -> Result<Vec<HierarchicalStep>, DatasetError> {
+    if blocks.is_empty() || blocks.iter().any(|block| block.is_empty()) {
+        return Err(DatasetError::InvalidData(
+            "Every block must contain at least one predictor.".to_string(),
+        ));
+    }
+
+    let mut predictors: Vec<&str> = Vec::new();
+    let mut previous_r_squared = 0.0;
+    let mut previous_df_model = 0;
+    let mut steps = Vec::with_capacity(blocks.len());
+
+    for (number, block) in blocks.into_iter().enumerate() {
+        for predictor in block {
+            if predictors.contains(&predictor) {
+                return Err(DatasetError::InvalidData(format!(
+                    "Predictor {} is entered more than once",
+                    predictor
+                )));
+            }
+            predictors.push(predictor);
+        }
+
+        let model = linear_regression(df, dependent_var, predictors.clone())?;
+        let r_squared_change = model.r_squared - previous_r_squared;
+        // Aliased predictors do not add degrees of freedom
+        let df_change = model.df_model - previous_df_model;
+        if df_change == 0 {
+            return Err(DatasetError::InvalidData(format!(
+                "Block {} adds no estimable predictors",
+                number + 1
+            )));
+        }
+        let f_change = (r_squared_change / df_change as f64)
+            / ((1.0 - model.r_squared) / model.df_residual as f64);
+
+        previous_r_squared = model.r_squared;
+        previous_df_model = model.df_model;
+        steps.push(HierarchicalStep {
+            predictors: predictors.iter().map(|p| p.to_string()).collect(),
+            r_squared_change,
+            f_change,
+            df_change,
+            df_residual: model.df_residual,
+            p_change: fdist::sf(f_change, df_change as f64, model.df_residual as f64),
+            model,
+        });
+    }
+
+    Ok(steps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{fixtures::mtcars, helper::roughly_equals};
+
+    #[test]
+    fn test_hierarchical_regression() {
+        let mut df = mtcars();
+
+        // anova(lm(mpg ~ wt), lm(mpg ~ wt + hp)) in R
+        let steps = hierarchical_regression(&df, "mpg", vec![vec!["wt"], vec!["hp"]]).unwrap();
+        assert_eq!(steps.len(), 2);
+        assert!(roughly_equals(steps[0].r_squared_change, 0.7528, 1e-4));
+        assert!(roughly_equals(steps[0].f_change, 91.375, 1e-3));
+        assert_eq!((steps[0].df_change, steps[0].df_residual), (1, 30));
+
+        // Adding a single predictor, F change is the square of its t statistic
+        let t = steps[1].model.coefficients[2].t;
+        assert!(roughly_equals(steps[1].f_change, t * t, 1e-9));
+        assert!(roughly_equals(steps[1].r_squared_change, 0.0740, 1e-4));
+        assert!(roughly_equals(steps[1].p_change, 0.00145, 1e-5));
+        assert_eq!(steps[1].predictors, vec!["wt", "hp"]);
+
+        assert!(hierarchical_regression(&df, "mpg", vec![vec!["wt"], vec!["wt"]]).is_err());
+
+        // A block of aliased predictors has no R-squared change to test
+        let wt = df.get_column("wt").unwrap().get_values_as_f64().unwrap();
+        df.add_numerical_column("wt_kg", wt.iter().map(|w| w * 453.59237).collect());
+        let blocks = vec![vec!["wt"], vec!["wt_kg"]];
+        assert!(hierarchical_regression(&df, "mpg", blocks).is_err());
+    }
+}
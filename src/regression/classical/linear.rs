@@ -0,0 +1,670 @@
+use crate::{
+    core::{
+        dataframe::DataFrame,
+        design::{design_matrix, DesignMatrix, Predictor},
+        errors::DatasetError,
+        formula::Formula,
+        helper::{pearson, sample_variance},
+    },
+    distributions::{chisq, fdist, tdist},
+    regression::helper::{cholesky, QrDecomposition},
+};
+
+#[derive(Debug)]
+pub struct Coefficient {
+    pub name: String,
+    pub estimate: f64,
+    pub std_error: f64,
+    pub t: f64,
+    pub p_value: f64,
+    pub ci_lower: f64, // 95% confidence interval
+    pub ci_upper: f64,
+    pub standardized: Option<f64>, // beta, not defined for the intercept
+}
+
+#[derive(Debug)]
+pub struct LinearRegressionResult {
+    pub coefficients: Vec<Coefficient>,
+    pub aliased: Vec<String>, // linear combinations of earlier columns, not estimated
+    pub r_squared: f64,
+    pub adj_r_squared: f64,
+    pub f_stat: f64,
+    pub df_model: usize,
+    pub df_residual: usize,
+    pub f_p_value: f64,
+    pub residual_se: f64,
+    pub fitted: Vec<f64>,
+    pub residuals: Vec<f64>,
+    pub model_matrix: Vec<Vec<f64>>, // estimable design columns, in the order of the coefficients
+    pub n: usize,
+}
+
+#[derive(Debug)]
+pub struct Collinearity {
+    pub term: String,
+    pub df: usize, // design columns of the term
+    // Generalized VIF for terms with several columns (Fox & Monette, 1992), the usual
+    // VIF otherwise
+    pub vif: f64,
+    pub tolerance: f64,
+    pub adjusted: f64, // VIF^(1 / (2 df)), comparable across terms as sqrt(VIF)
+}
+
+// Koenker's studentized Breusch-Pagan test, bptest() in lmtest
+#[derive(Debug)]
+pub struct BreuschPagan {
+    pub statistic: f64,
+    pub df: usize,
+    pub p_value: f64,
+}
+
+#[derive(Debug)]
+pub struct Outlier {
+    pub row: usize, // row of the source DataFrame
+    pub studentized_residual: f64,
+    pub leverage: f64,
+    pub cooks_distance: f64,
+    pub bonferroni_p: f64, // outlier test of the studentized residual, outlierTest() in car
+}
+
+#[derive(Debug)]
+pub struct RegressionDiagnostics {
+    pub standardized_residuals: Vec<f64>,
+    pub studentized_residuals: Vec<f64>, // externally studentized, rstudent() in R
+    pub leverage: Vec<f64>,              // hat values
+    pub cooks_distance: Vec<f64>,
+    pub dfbetas: Vec<Vec<f64>>, // per row, in the order of the estimated coefficients
+    pub collinearity: Vec<Collinearity>, // per term, empty with fewer than two terms
+    pub durbin_watson: f64,
+    pub residual_autocorrelation: f64, // lag 1
+    pub breusch_pagan: BreuschPagan,
+    pub outliers: Vec<Outlier>,
+}
+
+// Ordinary least squares regression of the dependent variable with an intercept,
+// like lm(y ~ x1 + x2) in R. Categorical predictors are dummy coded against their
+// first level, use linear_regression_design for other codings
+pub fn linear_regression(
+    df: &DataFrame,
+    dependent_var: &str,
+    predictors: Vec<&str>,
+) -> Result<LinearRegressionResult, DatasetError> {
+    let predictors: Vec<Predictor> = predictors.into_iter().map(Predictor::new).collect();
+    linear_regression_design(df, dependent_var, &predictors)
+}
+
+pub fn linear_regression_design(
+    df: &DataFrame,
+    dependent_var: &str,
+    predictors: &[Predictor],
+) -> Result<LinearRegressionResult, DatasetError> {
+    let y = df.get_column(dependent_var)?.get_values_as_f64()?;
+    let design = design_matrix(df, predictors, true)?;
+    if design.n() != y.len() {
+        return Err(DatasetError::InvalidData(format!(
+            "The predictors and {} must have the same number of observations",
+            dependent_var
+        )));
+    }
+
+    ols(&design.rows, &y, &design.names, true)
+}
+
+// Linear regression from a model formula such as "score ~ condition * age", see
+// core::formula for the supported terms
+pub fn linear_regression_formula(
+    df: &DataFrame,
+    formula: &str,
+) -> Result<LinearRegressionResult, DatasetError> {
+    let formula = Formula::parse(formula)?;
+    let y = formula.response(df)?;
+    let design = formula.design_matrix(df)?;
+    if design.n() != y.len() {
+        return Err(DatasetError::InvalidData(
+            "The predictors and the response must have the same number of observations."
+                .to_string(),
+        ));
+    }
+
+    ols(&design.rows, &y, &design.names, formula.intercept)
+}
+
+// Least squares fit on a design matrix given by rows, solved by a pivoted QR
+// decomposition. With an intercept it must be the first column, without one
+// R-squared is relative to zero as in R
+pub fn ols(
+    design: &[Vec<f64>],
+    y: &[f64],
+    names: &[String],
+    intercept: bool,
+) -> Result<LinearRegressionResult, DatasetError> {
+    let n = y.len();
+    let p = names.len();
+    if design.len() != n || design.iter().any(|row| row.len() != p) {
+        return Err(DatasetError::InvalidData(
+            "The design matrix does not match the observations.".to_string(),
+        ));
+    }
+
+    let qr = QrDecomposition::new(design)?;
+    let rank = qr.rank;
+    if n <= rank {
+        return Err(DatasetError::InvalidData(format!(
+            "At least {} observations are needed for {} coefficients",
+            rank + 1,
+            rank
+        )));
+    }
+
+    // Estimable columns keep their original order
+    let estimable = &qr.pivot[..rank];
+    let estimates = qr.coefficients(y);
+    let unscaled = qr.unscaled_covariance();
+
+    let fitted: Vec<f64> = design
+        .iter()
+        .map(|row| {
+            estimable
+                .iter()
+                .zip(&estimates)
+                .map(|(&j, b)| row[j] * b)
+                .sum()
+        })
+        .collect();
+    let residuals: Vec<f64> = y.iter().zip(&fitted).map(|(a, b)| a - b).collect();
+
+    let df_residual = n - rank;
+    let df_model = if intercept { rank - 1 } else { rank };
+    let sse: f64 = qr.qty(y)[rank..].iter().map(|r| r * r).sum();
+    let center = if intercept {
+        y.iter().sum::<f64>() / n as f64
+    } else {
+        0.0
+    };
+    let sst: f64 = y.iter().map(|v| (v - center).powi(2)).sum();
+    let sigma2 = sse / df_residual as f64;
+
+    let r_squared = 1.0 - sse / sst;
+    let df_total = if intercept { n - 1 } else { n };
+    let adj_r_squared = 1.0 - (1.0 - r_squared) * df_total as f64 / df_residual as f64;
+    let f_stat = ((sst - sse) / df_model as f64) / sigma2;
+
+    let critical = tdist::quantile(0.975, df_residual as f64);
+    let sd_y = sample_variance(y).sqrt();
+    let coefficients = estimable
+        .iter()
+        .enumerate()
+        .map(|(i, &j)| {
+            let std_error = (sigma2 * unscaled[i][i]).sqrt();
+            let t = estimates[i] / std_error;
+            let is_intercept = intercept && j == 0;
+            let standardized = if is_intercept {
+                None
+            } else {
+                let column: Vec<f64> = design.iter().map(|row| row[j]).collect();
+                Some(estimates[i] * sample_variance(&column).sqrt() / sd_y)
+            };
+            Coefficient {
+                name: names[j].clone(),
+                estimate: estimates[i],
+                std_error,
+                t,
+                p_value: tdist::p_value(t, df_residual as f64),
+                ci_lower: estimates[i] - critical * std_error,
+                ci_upper: estimates[i] + critical * std_error,
+                standardized,
+            }
+        })
+        .collect();
+
+    Ok(LinearRegressionResult {
+        coefficients,
+        aliased: qr.aliased().iter().map(|&j| names[j].clone()).collect(),
+        r_squared,
+        adj_r_squared,
+        f_stat,
+        df_model,
+        df_residual,
+        f_p_value: fdist::sf(f_stat, df_model as f64, df_residual as f64),
+        residual_se: sigma2.sqrt(),
+        fitted,
+        residuals,
+        model_matrix: design
+            .iter()
+            .map(|row| estimable.iter().map(|&j| row[j]).collect())
+            .collect(),
+        n,
+    })
+}
+
+// Influence, collinearity and residual checks of linear_regression with the same
+// arguments
+pub fn regression_diagnostics(
+    df: &DataFrame,
+    dependent_var: &str,
+    predictors: Vec<&str>,
+) -> Result<RegressionDiagnostics, DatasetError> {
+    let predictors: Vec<Predictor> = predictors.into_iter().map(Predictor::new).collect();
+    let y = df.get_column(dependent_var)?.get_values_as_f64()?;
+    let design = design_matrix(df, &predictors, true)?;
+    diagnostics(&design, &y)
+}
+
+pub fn regression_diagnostics_formula(
+    df: &DataFrame,
+    formula: &str,
+) -> Result<RegressionDiagnostics, DatasetError> {
+    let formula = Formula::parse(formula)?;
+    let y = formula.response(df)?;
+    let design = formula.design_matrix(df)?;
+    diagnostics(&design, &y)
+}
+
+// Diagnostics of the least squares fit on a design matrix. Rows are reported as outliers
+// when |studentized residual| > 2, leverage > 2p/n or Cook's distance > 4/n
+// A row with leverage 1 (up to rounding) is fitted exactly and has no residual to scale:
+// its leverage is set to 1 and its residuals, Cook's distance and dfbetas are NaN, as
+// influence.measures in R
+pub fn diagnostics(
+    design: &DesignMatrix,
+    y: &[f64],
+) -> Result<RegressionDiagnostics, DatasetError> {
+    if design.n() != y.len() {
+        return Err(DatasetError::InvalidData(
+            "The predictors and the response must have the same number of observations."
+                .to_string(),
+        ));
+    }
+    let fit = ols(&design.rows, y, &design.names, design.intercept)?;
+    let n = fit.n;
+    let p = n - fit.df_residual;
+    if fit.df_residual < 2 {
+        return Err(DatasetError::InvalidData(
+            "At least two residual degrees of freedom are needed for diagnostics.".to_string(),
+        ));
+    }
+
+    let qr = QrDecomposition::new(&design.rows)?;
+    let estimable = &qr.pivot[..p];
+    let unscaled = qr.unscaled_covariance();
+    let residuals = &fit.residuals;
+    let sse: f64 = residuals.iter().map(|e| e * e).sum();
+    let sigma = fit.residual_se;
+
+    let mut standardized_residuals = Vec::with_capacity(n);
+    let mut studentized_residuals = Vec::with_capacity(n);
+    let mut leverage = Vec::with_capacity(n);
+    let mut cooks_distance = Vec::with_capacity(n);
+    let mut dfbetas = Vec::with_capacity(n);
+    for (row, &e) in design.rows.iter().zip(residuals) {
+        let x: Vec<f64> = estimable.iter().map(|&j| row[j]).collect();
+        // (X'X)^-1 x_i, the change of the estimates without row i is this times e / (1 - h)
+        let ux: Vec<f64> = unscaled
+            .iter()
+            .map(|u| u.iter().zip(&x).map(|(a, b)| a * b).sum())
+            .collect();
+        let h: f64 = x.iter().zip(&ux).map(|(a, b)| a * b).sum();
+        if h >= 1.0 - 10.0 * f64::EPSILON {
+            standardized_residuals.push(f64::NAN);
+            studentized_residuals.push(f64::NAN);
+            leverage.push(1.0);
+            cooks_distance.push(f64::NAN);
+            dfbetas.push(vec![f64::NAN; p]);
+            continue;
+        }
+        // Residual standard error without row i
+        let sigma_i = ((sse - e * e / (1.0 - h)) / (fit.df_residual - 1) as f64).sqrt();
+        let standardized = e / (sigma * (1.0 - h).sqrt());
+
+        standardized_residuals.push(standardized);
+        studentized_residuals.push(e / (sigma_i * (1.0 - h).sqrt()));
+        leverage.push(h);
+        cooks_distance.push(standardized * standardized * h / (p as f64 * (1.0 - h)));
+        dfbetas.push(
+            ux.iter()
+                .enumerate()
+                .map(|(k, v)| v * e / (1.0 - h) / (sigma_i * unscaled[k][k].sqrt()))
+                .collect(),
+        );
+    }
+
+    let durbin_watson = residuals
+        .windows(2)
+        .map(|w| (w[1] - w[0]).powi(2))
+        .sum::<f64>()
+        / sse;
+    let residual_autocorrelation = residuals.windows(2).map(|w| w[0] * w[1]).sum::<f64>() / sse;
+
+    // n R^2 of the squared residuals regressed on the predictors
+    let squared: Vec<f64> = residuals.iter().map(|e| e * e).collect();
+    let auxiliary = ols(&design.rows, &squared, &design.names, design.intercept)?;
+    let statistic = n as f64 * auxiliary.r_squared;
+    let breusch_pagan = BreuschPagan {
+        statistic,
+        df: auxiliary.df_model,
+        p_value: chisq::sf(statistic, auxiliary.df_model as f64),
+    };
+
+    let outliers = (0..n)
+        .filter(|&i| {
+            studentized_residuals[i].abs() > 2.0
+                || leverage[i] > 2.0 * p as f64 / n as f64
+                || cooks_distance[i] > 4.0 / n as f64
+        })
+        .map(|i| {
+            let t = studentized_residuals[i];
+            Outlier {
+                row: i,
+                studentized_residual: t,
+                leverage: leverage[i],
+                cooks_distance: cooks_distance[i],
+                bonferroni_p: (n as f64 * tdist::p_value(t, (fit.df_residual - 1) as f64)).min(1.0),
+            }
+        })
+        .collect();
+
+    Ok(RegressionDiagnostics {
+        standardized_residuals,
+        studentized_residuals,
+        leverage,
+        cooks_distance,
+        dfbetas,
+        collinearity: collinearity(design),
+        durbin_watson,
+        residual_autocorrelation,
+        breusch_pagan,
+        outliers,
+    })
+}
+
+// GVIF of a term from determinants of the predictor correlation matrix:
+// det(R_term) det(R_others) / det(R). Aliased columns give an infinite VIF
+fn collinearity(design: &DesignMatrix) -> Vec<Collinearity> {
+    if design.terms.len() < 2 {
+        return Vec::new();
+    }
+    let columns: Vec<Vec<f64>> = design
+        .terms
+        .iter()
+        .flat_map(|term| term.columns.clone())
+        .map(|j| design.column(j))
+        .collect();
+    let correlation: Vec<Vec<f64>> = columns
+        .iter()
+        .map(|a| columns.iter().map(|b| pearson(a, b)).collect())
+        .collect();
+    let log_determinant = |indices: &[usize]| {
+        let sub: Vec<Vec<f64>> = indices
+            .iter()
+            .map(|&i| indices.iter().map(|&j| correlation[i][j]).collect())
+            .collect();
+        cholesky(&sub)
+            .map(|lower| {
+                (0..indices.len())
+                    .map(|i| 2.0 * lower[i][i].ln())
+                    .sum::<f64>()
+            })
+            .ok()
+    };
+    let all: Vec<usize> = (0..columns.len()).collect();
+    let total = log_determinant(&all);
+
+    let offset = design.terms[0].columns.start;
+    design
+        .terms
+        .iter()
+        .map(|term| {
+            let own: Vec<usize> = term.columns.clone().map(|j| j - offset).collect();
+            let others: Vec<usize> = all.iter().filter(|i| !own.contains(i)).copied().collect();
+            let vif = match (log_determinant(&own), log_determinant(&others), total) {
+                (Some(a), Some(b), Some(c)) => (a + b - c).exp(),
+                _ => f64::INFINITY,
+            };
+            let df = term.columns.len();
+            Collinearity {
+                term: term.name.clone(),
+                df,
+                vif,
+                tolerance: 1.0 / vif,
+                adjusted: vif.powf(1.0 / (2.0 * df as f64)),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{design::Coding, fixtures::mtcars, helper::roughly_equals, reader};
+
+    #[test]
+    fn test_linear_regression() {
+        // summary(lm(mpg ~ wt + hp, data = mtcars)) and confint()
+        let result = linear_regression(&mtcars(), "mpg", vec!["wt", "hp"]).unwrap();
+        let wt = &result.coefficients[1];
+        assert!(roughly_equals(
+            result.coefficients[0].estimate,
+            37.22727,
+            1e-5
+        ));
+        assert!(roughly_equals(wt.estimate, -3.87783, 1e-5));
+        assert!(roughly_equals(wt.std_error, 0.63273, 1e-5));
+        assert!(roughly_equals(wt.t, -6.129, 1e-3));
+        assert!(roughly_equals(wt.p_value, 1.12e-6, 1e-8));
+        assert!(roughly_equals(wt.ci_lower, -5.17191, 1e-5));
+        assert!(roughly_equals(wt.ci_upper, -2.58375, 1e-5));
+        assert!(roughly_equals(wt.standardized.unwrap(), -0.62955, 1e-5));
+        assert!(result.coefficients[0].standardized.is_none());
+
+        let hp = &result.coefficients[2];
+        assert!(roughly_equals(hp.p_value, 0.00145, 1e-5));
+
+        assert!(roughly_equals(result.r_squared, 0.8268, 1e-4));
+        assert!(roughly_equals(result.adj_r_squared, 0.8148, 1e-4));
+        assert!(roughly_equals(result.f_stat, 69.21, 1e-2));
+        assert_eq!((result.df_model, result.df_residual), (2, 29));
+        assert!(roughly_equals(result.residual_se, 2.593, 1e-3));
+        assert!(result.f_p_value < 1e-11);
+
+        let sum: f64 = result.residuals.iter().sum();
+        assert!(roughly_equals(sum, 0.0, 1e-9));
+        assert!(roughly_equals(
+            result.fitted[0] + result.residuals[0],
+            21.0,
+            1e-12
+        ));
+    }
+
+    #[test]
+    fn test_regression_formula() {
+        // summary(lm(mpg ~ wt * hp, data = mtcars))
+        let result = linear_regression_formula(&mtcars(), "mpg ~ wt * hp").unwrap();
+        let expected = [
+            ("(Intercept)", 49.80842, 3.60516),
+            ("wt", -8.21662, 1.26971),
+            ("hp", -0.12010, 0.02470),
+            ("wt:hp", 0.02785, 0.00742),
+        ];
+        for (coefficient, (name, estimate, std_error)) in result.coefficients.iter().zip(expected) {
+            assert_eq!(coefficient.name, name);
+            assert!(roughly_equals(coefficient.estimate, estimate, 1e-5));
+            assert!(roughly_equals(coefficient.std_error, std_error, 1e-5));
+        }
+        assert!(roughly_equals(result.r_squared, 0.8848, 1e-4));
+
+        let additive = linear_regression_formula(&mtcars(), "mpg ~ wt + hp").unwrap();
+        let direct = linear_regression(&mtcars(), "mpg", vec!["wt", "hp"]).unwrap();
+        assert!(roughly_equals(
+            additive.coefficients[1].estimate,
+            direct.coefficients[1].estimate,
+            1e-12
+        ));
+        assert!(linear_regression_formula(&mtcars(), "~ wt").is_err());
+    }
+
+    #[test]
+    fn test_categorical_predictors() {
+        // lm(score ~ condition + age + happiness) with the default and other codings
+        let df = reader::import_csv("samples/data3.csv").unwrap();
+        let result =
+            linear_regression(&df, "score", vec!["condition", "age", "happiness"]).unwrap();
+        let names: Vec<&str> = result
+            .coefficients
+            .iter()
+            .map(|c| c.name.as_str())
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "(Intercept)",
+                "condition[experimental]",
+                "condition[placebo]",
+                "age",
+                "happiness"
+            ]
+        );
+        assert!(roughly_equals(
+            result.coefficients[1].estimate,
+            14.185214,
+            1e-6
+        ));
+        assert!(roughly_equals(
+            result.coefficients[4].estimate,
+            0.204015,
+            1e-6
+        ));
+
+        let predictors = [
+            Predictor {
+                name: "condition",
+                coding: Coding::Treatment,
+                reference: Some("placebo"),
+            },
+            Predictor::new("age"),
+        ];
+        let result = linear_regression_design(&df, "score", &predictors).unwrap();
+        assert_eq!(result.coefficients[1].name, "condition[control]");
+
+        // Effect coding: deviations of the adjusted means from their average
+        let predictors = [
+            Predictor {
+                name: "condition",
+                coding: Coding::Effect,
+                reference: None,
+            },
+            Predictor::new("age"),
+            Predictor::new("happiness"),
+        ];
+        let result = linear_regression_design(&df, "score", &predictors).unwrap();
+        let means = [11.501136, 25.686350, 18.181487];
+        let average = means.iter().sum::<f64>() / 3.0;
+        assert!(roughly_equals(
+            result.coefficients[2].estimate,
+            means[1] - average,
+            1e-5
+        ));
+    }
+
+    #[test]
+    fn test_invalid_regression() {
+        let mut df = mtcars();
+        let wt = df.get_column("wt").unwrap().get_values_as_f64().unwrap();
+        df.add_numerical_column("wt_kg", wt.iter().map(|w| w * 453.59237).collect());
+
+        // A rescaled copy is aliased instead of breaking the fit
+        let result = linear_regression(&df, "mpg", vec!["wt", "wt_kg", "hp"]).unwrap();
+        assert_eq!(result.aliased, vec!["wt_kg".to_string()]);
+        assert_eq!(result.coefficients.len(), 3);
+        assert_eq!(result.coefficients[2].name, "hp");
+        assert!(roughly_equals(
+            result.coefficients[1].estimate,
+            -3.87783,
+            1e-5
+        ));
+        assert_eq!((result.df_model, result.df_residual), (2, 29));
+
+        assert!(linear_regression(&df, "mpg", vec!["missing"]).is_err());
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        // influence.measures(lm(mpg ~ wt + hp, data = mtcars)), Chrysler Imperial is row 17
+        let result = regression_diagnostics(&mtcars(), "mpg", vec!["wt", "hp"]).unwrap();
+        assert!(roughly_equals(result.leverage[16], 0.1864872, 1e-7));
+        assert!(roughly_equals(
+            result.standardized_residuals[16],
+            2.354517,
+            1e-6
+        ));
+        assert!(roughly_equals(
+            result.studentized_residuals[16],
+            2.572478,
+            1e-6
+        ));
+        assert!(roughly_equals(result.cooks_distance[16], 0.4236109, 1e-7));
+        let expected = [-0.9240568, 0.9355997, -0.1480098];
+        for (value, expected) in result.dfbetas[16].iter().zip(expected) {
+            assert!(roughly_equals(*value, expected, 1e-7));
+        }
+        let sum: f64 = result.leverage.iter().sum();
+        assert!(roughly_equals(sum, 3.0, 1e-9));
+
+        // vif(), durbinWatsonTest() and bptest()
+        assert!(roughly_equals(result.collinearity[0].vif, 1.766625, 1e-6));
+        assert!(roughly_equals(
+            result.collinearity[1].tolerance,
+            0.5660512,
+            1e-7
+        ));
+        assert!(roughly_equals(result.durbin_watson, 1.362399, 1e-6));
+        assert!(roughly_equals(
+            result.residual_autocorrelation,
+            0.2954091,
+            1e-7
+        ));
+        assert!(roughly_equals(
+            result.breusch_pagan.statistic,
+            0.8807225,
+            1e-7
+        ));
+        assert_eq!(result.breusch_pagan.df, 2);
+        assert!(roughly_equals(result.breusch_pagan.p_value, 0.6438, 1e-4));
+
+        let rows: Vec<usize> = result.outliers.iter().map(|o| o.row).collect();
+        assert_eq!(rows, vec![15, 16, 17, 19, 28, 30]);
+        assert!(roughly_equals(
+            result.outliers[3].bonferroni_p,
+            0.4652771,
+            1e-6
+        ));
+
+        // Generalized VIF of a factor with GVIF^(1 / (2 df))
+        let df = reader::import_csv("samples/data3.csv").unwrap();
+        let result =
+            regression_diagnostics_formula(&df, "score ~ condition + age + happiness").unwrap();
+        let condition = &result.collinearity[0];
+        assert_eq!((condition.term.as_str(), condition.df), ("condition", 2));
+        assert!(roughly_equals(condition.vif, 1.052644, 1e-6));
+        assert!(roughly_equals(condition.adjusted, 1.012909, 1e-6));
+        assert!(roughly_equals(result.collinearity[1].vif, 1.009779, 1e-6));
+    }
+
+    #[test]
+    fn test_unit_leverage() {
+        // A dummy of the first car fits it exactly, influence.measures(lm(mpg ~ wt + hp +
+        // first)) gives hat 1 and NaN for its residuals and influence
+        let mut df = mtcars();
+        df.add_numerical_column("first", (0..32).map(|i| (i == 0) as u8 as f64).collect());
+        let result = regression_diagnostics(&df, "mpg", vec!["wt", "hp", "first"]).unwrap();
+        assert_eq!(result.leverage[0], 1.0);
+        assert!(result.standardized_residuals[0].is_nan());
+        assert!(result.studentized_residuals[0].is_nan());
+        assert!(result.cooks_distance[0].is_nan());
+        assert!(result.dfbetas[0].iter().all(|v| v.is_nan()));
+        assert!((1..32)
+            .all(|i| result.studentized_residuals[i].is_finite()
+                && result.cooks_distance[i].is_finite()));
+        let sum: f64 = result.leverage.iter().sum();
+        assert!(roughly_equals(sum, 4.0, 1e-9));
+    }
+}
@@ -0,0 +1,1320 @@
+use crate::{
+    core::{
+        dataframe::{ColumnType, DataFrame},
+        design::{design_matrix, factor_levels, numeric_values, Predictor},
+        errors::DatasetError,
+        formula::{Expression, Formula},
+    },
+    distributions::{chisq, normal},
+    regression::helper::{cholesky, cholesky_solve, multiply, QrDecomposition},
+};
+
+const MAX_ITERATIONS: usize = 25; // as glm.control() in R
+const TOLERANCE: f64 = 1e-8;
+// Fitted probabilities this close to 0 or 1 indicate (quasi-)complete separation
+const SEPARATION_TOLERANCE: f64 = 1e-8;
+// Newton-Raphson for the multinomial and ordinal models
+const MAX_NEWTON_ITERATIONS: usize = 100;
+const NEWTON_TOLERANCE: f64 = 1e-10;
+
+#[derive(Debug)]
+pub struct LogisticCoefficient {
+    pub name: String,
+    pub estimate: f64, // log odds
+    pub std_error: f64,
+    pub z: f64, // Wald statistic
+    pub p_value: f64,
+    pub odds_ratio: f64,
+    pub or_ci_lower: f64, // 95% Wald confidence interval of the odds ratio
+    pub or_ci_upper: f64,
+}
+
+#[derive(Debug)]
+pub struct LogisticRegressionResult {
+    pub coefficients: Vec<LogisticCoefficient>,
+    pub aliased: Vec<String>,
+    pub event: Option<String>, // level coded 1 for a categorical outcome
+    pub deviance: f64,
+    pub null_deviance: f64,
+    pub log_likelihood: f64,
+    pub aic: f64,
+    pub bic: f64,
+    pub chi_square: f64, // likelihood-ratio test against the null model
+    pub df_model: usize,
+    pub df_residual: usize,
+    pub lr_p_value: f64,
+    pub mcfadden_r_squared: f64,
+    pub cox_snell_r_squared: f64,
+    pub nagelkerke_r_squared: f64,
+    pub fitted: Vec<f64>,            // predicted probabilities
+    pub residuals: Vec<f64>,         // response residuals y - p
+    pub model_matrix: Vec<Vec<f64>>, // estimable design columns, in the order of the coefficients
+    pub iterations: usize,
+    pub converged: bool,
+    // Fitted probabilities of 0 or 1 (or no convergence): the estimates and standard
+    // errors of some coefficients are diverging and should not be interpreted
+    pub separation: bool,
+    pub n: usize,
+}
+
+// Binary logistic regression with an intercept, like glm(y ~ x, family = binomial) in R.
+// The outcome is a binary column, a numerical 0/1 column or a categorical column with
+// two levels, where the second level in alphabetical order is the event
+pub fn logistic_regression(
+    df: &DataFrame,
+    dependent_var: &str,
+    predictors: Vec<&str>,
+) -> Result<LogisticRegressionResult, DatasetError> {
+    let predictors: Vec<Predictor> = predictors.into_iter().map(Predictor::new).collect();
+    logistic_regression_design(df, dependent_var, &predictors)
+}
+
+pub fn logistic_regression_design(
+    df: &DataFrame,
+    dependent_var: &str,
+    predictors: &[Predictor],
+) -> Result<LogisticRegressionResult, DatasetError> {
+    let (y, event) = binary_outcome(df, dependent_var)?;
+    let design = design_matrix(df, predictors, true)?;
+    let mut result = logistic(&design.rows, &y, &design.names, true)?;
+    result.event = event;
+    Ok(result)
+}
+
+// Logistic regression from a model formula such as "passed ~ hours + condition"
+pub fn logistic_regression_formula(
+    df: &DataFrame,
+    formula: &str,
+) -> Result<LogisticRegressionResult, DatasetError> {
+    let formula = Formula::parse(formula)?;
+    let (y, event) = match &formula.response {
+        Some(Expression::Variable(name)) => binary_outcome(df, name)?,
+        _ => (formula.response(df)?, None),
+    };
+    let design = formula.design_matrix(df)?;
+    let mut result = logistic(&design.rows, &y, &design.names, formula.intercept)?;
+    result.event = event;
+    Ok(result)
+}
+
+// Outcome coded 0/1 and, for a categorical column, the level coded 1
+pub(crate) fn binary_outcome(
+    df: &DataFrame,
+    name: &str,
+) -> Result<(Vec<f64>, Option<String>), DatasetError> {
+    if df.get_column(name)?.column_type() == ColumnType::Categorical {
+        let (levels, codes) = factor_levels(df, name)?;
+        if levels.len() != 2 {
+            return Err(DatasetError::InvalidData(format!(
+                "Outcome {} must have two levels, found {}",
+                name,
+                levels.len()
+            )));
+        }
+        let y = codes.iter().map(|&c| c as f64).collect();
+        return Ok((y, Some(levels[1].clone())));
+    }
+    Ok((numeric_values(df, name)?, None))
+}
+
+// Maximum likelihood fit by iteratively reweighted least squares on a design matrix
+// given by rows. With an intercept it must be the first column, without one the null
+// model has all probabilities at 1/2 as in R
+pub fn logistic(
+    design: &[Vec<f64>],
+    y: &[f64],
+    names: &[String],
+    intercept: bool,
+) -> Result<LogisticRegressionResult, DatasetError> {
+    let n = y.len();
+    let p = names.len();
+    if design.len() != n || design.iter().any(|row| row.len() != p) {
+        return Err(DatasetError::InvalidData(
+            "The design matrix does not match the observations.".to_string(),
+        ));
+    }
+    if y.iter().any(|&v| v != 0.0 && v != 1.0) {
+        return Err(DatasetError::InvalidData(
+            "The outcome of a logistic regression must be coded 0/1.".to_string(),
+        ));
+    }
+
+    // Aliased columns are dropped once, the weights do not change the rank
+    let qr = QrDecomposition::new(design)?;
+    let estimable: Vec<usize> = qr.pivot[..qr.rank].to_vec();
+    let rank = estimable.len();
+    if n <= rank {
+        return Err(DatasetError::InvalidData(format!(
+            "At least {} observations are needed for {} coefficients",
+            rank + 1,
+            rank
+        )));
+    }
+    let x: Vec<Vec<f64>> = design
+        .iter()
+        .map(|row| estimable.iter().map(|&j| row[j]).collect())
+        .collect();
+
+    let mut beta = vec![0.0; rank];
+    let mut fitted = vec![0.5; n];
+    let mut deviance = binomial_deviance(y, &fitted);
+    let mut iterations = 0;
+    let mut converged = false;
+    while iterations < MAX_ITERATIONS {
+        iterations += 1;
+
+        // Weighted least squares on the working response z = eta + (y - mu) / w
+        let mut weighted_rows = Vec::with_capacity(n);
+        let mut weighted_z = Vec::with_capacity(n);
+        for i in 0..n {
+            let eta: f64 = x[i].iter().zip(&beta).map(|(a, b)| a * b).sum();
+            let w = fitted[i] * (1.0 - fitted[i]);
+            let root = w.sqrt();
+            weighted_rows.push(x[i].iter().map(|v| v * root).collect::<Vec<f64>>());
+            weighted_z.push(eta * root + (y[i] - fitted[i]) / root);
+        }
+        let weighted_qr = QrDecomposition::new(&weighted_rows)?;
+        if weighted_qr.rank < rank {
+            break;
+        }
+        let estimates = weighted_qr.coefficients(&weighted_z);
+        for (k, &j) in weighted_qr.pivot[..rank].iter().enumerate() {
+            beta[j] = estimates[k];
+        }
+
+        fitted = x.iter().map(|row| probability(row, &beta)).collect();
+        let previous = deviance;
+        deviance = binomial_deviance(y, &fitted);
+        if (deviance - previous).abs() / (deviance.abs() + 0.1) < TOLERANCE {
+            converged = true;
+            break;
+        }
+    }
+
+    // Covariance from the information matrix at the final weights
+    let rows: Vec<Vec<f64>> = x
+        .iter()
+        .zip(&fitted)
+        .map(|(row, mu)| {
+            let root = (mu * (1.0 - mu)).sqrt();
+            row.iter().map(|v| v * root).collect()
+        })
+        .collect();
+    let information = QrDecomposition::new(&rows)?;
+    let mut std_errors = vec![f64::INFINITY; rank];
+    if information.rank == rank {
+        let covariance = information.unscaled_covariance();
+        for (k, &j) in information.pivot.iter().enumerate() {
+            std_errors[j] = covariance[k][k].sqrt();
+        }
+    }
+
+    let null_fitted = if intercept {
+        vec![y.iter().sum::<f64>() / n as f64; n]
+    } else {
+        vec![0.5; n]
+    };
+    let null_deviance = binomial_deviance(y, &null_fitted);
+    let df_model = if intercept { rank - 1 } else { rank };
+    let chi_square = null_deviance - deviance;
+    let (mcfadden_r_squared, cox_snell_r_squared, nagelkerke_r_squared) =
+        pseudo_r_squared(deviance, null_deviance, n);
+    let inside = SEPARATION_TOLERANCE..=1.0 - SEPARATION_TOLERANCE;
+    let separation = !converged || fitted.iter().any(|mu| !inside.contains(mu));
+
+    let coefficients = estimable
+        .iter()
+        .enumerate()
+        .map(|(k, &j)| wald_coefficient(&names[j], beta[k], std_errors[k]))
+        .collect();
+
+    let aliased = (0..p)
+        .filter(|j| !estimable.contains(j))
+        .map(|j| names[j].clone())
+        .collect();
+    Ok(LogisticRegressionResult {
+        coefficients,
+        aliased,
+        event: None,
+        deviance,
+        null_deviance,
+        log_likelihood: -deviance / 2.0,
+        aic: deviance + 2.0 * rank as f64,
+        bic: deviance + (n as f64).ln() * rank as f64,
+        chi_square,
+        df_model,
+        df_residual: n - rank,
+        lr_p_value: chisq::sf(chi_square, df_model as f64),
+        mcfadden_r_squared,
+        cox_snell_r_squared,
+        nagelkerke_r_squared,
+        residuals: y.iter().zip(&fitted).map(|(v, mu)| v - mu).collect(),
+        fitted,
+        model_matrix: x,
+        iterations,
+        converged,
+        separation,
+        n,
+    })
+}
+
+// Probabilities are kept away from 0 and 1 so the weights stay positive
+fn probability(row: &[f64], beta: &[f64]) -> f64 {
+    let eta: f64 = row.iter().zip(beta).map(|(a, b)| a * b).sum();
+    (1.0 / (1.0 + (-eta).exp())).clamp(f64::EPSILON, 1.0 - f64::EPSILON)
+}
+
+fn binomial_deviance(y: &[f64], fitted: &[f64]) -> f64 {
+    -2.0 * y
+        .iter()
+        .zip(fitted)
+        .map(|(&v, &mu)| if v == 1.0 { mu.ln() } else { (1.0 - mu).ln() })
+        .sum::<f64>()
+}
+
+// Wald test and 95% confidence interval of the odds ratio of a coefficient
+pub(crate) fn wald_coefficient(name: &str, estimate: f64, std_error: f64) -> LogisticCoefficient {
+    let critical = normal::quantile(0.975);
+    let z = estimate / std_error;
+    LogisticCoefficient {
+        name: name.to_string(),
+        estimate,
+        std_error,
+        z,
+        p_value: normal::p_value(z),
+        odds_ratio: estimate.exp(),
+        or_ci_lower: (estimate - critical * std_error).exp(),
+        or_ci_upper: (estimate + critical * std_error).exp(),
+    }
+}
+
+// McFadden, Cox-Snell and Nagelkerke R-squared
+fn pseudo_r_squared(deviance: f64, null_deviance: f64, n: usize) -> (f64, f64, f64) {
+    let cox_snell = 1.0 - (-(null_deviance - deviance) / n as f64).exp();
+    (
+        1.0 - deviance / null_deviance,
+        cox_snell,
+        cox_snell / (1.0 - (-null_deviance / n as f64).exp()),
+    )
+}
+
+// Coefficients of one outcome category against the reference category, the odds
+// ratios are relative risk ratios
+#[derive(Debug)]
+pub struct MultinomialEquation {
+    pub level: String,
+    pub coefficients: Vec<LogisticCoefficient>,
+}
+
+#[derive(Debug)]
+pub struct MultinomialRegressionResult {
+    pub levels: Vec<String>, // the first level is the reference category
+    pub equations: Vec<MultinomialEquation>,
+    pub aliased: Vec<String>,
+    pub deviance: f64,
+    pub null_deviance: f64,
+    pub log_likelihood: f64,
+    pub aic: f64,
+    pub bic: f64,
+    pub chi_square: f64,
+    pub df_model: usize,
+    pub lr_p_value: f64,
+    pub mcfadden_r_squared: f64,
+    pub cox_snell_r_squared: f64,
+    pub nagelkerke_r_squared: f64,
+    pub probabilities: Vec<Vec<f64>>, // predicted probability of every level per observation
+    pub iterations: usize,
+    pub converged: bool,
+    pub n: usize,
+}
+
+// A cut point between adjacent categories on the logit scale, named like "low|mid"
+#[derive(Debug)]
+pub struct Threshold {
+    pub name: String,
+    pub estimate: f64,
+    pub std_error: f64,
+    pub z: f64,
+}
+
+// Brant's Wald test of the proportional odds assumption, for all predictors together
+// ("Omnibus") and for every coefficient
+#[derive(Debug)]
+pub struct BrantTest {
+    pub term: String,
+    pub chi_square: f64,
+    pub df: usize,
+    pub p_value: f64,
+}
+
+#[derive(Debug)]
+pub struct OrdinalRegressionResult {
+    pub levels: Vec<String>, // from lowest to highest
+    pub thresholds: Vec<Threshold>,
+    // Positive coefficients shift the outcome to higher categories, the odds ratio is
+    // that of a higher against a lower or equal category
+    pub coefficients: Vec<LogisticCoefficient>,
+    pub aliased: Vec<String>,
+    pub deviance: f64,
+    pub null_deviance: f64,
+    pub log_likelihood: f64,
+    pub aic: f64,
+    pub bic: f64,
+    pub chi_square: f64,
+    pub df_model: usize,
+    pub lr_p_value: f64,
+    pub mcfadden_r_squared: f64,
+    pub cox_snell_r_squared: f64,
+    pub nagelkerke_r_squared: f64,
+    // Empty with two levels or without predictors, an error when a split cannot be fitted
+    pub brant: Result<Vec<BrantTest>, DatasetError>,
+    pub probabilities: Vec<Vec<f64>>,
+    pub iterations: usize,
+    pub converged: bool,
+    pub n: usize,
+}
+
+// Multinomial logistic regression against the first level of the outcome with an
+// intercept, like nnet::multinom in R. Categorical outcomes use their levels in
+// alphabetical order, numerical ones their sorted values
+pub fn multinomial_regression(
+    df: &DataFrame,
+    dependent_var: &str,
+    predictors: Vec<&str>,
+) -> Result<MultinomialRegressionResult, DatasetError> {
+    let (levels, outcome) = category_outcome(df, dependent_var, None)?;
+    let predictors: Vec<Predictor> = predictors.into_iter().map(Predictor::new).collect();
+    let design = design_matrix(df, &predictors, true)?;
+    multinomial(&design.rows, &outcome, &levels, &design.names, true)
+}
+
+pub fn multinomial_regression_formula(
+    df: &DataFrame,
+    formula: &str,
+) -> Result<MultinomialRegressionResult, DatasetError> {
+    let formula = Formula::parse(formula)?;
+    let (levels, outcome) = category_outcome(df, response_name(&formula)?, None)?;
+    let design = formula.design_matrix(df)?;
+    multinomial(
+        &design.rows,
+        &outcome,
+        &levels,
+        &design.names,
+        formula.intercept,
+    )
+}
+
+// Proportional odds (cumulative logit) model logit P(Y <= j) = threshold_j - x'b, like
+// MASS::polr in R. The order lists the levels from lowest to highest, by default they
+// are sorted as for multinomial_regression
+pub fn ordinal_regression(
+    df: &DataFrame,
+    dependent_var: &str,
+    predictors: Vec<&str>,
+    order: Option<Vec<&str>>,
+) -> Result<OrdinalRegressionResult, DatasetError> {
+    let (levels, outcome) = category_outcome(df, dependent_var, order.as_deref())?;
+    let predictors: Vec<Predictor> = predictors.into_iter().map(Predictor::new).collect();
+    let design = design_matrix(df, &predictors, false)?;
+    ordinal(&design.rows, &outcome, &levels, &design.names)
+}
+
+// The thresholds take the place of the intercept, so it is left out of the design
+pub fn ordinal_regression_formula(
+    df: &DataFrame,
+    formula: &str,
+    order: Option<Vec<&str>>,
+) -> Result<OrdinalRegressionResult, DatasetError> {
+    let formula = Formula::parse(formula)?;
+    let (levels, outcome) = category_outcome(df, response_name(&formula)?, order.as_deref())?;
+    let design = formula.design_matrix(df)?;
+    let skip = design.intercept as usize;
+    let rows: Vec<Vec<f64>> = design.rows.iter().map(|row| row[skip..].to_vec()).collect();
+    ordinal(&rows, &outcome, &levels, &design.names[skip..])
+}
+
+fn response_name(formula: &Formula) -> Result<&str, DatasetError> {
+    match &formula.response {
+        Some(Expression::Variable(name)) => Ok(name),
+        _ => Err(DatasetError::InvalidData(
+            "The response of the formula must be a column.".to_string(),
+        )),
+    }
+}
+
+// Levels of the outcome and the level index of every observation
+pub(crate) fn category_outcome(
+    df: &DataFrame,
+    name: &str,
+    order: Option<&[&str]>,
+) -> Result<(Vec<String>, Vec<usize>), DatasetError> {
+    let column = df.get_column(name)?;
+    let values: Vec<String> = match column.column_type() {
+        ColumnType::Categorical => column.get_values_as_str()?,
+        _ => numeric_values(df, name)?
+            .iter()
+            .map(|v| v.to_string())
+            .collect(),
+    };
+
+    let levels: Vec<String> = match order {
+        Some(order) => order.iter().map(|l| l.to_string()).collect(),
+        None if column.column_type() == ColumnType::Categorical => factor_levels(df, name)?.0,
+        None => {
+            let mut sorted = numeric_values(df, name)?;
+            sorted.sort_by(|a, b| a.total_cmp(b));
+            sorted.dedup();
+            sorted.iter().map(|v| v.to_string()).collect()
+        }
+    };
+    if levels.len() < 2 {
+        return Err(DatasetError::InvalidData(format!(
+            "Outcome {} needs at least two levels",
+            name
+        )));
+    }
+
+    let outcome = values
+        .iter()
+        .map(|value| {
+            levels.iter().position(|l| l == value).ok_or_else(|| {
+                DatasetError::InvalidData(format!("{} is not one of the listed levels", value))
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if let Some(level) = (0..levels.len()).find(|l| !outcome.contains(l)) {
+        return Err(DatasetError::InvalidData(format!(
+            "Level {} of {} does not occur",
+            levels[level], name
+        )));
+    }
+    Ok((levels, outcome))
+}
+
+// Maximum likelihood fit of the multinomial logit on a design matrix given by rows
+pub fn multinomial(
+    design: &[Vec<f64>],
+    outcome: &[usize],
+    levels: &[String],
+    names: &[String],
+    intercept: bool,
+) -> Result<MultinomialRegressionResult, DatasetError> {
+    let n = outcome.len();
+    let (x, estimable) = estimable_columns(design, names, n, intercept)?;
+    let p = estimable.len();
+    let k = levels.len();
+
+    // The parameters are stacked by outcome level: b[(j - 1) * p + c]
+    let objective = |beta: &[f64]| {
+        let mut log_likelihood = 0.0;
+        let mut gradient = vec![0.0; (k - 1) * p];
+        let mut information = vec![vec![0.0; (k - 1) * p]; (k - 1) * p];
+        for (row, &y) in x.iter().zip(outcome) {
+            let probabilities = softmax(row, beta, k);
+            log_likelihood += probabilities[y].ln();
+            for j in 1..k {
+                let residual = (y == j) as u8 as f64 - probabilities[j];
+                for c in 0..p {
+                    gradient[(j - 1) * p + c] += row[c] * residual;
+                }
+                for l in 1..k {
+                    let weight = probabilities[j] * ((j == l) as u8 as f64 - probabilities[l]);
+                    for c in 0..p {
+                        for d in 0..p {
+                            information[(j - 1) * p + c][(l - 1) * p + d] +=
+                                row[c] * row[d] * weight;
+                        }
+                    }
+                }
+            }
+        }
+        log_likelihood
+            .is_finite()
+            .then_some((log_likelihood, gradient, information))
+    };
+    let optimum = maximize(vec![0.0; (k - 1) * p], objective)?;
+
+    let equations = (1..k)
+        .map(|j| MultinomialEquation {
+            level: levels[j].clone(),
+            coefficients: (0..p)
+                .map(|c| {
+                    let index = (j - 1) * p + c;
+                    wald_coefficient(
+                        &names[estimable[c]],
+                        optimum.parameters[index],
+                        optimum.covariance[index][index].sqrt(),
+                    )
+                })
+                .collect(),
+        })
+        .collect();
+
+    let deviance = -2.0 * optimum.log_likelihood;
+    let null_deviance = if intercept {
+        null_category_deviance(outcome, k)
+    } else {
+        2.0 * n as f64 * (k as f64).ln()
+    };
+    let parameters = (k - 1) * p;
+    let df_model = (k - 1) * (p - intercept as usize);
+    let (mcfadden_r_squared, cox_snell_r_squared, nagelkerke_r_squared) =
+        pseudo_r_squared(deviance, null_deviance, n);
+    Ok(MultinomialRegressionResult {
+        levels: levels.to_vec(),
+        equations,
+        aliased: aliased_names(names, &estimable),
+        deviance,
+        null_deviance,
+        log_likelihood: optimum.log_likelihood,
+        aic: deviance + 2.0 * parameters as f64,
+        bic: deviance + (n as f64).ln() * parameters as f64,
+        chi_square: null_deviance - deviance,
+        df_model,
+        lr_p_value: chisq::sf(null_deviance - deviance, df_model as f64),
+        mcfadden_r_squared,
+        cox_snell_r_squared,
+        nagelkerke_r_squared,
+        probabilities: x
+            .iter()
+            .map(|row| softmax(row, &optimum.parameters, k))
+            .collect(),
+        iterations: optimum.iterations,
+        converged: optimum.converged,
+        n,
+    })
+}
+
+// Maximum likelihood fit of the proportional odds model on a design matrix without
+// an intercept column, with the outcome levels from lowest to highest
+pub fn ordinal(
+    design: &[Vec<f64>],
+    outcome: &[usize],
+    levels: &[String],
+    names: &[String],
+) -> Result<OrdinalRegressionResult, DatasetError> {
+    let n = outcome.len();
+    let k = levels.len();
+    let (x, estimable) = estimable_columns(design, names, n, false)?;
+    let p = estimable.len();
+
+    // Parameters are the k - 1 thresholds followed by the coefficients
+    let objective = |parameters: &[f64]| {
+        let (thresholds, beta) = parameters.split_at(k - 1);
+        if thresholds.windows(2).any(|t| t[0] >= t[1]) {
+            return None;
+        }
+
+        let size = k - 1 + p;
+        let mut log_likelihood = 0.0;
+        let mut gradient = vec![0.0; size];
+        let mut information = vec![vec![0.0; size]; size];
+        for (row, &y) in x.iter().zip(outcome) {
+            let eta: f64 = row.iter().zip(beta).map(|(a, b)| a * b).sum();
+            // Cut points above (a) and below (b) the observed category
+            let upper = if y == k - 1 {
+                CutPoint::upper_bound()
+            } else {
+                CutPoint::new(thresholds[y] - eta)
+            };
+            let lower = if y == 0 {
+                CutPoint::lower_bound()
+            } else {
+                CutPoint::new(thresholds[y - 1] - eta)
+            };
+            let probability = upper.cdf - lower.cdf;
+            if probability <= 0.0 {
+                return None;
+            }
+            log_likelihood += probability.ln();
+
+            // Derivatives of log P with respect to both cut points and eta = x'b,
+            // the information is the negative Hessian
+            let (fa, fb) = (upper.density / probability, lower.density / probability);
+            let (da, db) = (upper.slope / probability, lower.slope / probability);
+            let info_aa = fa * fa - da;
+            let info_bb = fb * fb + db;
+            let info_ab = -fa * fb;
+            let info_a_eta = da - fa * (fa - fb);
+            let info_b_eta = fb * (fa - fb) - db;
+            let info_eta_eta = (fa - fb).powi(2) - (da - db);
+
+            let mut cuts = Vec::with_capacity(2);
+            if y < k - 1 {
+                cuts.push((y, fa, info_aa, info_a_eta));
+            }
+            if y > 0 {
+                cuts.push((y - 1, -fb, info_bb, info_b_eta));
+            }
+            for &(j, first, second, mixed) in &cuts {
+                gradient[j] += first;
+                information[j][j] += second;
+                for c in 0..p {
+                    information[j][k - 1 + c] += row[c] * mixed;
+                    information[k - 1 + c][j] += row[c] * mixed;
+                }
+            }
+            if cuts.len() == 2 {
+                information[y][y - 1] += info_ab;
+                information[y - 1][y] += info_ab;
+            }
+            for c in 0..p {
+                gradient[k - 1 + c] -= row[c] * (fa - fb);
+                for d in 0..p {
+                    information[k - 1 + c][k - 1 + d] += row[c] * row[d] * info_eta_eta;
+                }
+            }
+        }
+        Some((log_likelihood, gradient, information))
+    };
+
+    // Start from the thresholds of the null model
+    let mut start = Vec::with_capacity(k - 1 + p);
+    let mut cumulative = 0.0;
+    for level in 0..k - 1 {
+        cumulative += outcome.iter().filter(|&&y| y == level).count() as f64 / n as f64;
+        start.push((cumulative / (1.0 - cumulative)).ln());
+    }
+    start.extend(vec![0.0; p]);
+    let optimum = maximize(start, objective)?;
+    let (thresholds, beta) = optimum.parameters.split_at(k - 1);
+
+    let threshold_results = (0..k - 1)
+        .map(|j| {
+            let std_error = optimum.covariance[j][j].sqrt();
+            Threshold {
+                name: format!("{}|{}", levels[j], levels[j + 1]),
+                estimate: thresholds[j],
+                std_error,
+                z: thresholds[j] / std_error,
+            }
+        })
+        .collect();
+    let coefficients = (0..p)
+        .map(|c| {
+            let index = k - 1 + c;
+            wald_coefficient(
+                &names[estimable[c]],
+                beta[c],
+                optimum.covariance[index][index].sqrt(),
+            )
+        })
+        .collect();
+    let probabilities = x
+        .iter()
+        .map(|row| {
+            let eta: f64 = row.iter().zip(beta).map(|(a, b)| a * b).sum();
+            let cumulative: Vec<f64> = thresholds
+                .iter()
+                .map(|t| logistic_cdf(t - eta))
+                .chain([1.0])
+                .collect();
+            (0..k)
+                .map(|j| cumulative[j] - if j == 0 { 0.0 } else { cumulative[j - 1] })
+                .collect()
+        })
+        .collect();
+
+    let deviance = -2.0 * optimum.log_likelihood;
+    let null_deviance = null_category_deviance(outcome, k);
+    let parameters = k - 1 + p;
+    let (mcfadden_r_squared, cox_snell_r_squared, nagelkerke_r_squared) =
+        pseudo_r_squared(deviance, null_deviance, n);
+    let coefficient_names: Vec<String> = estimable.iter().map(|&j| names[j].clone()).collect();
+    Ok(OrdinalRegressionResult {
+        levels: levels.to_vec(),
+        thresholds: threshold_results,
+        coefficients,
+        aliased: aliased_names(names, &estimable),
+        deviance,
+        null_deviance,
+        log_likelihood: optimum.log_likelihood,
+        aic: deviance + 2.0 * parameters as f64,
+        bic: deviance + (n as f64).ln() * parameters as f64,
+        chi_square: null_deviance - deviance,
+        df_model: p,
+        lr_p_value: chisq::sf(null_deviance - deviance, p as f64),
+        mcfadden_r_squared,
+        cox_snell_r_squared,
+        nagelkerke_r_squared,
+        brant: brant_test(&x, outcome, k, &coefficient_names),
+        probabilities,
+        iterations: optimum.iterations,
+        converged: optimum.converged,
+        n,
+    })
+}
+
+// Non-aliased columns of the design, an intercept column is added for the rank
+// check of the ordinal model whose thresholds act as intercepts
+fn estimable_columns(
+    design: &[Vec<f64>],
+    names: &[String],
+    n: usize,
+    intercept: bool,
+) -> Result<(Vec<Vec<f64>>, Vec<usize>), DatasetError> {
+    let p = names.len();
+    if design.len() != n || design.iter().any(|row| row.len() != p) {
+        return Err(DatasetError::InvalidData(
+            "The design matrix does not match the observations.".to_string(),
+        ));
+    }
+
+    let estimable: Vec<usize> = if intercept || p == 0 {
+        let qr = QrDecomposition::new(design)?;
+        qr.pivot[..qr.rank].to_vec()
+    } else {
+        let rows: Vec<Vec<f64>> = design
+            .iter()
+            .map(|row| [1.0].into_iter().chain(row.iter().copied()).collect())
+            .collect();
+        let qr = QrDecomposition::new(&rows)?;
+        qr.pivot[..qr.rank]
+            .iter()
+            .filter(|&&j| j > 0)
+            .map(|&j| j - 1)
+            .collect()
+    };
+    let x = design
+        .iter()
+        .map(|row| estimable.iter().map(|&j| row[j]).collect())
+        .collect();
+    Ok((x, estimable))
+}
+
+fn aliased_names(names: &[String], estimable: &[usize]) -> Vec<String> {
+    (0..names.len())
+        .filter(|j| !estimable.contains(j))
+        .map(|j| names[j].clone())
+        .collect()
+}
+
+// Category probabilities of the multinomial logit, the first level has eta = 0
+fn softmax(row: &[f64], beta: &[f64], levels: usize) -> Vec<f64> {
+    let p = row.len();
+    let etas: Vec<f64> = (0..levels)
+        .map(|j| match j {
+            0 => 0.0,
+            _ => row
+                .iter()
+                .zip(&beta[(j - 1) * p..j * p])
+                .map(|(a, b)| a * b)
+                .sum(),
+        })
+        .collect();
+    let max = etas.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let total: f64 = etas.iter().map(|eta| (eta - max).exp()).sum();
+    etas.iter().map(|eta| (eta - max).exp() / total).collect()
+}
+
+// Deviance of the model with only the category proportions
+fn null_category_deviance(outcome: &[usize], levels: usize) -> f64 {
+    let n = outcome.len() as f64;
+    -2.0 * (0..levels)
+        .map(|level| {
+            let count = outcome.iter().filter(|&&y| y == level).count() as f64;
+            count * (count / n).ln()
+        })
+        .sum::<f64>()
+}
+
+fn logistic_cdf(z: f64) -> f64 {
+    1.0 / (1.0 + (-z).exp())
+}
+
+// Logistic distribution function, density and derivative of the density at a cut point
+struct CutPoint {
+    cdf: f64,
+    density: f64,
+    slope: f64,
+}
+
+impl CutPoint {
+    fn new(z: f64) -> CutPoint {
+        let cdf = logistic_cdf(z);
+        let density = cdf * (1.0 - cdf);
+        CutPoint {
+            cdf,
+            density,
+            slope: density * (1.0 - 2.0 * cdf),
+        }
+    }
+
+    fn lower_bound() -> CutPoint {
+        CutPoint {
+            cdf: 0.0,
+            density: 0.0,
+            slope: 0.0,
+        }
+    }
+
+    fn upper_bound() -> CutPoint {
+        CutPoint {
+            cdf: 1.0,
+            density: 0.0,
+            slope: 0.0,
+        }
+    }
+}
+
+pub(crate) struct Optimum {
+    pub(crate) parameters: Vec<f64>,
+    pub(crate) log_likelihood: f64,
+    pub(crate) covariance: Vec<Vec<f64>>, // inverse of the information matrix
+    pub(crate) iterations: usize,
+    pub(crate) converged: bool,
+}
+
+// Newton-Raphson with step halving for an objective returning the log-likelihood, its
+// gradient and the information matrix, or None outside of the parameter space
+pub(crate) fn maximize<F>(start: Vec<f64>, objective: F) -> Result<Optimum, DatasetError>
+where
+    F: Fn(&[f64]) -> Option<(f64, Vec<f64>, Vec<Vec<f64>>)>,
+{
+    let invalid = || {
+        DatasetError::InvalidData(
+            "The likelihood cannot be evaluated at the starting values.".to_string(),
+        )
+    };
+    let mut parameters = start;
+    let (mut log_likelihood, mut gradient, mut information) =
+        objective(&parameters).ok_or_else(invalid)?;
+    let mut iterations = 0;
+    let mut converged = false;
+
+    while iterations < MAX_NEWTON_ITERATIONS {
+        iterations += 1;
+        let Ok(lower) = cholesky(&information) else {
+            break;
+        };
+        let step = cholesky_solve(&lower, &gradient);
+
+        let mut scale = 1.0;
+        let mut accepted = None;
+        for _ in 0..30 {
+            let candidate: Vec<f64> = parameters
+                .iter()
+                .zip(&step)
+                .map(|(a, b)| a + scale * b)
+                .collect();
+            if let Some(value) = objective(&candidate) {
+                if value.0 >= log_likelihood - 1e-12 * log_likelihood.abs() {
+                    accepted = Some((candidate, value));
+                    break;
+                }
+            }
+            scale /= 2.0;
+        }
+        let Some((candidate, value)) = accepted else {
+            break;
+        };
+
+        let change = (value.0 - log_likelihood).abs() / (2.0 * value.0.abs() + 0.1);
+        parameters = candidate;
+        (log_likelihood, gradient, information) = value;
+        if change < NEWTON_TOLERANCE {
+            converged = true;
+            break;
+        }
+    }
+
+    Ok(Optimum {
+        covariance: inverse(&information)?,
+        parameters,
+        log_likelihood,
+        iterations,
+        converged,
+    })
+}
+
+pub(crate) fn inverse(matrix: &[Vec<f64>]) -> Result<Vec<Vec<f64>>, DatasetError> {
+    let lower = cholesky(matrix).map_err(|_| {
+        DatasetError::InvalidData(
+            "The information matrix is singular, check for separation or empty categories."
+                .to_string(),
+        )
+    })?;
+    let n = matrix.len();
+    let mut result = vec![vec![0.0; n]; n];
+    for j in 0..n {
+        let mut unit = vec![0.0; n];
+        unit[j] = 1.0;
+        for (i, value) in cholesky_solve(&lower, &unit).into_iter().enumerate() {
+            result[i][j] = value;
+        }
+    }
+    Ok(result)
+}
+
+// Brant (1990): separate binary logistic regressions of Y > j for every cut point, whose
+// slopes are equal under proportional odds. The Wald statistic compares the slopes of
+// the first split with those of every other split
+#[allow(clippy::needless_range_loop)]
+fn brant_test(
+    x: &[Vec<f64>],
+    outcome: &[usize],
+    levels: usize,
+    names: &[String],
+) -> Result<Vec<BrantTest>, DatasetError> {
+    let splits = levels - 1;
+    let q = names.len();
+    if splits < 2 || q == 0 {
+        return Ok(Vec::new());
+    }
+
+    let rows: Vec<Vec<f64>> = x
+        .iter()
+        .map(|row| [1.0].into_iter().chain(row.iter().copied()).collect())
+        .collect();
+    let row_names: Vec<String> = ["(Intercept)".to_string()]
+        .into_iter()
+        .chain(names.iter().cloned())
+        .collect();
+    let mut slopes = Vec::with_capacity(splits);
+    let mut fitted = Vec::with_capacity(splits);
+    for j in 0..splits {
+        let y: Vec<f64> = outcome.iter().map(|&v| (v > j) as u8 as f64).collect();
+        let fit = logistic(&rows, &y, &row_names, true)?;
+        if !fit.aliased.is_empty() || fit.separation {
+            return Err(DatasetError::InvalidData(format!(
+                "The split at {} cannot be fitted.",
+                j
+            )));
+        }
+        slopes.push(
+            fit.coefficients[1..]
+                .iter()
+                .map(|c| c.estimate)
+                .collect::<Vec<f64>>(),
+        );
+        fitted.push(fit.fitted);
+    }
+
+    // Cov(b_j, b_l) = (X'W_j X)^-1 X'W_jl X (X'W_l X)^-1 with W_jl = pi_l - pi_j pi_l
+    // for j <= l, restricted to the slopes
+    let cross_product = |weights: &[f64]| {
+        let p = q + 1;
+        let mut matrix = vec![vec![0.0; p]; p];
+        for (row, w) in rows.iter().zip(weights) {
+            for a in 0..p {
+                for b in 0..p {
+                    matrix[a][b] += row[a] * row[b] * w;
+                }
+            }
+        }
+        matrix
+    };
+    let inverses = (0..splits)
+        .map(|j| {
+            let weights: Vec<f64> = fitted[j].iter().map(|pi| pi * (1.0 - pi)).collect();
+            inverse(&cross_product(&weights))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut covariance = vec![vec![0.0; splits * q]; splits * q];
+    for j in 0..splits {
+        for l in j..splits {
+            let weights: Vec<f64> = fitted[j]
+                .iter()
+                .zip(&fitted[l])
+                .map(|(pj, pl)| pl - pj * pl)
+                .collect();
+            let block = multiply(
+                &multiply(&inverses[j], &cross_product(&weights)),
+                &inverses[l],
+            );
+            for a in 0..q {
+                for b in 0..q {
+                    covariance[j * q + a][l * q + b] = block[a + 1][b + 1];
+                    covariance[l * q + b][j * q + a] = block[a + 1][b + 1];
+                }
+            }
+        }
+    }
+
+    let wald = |columns: &[usize]| -> Result<BrantTest, DatasetError> {
+        // Differences b_1 - b_j for j = 2..splits
+        let index = |split: usize, c: usize| split * q + columns[c];
+        let size = (splits - 1) * columns.len();
+        let mut difference = vec![0.0; size];
+        let mut variance = vec![vec![0.0; size]; size];
+        for r in 0..splits - 1 {
+            for (a, &column) in columns.iter().enumerate() {
+                difference[r * columns.len() + a] = slopes[0][column] - slopes[r + 1][column];
+            }
+        }
+        for r in 0..splits - 1 {
+            for s in 0..splits - 1 {
+                for a in 0..columns.len() {
+                    for b in 0..columns.len() {
+                        let cov = |u: usize, v: usize| covariance[index(u, a)][index(v, b)];
+                        variance[r * columns.len() + a][s * columns.len() + b] =
+                            cov(0, 0) - cov(0, s + 1) - cov(r + 1, 0) + cov(r + 1, s + 1);
+                    }
+                }
+            }
+        }
+        let lower = cholesky(&variance)?;
+        let solved = cholesky_solve(&lower, &difference);
+        let chi_square: f64 = difference.iter().zip(&solved).map(|(a, b)| a * b).sum();
+        Ok(BrantTest {
+            term: String::new(),
+            chi_square,
+            df: size,
+            p_value: chisq::sf(chi_square, size as f64),
+        })
+    };
+
+    let mut tests = vec![BrantTest {
+        term: "Omnibus".to_string(),
+        ..wald(&(0..q).collect::<Vec<_>>())?
+    }];
+    for (c, name) in names.iter().enumerate() {
+        tests.push(BrantTest {
+            term: name.clone(),
+            ..wald(&[c])?
+        });
+    }
+    Ok(tests)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{design::DesignMatrix, fixtures::mtcars, helper::roughly_equals, reader};
+
+    #[test]
+    fn test_logistic_regression() {
+        // summary(glm(am ~ wt + hp, family = binomial, data = mtcars))
+        let result = logistic_regression(&mtcars(), "am", vec!["wt", "hp"]).unwrap();
+        assert!(result.converged && !result.separation);
+        let expected = [
+            (18.86630, 7.44356, 0.01126),
+            (-8.08348, 3.06868, 0.00843),
+            (0.03626, 0.01773, 0.04091),
+        ];
+        for (coefficient, (estimate, std_error, p_value)) in
+            result.coefficients.iter().zip(expected)
+        {
+            assert!(roughly_equals(coefficient.estimate, estimate, 1e-5));
+            assert!(roughly_equals(coefficient.std_error, std_error, 1e-5));
+            assert!(roughly_equals(coefficient.p_value, p_value, 1e-5));
+        }
+
+        let wt = &result.coefficients[1];
+        assert!(roughly_equals(wt.odds_ratio, 0.000308597, 1e-9));
+        assert!(roughly_equals(wt.or_ci_lower, 7.539286e-7, 1e-12));
+        assert!(roughly_equals(wt.or_ci_upper, 0.1263143, 1e-7));
+
+        assert!(roughly_equals(result.null_deviance, 43.22973, 1e-5));
+        assert!(roughly_equals(result.deviance, 10.05911, 1e-5));
+        assert!(roughly_equals(result.aic, 16.05911, 1e-5));
+        assert!(roughly_equals(result.bic, 20.45632, 1e-5));
+        assert!(roughly_equals(result.chi_square, 33.17062, 1e-5));
+        assert_eq!((result.df_model, result.df_residual), (2, 29));
+        assert!(result.lr_p_value < 1e-7);
+        assert!(roughly_equals(result.mcfadden_r_squared, 0.767310, 1e-6));
+        assert!(roughly_equals(result.cox_snell_r_squared, 0.645335, 1e-6));
+        assert!(roughly_equals(result.nagelkerke_r_squared, 0.870897, 1e-6));
+
+        let formula = logistic_regression_formula(&mtcars(), "am ~ wt + hp").unwrap();
+        assert!(roughly_equals(formula.deviance, result.deviance, 1e-9));
+    }
+
+    #[test]
+    fn test_categorical_outcome() {
+        let mut df = mtcars();
+        let am = numeric_values(&df, "am").unwrap();
+        let labels = am
+            .iter()
+            .map(|&v| if v == 1.0 { "manual" } else { "automatic" });
+        df.add_categorical_column("transmission", labels.map(str::to_string).collect());
+
+        let result = logistic_regression(&df, "transmission", vec!["wt", "hp"]).unwrap();
+        assert_eq!(result.event.as_deref(), Some("manual"));
+        assert!(roughly_equals(result.deviance, 10.05911, 1e-5));
+        assert!(logistic_regression(&df, "wt", vec!["hp"]).is_err());
+    }
+
+    #[test]
+    fn test_separation() {
+        let mut df = DataFrame::new();
+        df.add_numerical_column("x", vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        df.add_numerical_column("y", vec![0.0, 0.0, 0.0, 1.0, 1.0, 1.0]);
+        let result = logistic_regression(&df, "y", vec!["x"]).unwrap();
+        assert!(result.separation);
+        assert!(result.deviance < 1e-6);
+    }
+
+    fn likert() -> DataFrame {
+        let mut df = DataFrame::new();
+        df.add_numerical_column(
+            "x",
+            vec![
+                3.2, 1.5, 6.5, 0.7, 5.4, 3.7, 0.6, 5.1, 0.4, 4.3, 0.7, 0.9, 4.2, 8.3, 1.2, 2.2,
+                6.3, 9.5, 5.8, 4.0, 9.8, 0.5, 8.6, 2.9, 1.4, 1.2, 3.1, 8.2, 1.8, 5.8, 6.4, 3.7,
+                5.5, 0.6, 0.6, 2.1, 6.8, 4.3, 3.1, 5.9,
+            ],
+        );
+        let g = [
+            1, 1, 0, 0, 1, 0, 0, 0, 0, 1, 0, 1, 1, 0, 1, 1, 1, 0, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 1,
+            0, 1, 0, 0, 0, 0, 1, 1, 0, 1, 1,
+        ];
+        df.add_binary_column("g", g.iter().map(|&v| v == 1).collect());
+        let y = [
+            "low", "low", "low", "low", "low", "low", "low", "mid", "low", "mid", "low", "mid",
+            "mid", "high", "low", "low", "mid", "high", "high", "low", "mid", "low", "mid", "low",
+            "low", "low", "low", "mid", "low", "mid", "high", "mid", "mid", "low", "low", "low",
+            "high", "mid", "mid", "high",
+        ];
+        df.add_categorical_column("y", y.iter().map(|v| v.to_string()).collect());
+        df
+    }
+
+    // MASS housing with one row per respondent (the Freq weights), satisfaction against
+    // influence, housing type and contact with the levels of the MASS book as reference
+    fn housing(intercept: bool) -> (DesignMatrix, Vec<String>, Vec<usize>, DataFrame) {
+        let df = reader::import_csv("samples/housing.csv").unwrap();
+        let (levels, outcome) =
+            category_outcome(&df, "Sat", Some(&["Low", "Medium", "High"])).unwrap();
+        let predictor = |name, reference| Predictor {
+            reference: Some(reference),
+            ..Predictor::new(name)
+        };
+        let predictors = [
+            predictor("Infl", "Low"),
+            predictor("Type", "Tower"),
+            predictor("Cont", "Low"),
+        ];
+        let design = design_matrix(&df, &predictors, intercept).unwrap();
+        (design, levels, outcome, df)
+    }
+
+    #[test]
+    fn test_multinomial_regression() {
+        // multinom(Sat ~ Infl + Type + Cont, weights = Freq, data = housing), nnet stops
+        // at a relative tolerance of 1e-8 so its estimates agree to about 1e-4
+        let (design, levels, outcome, df) = housing(true);
+        let result = multinomial(&design.rows, &outcome, &levels, &design.names, true).unwrap();
+        assert!(result.converged);
+        assert_eq!(result.equations[0].level, "Medium");
+
+        let expected = [
+            [
+                ("(Intercept)", -0.41923),
+                ("Infl[Medium]", 0.44644),
+                ("Infl[High]", 0.66497),
+                ("Type[Apartment]", -0.43564),
+                ("Type[Atrium]", 0.13134),
+                ("Type[Terrace]", -0.66658),
+                ("Cont[High]", 0.36085),
+            ],
+            [
+                ("(Intercept)", -0.13871),
+                ("Infl[Medium]", 0.73488),
+                ("Infl[High]", 1.61264),
+                ("Type[Apartment]", -0.73566),
+                ("Type[Atrium]", -0.40794),
+                ("Type[Terrace]", -1.41234),
+                ("Cont[High]", 0.48188),
+            ],
+        ];
+        for (equation, expected) in result.equations.iter().zip(expected) {
+            for (name, estimate) in expected {
+                let coefficient = equation
+                    .coefficients
+                    .iter()
+                    .find(|c| c.name == name)
+                    .unwrap();
+                assert!(roughly_equals(coefficient.estimate, estimate, 1e-4));
+            }
+        }
+        // Residual deviance 3470.084 and AIC 3498.084
+        assert!(roughly_equals(result.deviance, 3470.084, 1e-3));
+        assert!(roughly_equals(result.aic, 3498.084, 1e-3));
+        assert_eq!(result.df_model, 12);
+
+        for probabilities in &result.probabilities {
+            assert!(roughly_equals(probabilities.iter().sum(), 1.0, 1e-12));
+        }
+        // Other reference levels and level orders give the same fit
+        let formula = multinomial_regression_formula(&df, "Sat ~ Infl + Type + Cont").unwrap();
+        assert!(roughly_equals(formula.deviance, result.deviance, 1e-6));
+        let direct = multinomial_regression(&likert(), "y", vec!["x", "g"]).unwrap();
+        assert_eq!(direct.levels, vec!["high", "low", "mid"]);
+    }
+
+    #[test]
+    fn test_ordinal_regression() {
+        // summary(polr(Sat ~ Infl + Type + Cont, weights = Freq, data = housing))
+        let (design, levels, outcome, _) = housing(false);
+        let result = ordinal(&design.rows, &outcome, &levels, &design.names).unwrap();
+        assert!(result.converged);
+        assert_eq!(result.thresholds[0].name, "Low|Medium");
+
+        let thresholds = [(-0.4961353, 0.1248), (0.6907083, 0.1255)];
+        for (threshold, (estimate, std_error)) in result.thresholds.iter().zip(thresholds) {
+            assert!(roughly_equals(threshold.estimate, estimate, 1e-6));
+            assert!(roughly_equals(threshold.std_error, std_error, 1e-4));
+        }
+        let coefficients = [
+            ("Infl[Medium]", 0.5663937, 0.1047),
+            ("Infl[High]", 1.2888191, 0.1272),
+            ("Type[Apartment]", -0.5723501, 0.1192),
+            ("Type[Atrium]", -0.3661866, 0.1552),
+            ("Type[Terrace]", -1.0910149, 0.1515),
+            ("Cont[High]", 0.3602841, 0.09554),
+        ];
+        for (name, estimate, std_error) in coefficients {
+            let coefficient = result.coefficients.iter().find(|c| c.name == name).unwrap();
+            assert!(roughly_equals(coefficient.estimate, estimate, 1e-6));
+            assert!(roughly_equals(coefficient.std_error, std_error, 1e-4));
+        }
+        // Residual deviance 3479.149 and AIC 3495.149
+        assert!(roughly_equals(result.deviance, 3479.149, 1e-3));
+        assert!(roughly_equals(result.aic, 3495.149, 1e-3));
+
+        // Brant test from the binary logits of Sat > Low and Sat > Medium, as brant()
+        // computes it for this fit
+        let brant = result.brant.as_ref().unwrap();
+        assert_eq!(brant.len(), 7);
+        assert_eq!((brant[0].term.as_str(), brant[0].df), ("Omnibus", 6));
+        let expected = [
+            ("Omnibus", 8.534494001, 0.2014993900),
+            ("Infl[High]", 0.1965044745, 0.6575572520),
+            ("Infl[Medium]", 0.3173382643, 0.5732116586),
+            ("Type[Apartment]", 0.4681518543, 0.4938386328),
+            ("Type[Atrium]", 2.804752342, 0.09398535677),
+            ("Type[Terrace]", 0.03122597554, 0.8597371826),
+            ("Cont[High]", 1.285940335, 0.2567974452),
+        ];
+        for (test, (term, chi_square, p_value)) in brant.iter().zip(expected) {
+            assert_eq!(test.term, term);
+            assert!(roughly_equals(test.chi_square, chi_square, 1e-6));
+            assert!(roughly_equals(test.p_value, p_value, 1e-6));
+        }
+
+        let first = &result.probabilities[0];
+        assert!(roughly_equals(first.iter().sum(), 1.0, 1e-12));
+
+        // Alphabetical order puts high below low, the fit differs
+        let order = Some(vec!["low", "mid", "high"]);
+        let ordered = ordinal_regression(&likert(), "y", vec!["x", "g"], order).unwrap();
+        let formula = ordinal_regression_formula(&likert(), "y ~ x + g", None).unwrap();
+        assert!(formula.deviance > ordered.deviance);
+        let wrong = Some(vec!["low", "mid"]);
+        assert!(ordinal_regression(&likert(), "y", vec!["x"], wrong).is_err());
+    }
+
+    #[test]
+    fn test_brant_separation() {
+        // A predictor equal to y > mid separates the second split, the model is still
+        // fitted but the Brant test is an error
+        let mut df = likert();
+        let (levels, codes) = factor_levels(&df, "y").unwrap();
+        assert_eq!(levels[0], "high");
+        df.add_binary_column("top", codes.iter().map(|&c| c == 0).collect());
+        let order = Some(vec!["low", "mid", "high"]);
+        let result = ordinal_regression(&df, "y", vec!["x", "top"], order).unwrap();
+        assert!(result.brant.is_err());
+    }
+}
@@ -1,3 +1,6 @@
 pub mod correlation;
+pub mod hierarchical;
 pub mod linear;
 pub mod logistic;
+pub mod moderation;
+pub mod robust;
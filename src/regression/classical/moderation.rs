@@ -0,0 +1,325 @@
+use crate::{
+    core::{
+        dataframe::DataFrame,
+        design::{design_matrix, numeric_values, Predictor},
+        errors::DatasetError,
+        helper::{mean, sample_variance},
+    },
+    distributions::{fdist, tdist},
+    regression::{
+        classical::linear::{ols, LinearRegressionResult},
+        helper::QrDecomposition,
+    },
+};
+
+#[derive(Debug, Clone)]
+pub struct ModerationOptions<'a> {
+    pub covariates: Vec<&'a str>, // added to the model without interactions
+    // Mean-center the focal predictor and the moderator before forming their product
+    pub center: bool,
+    // Moderator values for the simple slopes, the mean and +/- 1 SD when None
+    pub moderator_values: Option<Vec<f64>>,
+}
+
+impl Default for ModerationOptions<'_> {
+    fn default() -> Self {
+        ModerationOptions {
+            covariates: Vec::new(),
+            center: true,
+            moderator_values: None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct SimpleSlope {
+    pub moderator_value: f64, // in the original units of the moderator
+    pub slope: f64,
+    pub std_error: f64,
+    pub t: f64,
+    pub p_value: f64,
+    pub ci_lower: f64, // 95% confidence interval
+    pub ci_upper: f64,
+}
+
+#[derive(Debug)]
+pub struct JohnsonNeyman {
+    // Moderator values where the slope of the focal predictor is just significant
+    pub bounds: Vec<f64>,
+    // Ranges of moderator values with a significant slope, possibly unbounded
+    pub significant: Vec<(f64, f64)>,
+    pub critical_t: f64,
+    pub observed_range: (f64, f64), // of the moderator
+}
+
+#[derive(Debug)]
+pub struct ModerationResult {
+    // Coefficients of the intercept, predictor, moderator, covariates and the product
+    // term named "predictor:moderator", as lm(y ~ x * m + covariates) in R
+    pub model: LinearRegressionResult,
+    pub r_squared_change: f64, // due to the interaction
+    pub f_change: f64,
+    pub f_change_p_value: f64,
+    pub simple_slopes: Vec<SimpleSlope>,
+    pub johnson_neyman: JohnsonNeyman,
+    pub centered: bool,
+}
+
+// Moderation of the effect of a numerical predictor on the dependent variable by a
+// numerical moderator, fitted with their product term. Moderator values are reported in
+// their original units whether or not the variables are centered
+pub fn moderation(
+    df: &DataFrame,
+    dependent_var: &str,
+    predictor: &str,
+    moderator: &str,
+    options: &ModerationOptions,
+) -> Result<ModerationResult, DatasetError> {
+    let y = df.get_column(dependent_var)?.get_values_as_f64()?;
+    let x = numeric_values(df, predictor)?;
+    let m = numeric_values(df, moderator)?;
+    let predictors: Vec<Predictor> = options
+        .covariates
+        .iter()
+        .map(|&name| Predictor::new(name))
+        .collect();
+    let covariates = design_matrix(df, &predictors, false)?;
+    if x.len() != y.len() || m.len() != y.len() || covariates.n() != y.len() {
+        return Err(DatasetError::InvalidData(
+            "All variables must have the same number of observations.".to_string(),
+        ));
+    }
+
+    let (x_center, m_center) = if options.center {
+        (mean(&x), mean(&m))
+    } else {
+        (0.0, 0.0)
+    };
+    let rows: Vec<Vec<f64>> = (0..y.len())
+        .map(|i| {
+            let (xi, mi) = (x[i] - x_center, m[i] - m_center);
+            let mut row = vec![1.0, xi, mi];
+            row.extend(&covariates.rows[i]);
+            row.push(xi * mi);
+            row
+        })
+        .collect();
+    let mut names = vec![
+        "(Intercept)".to_string(),
+        predictor.to_string(),
+        moderator.to_string(),
+    ];
+    names.extend(covariates.names);
+    names.push(format!("{}:{}", predictor, moderator));
+
+    let p = names.len();
+    let qr = QrDecomposition::new(&rows)?;
+    if qr.rank < p {
+        return Err(DatasetError::InvalidData(
+            "The predictor, moderator and covariates are linearly dependent.".to_string(),
+        ));
+    }
+    let model = ols(&rows, &y, &names, true)?;
+    let additive_rows: Vec<Vec<f64>> = rows.iter().map(|row| row[..p - 1].to_vec()).collect();
+    let additive = ols(&additive_rows, &y, &names[..p - 1], true)?;
+
+    // Covariance of the predictor and product coefficients
+    let unscaled = qr.unscaled_covariance();
+    let position = |j: usize| qr.pivot.iter().position(|&k| k == j).unwrap();
+    let (a, b) = (position(1), position(p - 1));
+    let sigma2 = model.residual_se.powi(2);
+    let (v11, v13, v33) = (
+        sigma2 * unscaled[a][a],
+        sigma2 * unscaled[a][b],
+        sigma2 * unscaled[b][b],
+    );
+    let b1 = model.coefficients[1].estimate;
+    let b3 = model.coefficients[p - 1].estimate;
+    let df_residual = model.df_residual as f64;
+    let critical_t = tdist::quantile(0.975, df_residual);
+
+    let moderator_values = options.moderator_values.clone().unwrap_or_else(|| {
+        let (center, sd) = (mean(&m), sample_variance(&m).sqrt());
+        vec![center - sd, center, center + sd]
+    });
+    let simple_slopes = moderator_values
+        .into_iter()
+        .map(|value| {
+            let w = value - m_center;
+            let slope = b1 + b3 * w;
+            let std_error = (v11 + 2.0 * w * v13 + w * w * v33).sqrt();
+            let t = slope / std_error;
+            SimpleSlope {
+                moderator_value: value,
+                slope,
+                std_error,
+                t,
+                p_value: tdist::p_value(t, df_residual),
+                ci_lower: slope - critical_t * std_error,
+                ci_upper: slope + critical_t * std_error,
+            }
+        })
+        .collect();
+
+    // The slope is significant where (b1 + b3 w)^2 >= t^2 var(w), a quadratic in w
+    let t2 = critical_t * critical_t;
+    let (qa, qb, qc) = (
+        b3 * b3 - t2 * v33,
+        2.0 * (b1 * b3 - t2 * v13),
+        b1 * b1 - t2 * v11,
+    );
+    let mut bounds = Vec::new();
+    if qa != 0.0 {
+        let discriminant = qb * qb - 4.0 * qa * qc;
+        if discriminant > 0.0 {
+            let root = discriminant.sqrt();
+            bounds.push((-qb - root) / (2.0 * qa));
+            bounds.push((-qb + root) / (2.0 * qa));
+        }
+    } else if qb != 0.0 {
+        bounds.push(-qc / qb);
+    }
+    bounds.iter_mut().for_each(|w| *w += m_center);
+    bounds.sort_by(|a, b| a.total_cmp(b));
+
+    let significant_at = |value: f64| {
+        let w = value - m_center;
+        qa * w * w + qb * w + qc >= 0.0
+    };
+    let mut edges = vec![f64::NEG_INFINITY];
+    edges.extend(&bounds);
+    edges.push(f64::INFINITY);
+    let significant = edges
+        .windows(2)
+        .filter(|edge| {
+            // A point strictly inside the range decides its significance
+            let inside = match (edge[0].is_finite(), edge[1].is_finite()) {
+                (true, true) => (edge[0] + edge[1]) / 2.0,
+                (true, false) => edge[0] + edge[0].abs().max(1.0),
+                (false, true) => edge[1] - edge[1].abs().max(1.0),
+                (false, false) => 0.0,
+            };
+            significant_at(inside)
+        })
+        .map(|edge| (edge[0], edge[1]))
+        .collect();
+
+    let observed_range = m
+        .iter()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &v| {
+            (lo.min(v), hi.max(v))
+        });
+    let r_squared_change = model.r_squared - additive.r_squared;
+    let f_change = r_squared_change / ((1.0 - model.r_squared) / df_residual);
+
+    Ok(ModerationResult {
+        r_squared_change,
+        f_change,
+        f_change_p_value: fdist::sf(f_change, 1.0, df_residual),
+        model,
+        simple_slopes,
+        johnson_neyman: JohnsonNeyman {
+            bounds,
+            significant,
+            critical_t,
+            observed_range,
+        },
+        centered: options.center,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{fixtures::mtcars, helper::roughly_equals, reader};
+
+    #[test]
+    fn test_moderation() {
+        // lm(mpg ~ wt * hp) on centered variables, sim_slopes() and johnson_neyman()
+        let options = ModerationOptions::default();
+        let result = moderation(&mtcars(), "mpg", "wt", "hp", &options).unwrap();
+        let names: Vec<&str> = result
+            .model
+            .coefficients
+            .iter()
+            .map(|c| c.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["(Intercept)", "wt", "hp", "wt:hp"]);
+        assert!(roughly_equals(
+            result.model.coefficients[1].estimate,
+            -4.131649,
+            1e-6
+        ));
+        assert!(roughly_equals(
+            result.model.coefficients[3].estimate,
+            0.02784815,
+            1e-8
+        ));
+        assert!(roughly_equals(result.r_squared_change, 0.05797826, 1e-8));
+        assert!(roughly_equals(result.f_change, 14.08750, 1e-5));
+
+        let expected = [
+            (78.12463, -6.040998, 0.7797549, 1.936976e-8),
+            (146.6875, -4.131649, 0.5295580, 1.689282e-8),
+            (215.2504, -2.222300, 0.6858664, 0.003075621),
+        ];
+        for (slope, (value, estimate, std_error, p)) in result.simple_slopes.iter().zip(expected) {
+            assert!(roughly_equals(slope.moderator_value, value, 1e-4));
+            assert!(roughly_equals(slope.slope, estimate, 1e-6));
+            assert!(roughly_equals(slope.std_error, std_error, 1e-7));
+            assert!(roughly_equals(slope.p_value, p, p * 1e-5));
+        }
+        assert!(roughly_equals(
+            result.simple_slopes[2].ci_upper,
+            -0.8173669,
+            1e-6
+        ));
+
+        // The slope of wt is significant outside [236.48, 471.76]
+        let jn = &result.johnson_neyman;
+        assert!(roughly_equals(jn.bounds[0], 236.4759, 1e-4));
+        assert!(roughly_equals(jn.bounds[1], 471.7646, 1e-4));
+        assert_eq!(jn.significant.len(), 2);
+        assert_eq!(jn.significant[0].0, f64::NEG_INFINITY);
+        assert!(roughly_equals(jn.significant[1].0, 471.7646, 1e-4));
+        assert_eq!(jn.observed_range, (52.0, 335.0));
+
+        // Without centering only the lower order terms change
+        let uncentered = ModerationOptions {
+            center: false,
+            moderator_values: Some(vec![100.0]),
+            ..Default::default()
+        };
+        let raw = moderation(&mtcars(), "mpg", "wt", "hp", &uncentered).unwrap();
+        assert!(roughly_equals(
+            raw.model.coefficients[1].estimate,
+            -8.21662,
+            1e-5
+        ));
+        assert!(roughly_equals(raw.johnson_neyman.bounds[0], 236.4759, 1e-4));
+        assert!(roughly_equals(
+            raw.simple_slopes[0].slope,
+            -8.21662 + 0.02784815 * 100.0,
+            1e-5
+        ));
+    }
+
+    #[test]
+    fn test_moderation_covariates() {
+        let df = reader::import_csv("samples/data3.csv").unwrap();
+        let options = ModerationOptions {
+            covariates: vec!["condition"],
+            ..Default::default()
+        };
+        let result = moderation(&df, "score", "age", "happiness", &options).unwrap();
+        let last = result.model.coefficients.last().unwrap();
+        assert_eq!(last.name, "age:happiness");
+        assert_eq!(result.model.coefficients.len(), 6);
+        assert!(roughly_equals(
+            result.f_change,
+            last.t * last.t,
+            1e-8 * result.f_change
+        ));
+        assert!(moderation(&df, "score", "age", "condition", &options).is_err());
+    }
+}
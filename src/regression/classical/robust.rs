@@ -0,0 +1,296 @@
+use crate::{
+    core::{
+        dataframe::{ColumnType, DataFrame},
+        design::{factor_levels, numeric_values},
+        errors::DatasetError,
+    },
+    distributions::tdist,
+    regression::{
+        classical::{
+            linear::LinearRegressionResult,
+            logistic::{inverse, wald_coefficient, LogisticRegressionResult},
+        },
+        helper::multiply,
+    },
+};
+
+// Heteroscedasticity-consistent covariance estimators as vcovHC() in sandwich
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HcType {
+    HC0, // White's estimator
+    HC1, // HC0 times n / (n - p)
+    HC2, // squared scores divided by 1 - h
+    HC3, // by (1 - h)^2, the default of vcovHC
+    HC4, // by (1 - h)^min(4, n h / p), for influential observations
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CovarianceType<'a> {
+    Robust(HcType),
+    // One-way cluster-robust errors, the cluster of every observation as from cluster_ids
+    Cluster(&'a [usize]),
+}
+
+impl LinearRegressionResult {
+    // Replaces the standard errors, t tests and confidence intervals by those of a
+    // sandwich covariance, estimates and model statistics are kept. Cluster-robust errors
+    // use the small sample adjustment G / (G - 1) (n - 1) / (n - p) of vcovCL() and
+    // Stata, tests keep the residual degrees of freedom as coeftest()
+    pub fn with_robust_se(mut self, covariance: CovarianceType) -> Result<Self, DatasetError> {
+        let weights = vec![1.0; self.n];
+        let covariance = sandwich_covariance(
+            &self.model_matrix,
+            &weights,
+            &self.residuals,
+            covariance,
+            true,
+        )?;
+
+        let df_residual = self.df_residual as f64;
+        let critical = tdist::quantile(0.975, df_residual);
+        for (k, coefficient) in self.coefficients.iter_mut().enumerate() {
+            let std_error = covariance[k][k].sqrt();
+            let t = coefficient.estimate / std_error;
+            coefficient.std_error = std_error;
+            coefficient.t = t;
+            coefficient.p_value = tdist::p_value(t, df_residual);
+            coefficient.ci_lower = coefficient.estimate - critical * std_error;
+            coefficient.ci_upper = coefficient.estimate + critical * std_error;
+        }
+        Ok(self)
+    }
+}
+
+impl LogisticRegressionResult {
+    // Replaces the Wald tests and odds ratio intervals by those of a sandwich covariance.
+    // The scores are x (y - p) and the leverages those of the weighted fit, cluster-robust
+    // errors are adjusted by G / (G - 1) only, as vcovCL() does for glm
+    pub fn with_robust_se(mut self, covariance: CovarianceType) -> Result<Self, DatasetError> {
+        let weights: Vec<f64> = self.fitted.iter().map(|mu| mu * (1.0 - mu)).collect();
+        let covariance = sandwich_covariance(
+            &self.model_matrix,
+            &weights,
+            &self.residuals,
+            covariance,
+            false,
+        )?;
+
+        for (k, coefficient) in self.coefficients.iter_mut().enumerate() {
+            *coefficient = wald_coefficient(
+                &coefficient.name,
+                coefficient.estimate,
+                covariance[k][k].sqrt(),
+            );
+        }
+        Ok(self)
+    }
+}
+
+// Cluster of every row from a categorical or numerical column such as participant ids
+pub fn cluster_ids(df: &DataFrame, name: &str) -> Result<Vec<usize>, DatasetError> {
+    if df.get_column(name)?.column_type() == ColumnType::Categorical {
+        return Ok(factor_levels(df, name)?.1);
+    }
+    let values = numeric_values(df, name)?;
+    if values.iter().any(|v| v.is_nan()) {
+        return Err(DatasetError::InvalidData(format!(
+            "Cluster variable {} has missing values",
+            name
+        )));
+    }
+    let mut levels = values.clone();
+    levels.sort_by(|a, b| a.total_cmp(b));
+    levels.dedup();
+    Ok(values
+        .iter()
+        .map(|v| levels.partition_point(|level| level < v))
+        .collect())
+}
+
+// B M B with bread B = (X'WX)^-1 and meat M from the score contributions x_i u_i
+fn sandwich_covariance(
+    x: &[Vec<f64>],
+    weights: &[f64],
+    scores: &[f64],
+    covariance: CovarianceType,
+    linear: bool,
+) -> Result<Vec<Vec<f64>>, DatasetError> {
+    let n = x.len();
+    let p = x[0].len();
+    let mut information = vec![vec![0.0; p]; p];
+    for (row, w) in x.iter().zip(weights) {
+        for j in 0..p {
+            for k in 0..p {
+                information[j][k] += w * row[j] * row[k];
+            }
+        }
+    }
+    let bread = inverse(&information)?;
+
+    // Outer products of the summed scores of each group of observations
+    let mut meat = vec![vec![0.0; p]; p];
+    let mut add = |score: &[f64], factor: f64| {
+        for j in 0..p {
+            for k in 0..p {
+                meat[j][k] += factor * score[j] * score[k];
+            }
+        }
+    };
+    match covariance {
+        CovarianceType::Robust(hc) => {
+            for i in 0..n {
+                let score: Vec<f64> = x[i].iter().map(|v| v * scores[i]).collect();
+                let h = weights[i] * quadratic_form(&bread, &x[i]);
+                let factor = match hc {
+                    HcType::HC0 => 1.0,
+                    HcType::HC1 => n as f64 / (n - p) as f64,
+                    HcType::HC2 => 1.0 / (1.0 - h),
+                    HcType::HC3 => 1.0 / (1.0 - h).powi(2),
+                    HcType::HC4 => (1.0 - h).powf(-(n as f64 * h / p as f64).min(4.0)),
+                };
+                add(&score, factor);
+            }
+        }
+        CovarianceType::Cluster(clusters) => {
+            if clusters.len() != n {
+                return Err(DatasetError::InvalidData(
+                    "Every observation needs a cluster.".to_string(),
+                ));
+            }
+            let groups = clusters.iter().max().map_or(0, |g| g + 1);
+            let mut sums = vec![vec![0.0; p]; groups];
+            for i in 0..n {
+                for j in 0..p {
+                    sums[clusters[i]][j] += x[i][j] * scores[i];
+                }
+            }
+            // Cluster ids without observations do not count
+            let mut observed = vec![false; groups];
+            clusters.iter().for_each(|&c| observed[c] = true);
+            let sums: Vec<Vec<f64>> = sums
+                .into_iter()
+                .zip(observed)
+                .filter_map(|(sum, seen)| seen.then_some(sum))
+                .collect();
+            let g = sums.len() as f64;
+            if sums.len() < 2 {
+                return Err(DatasetError::InvalidData(
+                    "Cluster-robust errors need at least two clusters.".to_string(),
+                ));
+            }
+            let mut factor = g / (g - 1.0);
+            if linear {
+                factor *= (n - 1) as f64 / (n - p) as f64;
+            }
+            for sum in &sums {
+                add(sum, factor);
+            }
+        }
+    }
+
+    let left = multiply(&bread, &meat);
+    Ok(multiply(&left, &bread))
+}
+
+fn quadratic_form(matrix: &[Vec<f64>], x: &[f64]) -> f64 {
+    matrix
+        .iter()
+        .zip(x)
+        .map(|(row, a)| a * row.iter().zip(x).map(|(m, b)| m * b).sum::<f64>())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::{fixtures::mtcars, helper::roughly_equals},
+        regression::classical::{
+            linear::{linear_regression, linear_regression_formula},
+            logistic::logistic_regression,
+        },
+    };
+
+    #[test]
+    fn test_robust_linear() {
+        // sqrt(diag(vcovHC(lm(mpg ~ wt + hp, data = mtcars), type = ...)))
+        let df = mtcars();
+        let expected = [
+            (HcType::HC0, 0.6199275, 0.006646058),
+            (HcType::HC1, 0.6512038, 0.006981361),
+            (HcType::HC2, 0.6877655, 0.007825029),
+            (HcType::HC3, 0.7685191, 0.009385138),
+            (HcType::HC4, 0.8650323, 0.01380655),
+        ];
+        for (hc, wt, hp) in expected {
+            let result = linear_regression(&df, "mpg", vec!["wt", "hp"])
+                .and_then(|fit| fit.with_robust_se(CovarianceType::Robust(hc)))
+                .unwrap();
+            assert!(roughly_equals(result.coefficients[1].std_error, wt, 1e-7));
+            assert!(roughly_equals(result.coefficients[2].std_error, hp, 1e-8));
+        }
+
+        let clusters = cluster_ids(&df, "carb").unwrap();
+        let result = linear_regression_formula(&df, "mpg ~ wt + hp")
+            .and_then(|fit| fit.with_robust_se(CovarianceType::Cluster(&clusters)))
+            .unwrap();
+        let wt = &result.coefficients[1];
+        assert!(roughly_equals(wt.estimate, -3.87783, 1e-5));
+        assert!(roughly_equals(wt.std_error, 0.8319062, 1e-7));
+        assert!(roughly_equals(wt.t, wt.estimate / wt.std_error, 1e-12));
+        let critical = tdist::quantile(0.975, 29.0);
+        assert!(roughly_equals(
+            wt.ci_upper,
+            wt.estimate + critical * wt.std_error,
+            1e-12
+        ));
+        assert!(roughly_equals(
+            result.coefficients[0].std_error,
+            2.413761,
+            1e-6
+        ));
+        assert!(roughly_equals(result.r_squared, 0.8268, 1e-4));
+    }
+
+    #[test]
+    fn test_robust_logistic() {
+        // sqrt(diag(vcovHC(glm(am ~ wt + hp, binomial, mtcars), type = ...))) and vcovCL
+        let df = mtcars();
+        let clusters = cluster_ids(&df, "carb").unwrap();
+        let expected = [
+            (HcType::HC0, 2.767488, 8.242918),
+            (HcType::HC1, 2.907111, 8.658786),
+            (HcType::HC3, 3.294247, 9.765087),
+        ];
+        for (hc, wt, intercept) in expected {
+            let result = logistic_regression(&df, "am", vec!["wt", "hp"])
+                .and_then(|fit| fit.with_robust_se(CovarianceType::Robust(hc)))
+                .unwrap();
+            assert!(roughly_equals(result.coefficients[1].std_error, wt, 1e-6));
+            assert!(roughly_equals(
+                result.coefficients[0].std_error,
+                intercept,
+                1e-6
+            ));
+        }
+
+        let result = logistic_regression(&df, "am", vec!["wt", "hp"])
+            .and_then(|fit| fit.with_robust_se(CovarianceType::Cluster(&clusters)))
+            .unwrap();
+        let wt = &result.coefficients[1];
+        assert!(roughly_equals(wt.estimate, -8.08348, 1e-5));
+        assert!(roughly_equals(wt.std_error, 2.750553, 1e-6));
+        assert!(roughly_equals(wt.z, wt.estimate / wt.std_error, 1e-12));
+        assert!(roughly_equals(
+            result.coefficients[2].std_error,
+            0.006520861,
+            1e-9
+        ));
+
+        assert!(cluster_ids(&df, "missing").is_err());
+        let fit = logistic_regression(&df, "am", vec!["wt"]).unwrap();
+        assert!(fit
+            .with_robust_se(CovarianceType::Cluster(&[0, 1]))
+            .is_err());
+    }
+}
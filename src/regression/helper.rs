@@ -1,82 +1,316 @@
 use crate::core::errors::DatasetError;
 
+// Relative column norm below which a column counts as linearly dependent on the
+// preceding ones, as in R's lm()
+const RANK_TOLERANCE: f64 = 1e-7;
+
+// Least squares coefficients of y on the rows of x. Fails when some columns are linear
+// combinations of earlier ones, use QrDecomposition to drop them as aliased instead
 pub fn multiple_linear_regression(x: &[Vec<f64>], y: &[f64]) -> Result<Vec<f64>, DatasetError> {
     let n = x.len(); // Number of samples
-    let m = x[0].len(); // Number of features
 
     // Validate dimensions
-    if n == 0 || m == 0 || y.len() != n {
+    if n == 0 || x[0].is_empty() || y.len() != n {
         return Err(DatasetError::InvalidData(
             "The input dimensions are not valid.".to_string(),
         ));
     }
 
-    // Build the matrix for (X'X)
-    let mut xtx = vec![vec![0.0; m]; m];
-    let mut xty = vec![0.0; m];
+    let qr = QrDecomposition::new(x)?;
+    if !qr.aliased().is_empty() {
+        let columns: Vec<String> = qr.aliased().iter().map(|j| j.to_string()).collect();
+        return Err(DatasetError::InvalidData(format!(
+            "Columns {} are linear combinations of earlier columns",
+            columns.join(", ")
+        )));
+    }
+    let mut coefficients = vec![0.0; x[0].len()];
+    for (&column, value) in qr.pivot.iter().zip(qr.coefficients(y)) {
+        coefficients[column] = value;
+    }
+    Ok(coefficients)
+}
+
+// Householder QR decomposition with the limited column pivoting of LINPACK's dqrdc2:
+// columns whose remaining norm is negligible are moved to the end, so the first `rank`
+// entries of `pivot` are the estimable columns in their original order
+#[derive(Debug, Clone)]
+pub struct QrDecomposition {
+    columns: Vec<Vec<f64>>, // Householder vectors below, R above the diagonal
+    diagonal: Vec<f64>,
+    pub pivot: Vec<usize>,
+    pub rank: usize,
+}
 
-    for i in 0..n {
-        for j in 0..m {
-            for k in 0..m {
-                xtx[j][k] += x[i][j] * x[i][k];
+impl QrDecomposition {
+    pub fn new(x: &[Vec<f64>]) -> Result<QrDecomposition, DatasetError> {
+        let n = x.len();
+        let p = x.first().map_or(0, |row| row.len());
+        if n == 0 || p == 0 || x.iter().any(|row| row.len() != p) {
+            return Err(DatasetError::InvalidData(
+                "The input dimensions are not valid.".to_string(),
+            ));
+        }
+
+        let mut columns: Vec<Vec<f64>> = (0..p)
+            .map(|j| x.iter().map(|row| row[j]).collect())
+            .collect();
+        let mut pivot: Vec<usize> = (0..p).collect();
+        let mut original_norms: Vec<f64> = columns.iter().map(|c| norm(c)).collect();
+        let mut diagonal = Vec::new();
+
+        let mut rank = p.min(n);
+        let mut k = 0;
+        while k < rank {
+            let remaining = norm(&columns[k][k..]);
+            if remaining <= RANK_TOLERANCE * original_norms[k] || remaining == 0.0 {
+                // Move the dependent column to the end and retry with the next one
+                let column = columns.remove(k);
+                columns.push(column);
+                let index = pivot.remove(k);
+                pivot.push(index);
+                let original = original_norms.remove(k);
+                original_norms.push(original);
+                rank -= 1;
+                continue;
+            }
+
+            let alpha = if columns[k][k] > 0.0 {
+                -remaining
+            } else {
+                remaining
+            };
+            columns[k][k] -= alpha;
+            let v: Vec<f64> = columns[k][k..].to_vec();
+            let v_norm2: f64 = v.iter().map(|a| a * a).sum();
+            for column in columns.iter_mut().skip(k + 1) {
+                let dot: f64 = v.iter().zip(&column[k..]).map(|(a, b)| a * b).sum();
+                let factor = 2.0 * dot / v_norm2;
+                for (entry, a) in column[k..].iter_mut().zip(&v) {
+                    *entry -= factor * a;
+                }
             }
-            xty[j] += x[i][j] * y[i];
+            diagonal.push(alpha);
+            k += 1;
         }
+
+        Ok(QrDecomposition {
+            columns,
+            diagonal,
+            pivot,
+            rank,
+        })
     }
 
-    // Solve for coefficients (X'X)^{-1}(X'y)
-    let xtx_inv = invert_matrix(&xtx)?;
-    let coefficients: Vec<f64> = xtx_inv
-        .iter()
-        .map(|row| row.iter().zip(&xty).map(|(a, b)| a * b).sum())
-        .collect();
+    // Q'y, its entries beyond the rank are the residuals in the orthogonal complement
+    pub fn qty(&self, y: &[f64]) -> Vec<f64> {
+        let mut result = y.to_vec();
+        for k in 0..self.rank {
+            let v = &self.columns[k][k..];
+            let v_norm2: f64 = v.iter().map(|a| a * a).sum();
+            let dot: f64 = v.iter().zip(&result[k..]).map(|(a, b)| a * b).sum();
+            let factor = 2.0 * dot / v_norm2;
+            for (entry, a) in result[k..].iter_mut().zip(v) {
+                *entry -= factor * a;
+            }
+        }
+        result
+    }
 
-    Ok(coefficients)
+    // Coefficients of the estimable columns, in the order of pivot[..rank]
+    pub fn coefficients(&self, y: &[f64]) -> Vec<f64> {
+        let qty = self.qty(y);
+        let mut coefficients = vec![0.0; self.rank];
+        for i in (0..self.rank).rev() {
+            let dot: f64 = (i + 1..self.rank)
+                .map(|j| self.r(i, j) * coefficients[j])
+                .sum();
+            coefficients[i] = (qty[i] - dot) / self.diagonal[i];
+        }
+        coefficients
+    }
+
+    // (X'X)^-1 of the estimable columns, computed as R^-1 R^-T
+    #[allow(clippy::needless_range_loop)]
+    pub fn unscaled_covariance(&self) -> Vec<Vec<f64>> {
+        let rank = self.rank;
+        let mut inverse = vec![vec![0.0; rank]; rank];
+        for j in 0..rank {
+            inverse[j][j] = 1.0 / self.diagonal[j];
+            for i in (0..j).rev() {
+                let dot: f64 = (i + 1..=j).map(|k| self.r(i, k) * inverse[k][j]).sum();
+                inverse[i][j] = -dot / self.diagonal[i];
+            }
+        }
+
+        (0..rank)
+            .map(|i| {
+                (0..rank)
+                    .map(|j| {
+                        (i.max(j)..rank)
+                            .map(|k| inverse[i][k] * inverse[j][k])
+                            .sum()
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    // Original indices of the columns that are linear combinations of earlier ones
+    pub fn aliased(&self) -> &[usize] {
+        &self.pivot[self.rank..]
+    }
+
+    fn r(&self, i: usize, j: usize) -> f64 {
+        if i == j {
+            self.diagonal[i]
+        } else {
+            self.columns[j][i]
+        }
+    }
+}
+
+fn norm(values: &[f64]) -> f64 {
+    values.iter().map(|v| v * v).sum::<f64>().sqrt()
 }
 
-// Function to invert a square matrix (using Gauss-Jordan elimination)
-fn invert_matrix(matrix: &[Vec<f64>]) -> Result<Vec<Vec<f64>>, DatasetError> {
+// Cholesky factor L (lower triangular, A = LL') of a symmetric positive definite matrix
+pub fn cholesky(matrix: &[Vec<f64>]) -> Result<Vec<Vec<f64>>, DatasetError> {
     let n = matrix.len();
+    let mut lower = vec![vec![0.0; n]; n];
 
-    // Check if the matrix is square
-    if n == 0 || matrix[0].len() != n {
-        return Err(DatasetError::InvalidData(
-            "Matrix must be square to invert.".to_string(),
-        ));
+    for i in 0..n {
+        for j in 0..=i {
+            let dot: f64 = lower[i][..j]
+                .iter()
+                .zip(&lower[j][..j])
+                .map(|(a, b)| a * b)
+                .sum();
+            if i == j {
+                let pivot = matrix[i][i] - dot;
+                if pivot <= 0.0 || !pivot.is_finite() {
+                    return Err(DatasetError::InvalidData(
+                        "Matrix is not positive definite.".to_string(),
+                    ));
+                }
+                lower[i][i] = pivot.sqrt();
+            } else {
+                lower[i][j] = (matrix[i][j] - dot) / lower[j][j];
+            }
+        }
     }
 
-    let mut augmented = matrix.to_vec();
+    Ok(lower)
+}
 
-    // Create augmented matrix [A | I]
+// Solves LL'x = b given the Cholesky factor L
+pub fn cholesky_solve(lower: &[Vec<f64>], b: &[f64]) -> Vec<f64> {
+    let n = lower.len();
+
+    let mut z = vec![0.0; n];
     for i in 0..n {
-        augmented[i].resize(2 * n, 0.0);
-        augmented[i][i + n] = 1.0; // Create identity matrix
+        let dot: f64 = lower[i][..i].iter().zip(&z[..i]).map(|(a, b)| a * b).sum();
+        z[i] = (b[i] - dot) / lower[i][i];
     }
 
-    for i in 0..n {
-        // Ensure the pivot element is not zero
-        let divisor = augmented[i][i];
-        if divisor.abs() < f64::EPSILON {
-            return Err(DatasetError::InvalidData(
-                "Matrix is singular and cannot be inverted.".to_string(),
-            ));
+    let mut x = vec![0.0; n];
+    for i in (0..n).rev() {
+        let dot: f64 = (i + 1..n).map(|k| lower[k][i] * x[k]).sum();
+        x[i] = (z[i] - dot) / lower[i][i];
+    }
+
+    x
+}
+
+// Matrix product of row-major matrices
+pub fn multiply(a: &[Vec<f64>], b: &[Vec<f64>]) -> Vec<Vec<f64>> {
+    a.iter()
+        .map(|row| {
+            (0..b[0].len())
+                .map(|j| row.iter().zip(b).map(|(x, b_row)| x * b_row[j]).sum())
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::helper::roughly_equals;
+
+    #[test]
+    #[allow(clippy::needless_range_loop)]
+    fn test_qr_decomposition() {
+        let x = vec![
+            vec![1.0, 0.0, 2.0, 1.0],
+            vec![1.0, 1.0, 2.0, 3.0],
+            vec![1.0, 2.0, 2.0, 2.0],
+            vec![1.0, 3.0, 2.0, 5.0],
+            vec![1.0, 4.0, 2.0, 4.0],
+        ];
+        let y = vec![1.0, 3.1, 4.9, 7.2, 8.8];
+
+        // The third column is twice the intercept
+        let qr = QrDecomposition::new(&x).unwrap();
+        assert_eq!(qr.rank, 3);
+        assert_eq!(qr.pivot, vec![0, 1, 3, 2]);
+        assert_eq!(qr.aliased(), &[2]);
+
+        // Normal equations of the estimable columns
+        let estimates = qr.coefficients(&y);
+        let residuals: Vec<f64> = x
+            .iter()
+            .zip(&y)
+            .map(|(row, value)| {
+                value - estimates[0] - estimates[1] * row[1] - estimates[2] * row[3]
+            })
+            .collect();
+        for column in [0, 1, 3] {
+            let dot: f64 = x
+                .iter()
+                .zip(&residuals)
+                .map(|(row, r)| row[column] * r)
+                .sum();
+            assert!(roughly_equals(dot, 0.0, 1e-10));
         }
-        for j in 0..2 * n {
-            augmented[i][j] /= divisor; // Normalize pivot row
+
+        // The legacy solver reports the aliased column instead of returning NaN
+        match multiple_linear_regression(&x, &y) {
+            Err(DatasetError::InvalidData(message)) => assert!(message.contains("Columns 2")),
+            other => panic!("expected an error, got {:?}", other),
+        }
+        let full_rank: Vec<Vec<f64>> = x.iter().map(|row| vec![row[0], row[1], row[3]]).collect();
+        let coefficients = multiple_linear_regression(&full_rank, &y).unwrap();
+        for (a, b) in coefficients.iter().zip(&estimates) {
+            assert!(roughly_equals(*a, *b, 1e-12));
         }
 
-        // Eliminate other rows
-        for k in 0..n {
-            if k != i {
-                let factor = augmented[k][i];
-                for j in 0..2 * n {
-                    augmented[k][j] -= factor * augmented[i][j];
-                }
+        // (X'X)^-1 times X'X is the identity
+        let covariance = qr.unscaled_covariance();
+        let estimable = [0, 1, 3];
+        for i in 0..3 {
+            for j in 0..3 {
+                let product: f64 = (0..3)
+                    .map(|k| {
+                        covariance[i][k]
+                            * x.iter()
+                                .map(|row| row[estimable[k]] * row[estimable[j]])
+                                .sum::<f64>()
+                    })
+                    .sum();
+                assert!(roughly_equals(product, (i == j) as u8 as f64, 1e-10));
             }
         }
     }
 
-    // Extract the inverse matrix
-    let inverse: Vec<Vec<f64>> = augmented.iter().map(|row| row[n..].to_vec()).collect();
-    Ok(inverse)
+    #[test]
+    fn test_cholesky() {
+        let matrix = vec![vec![4.0, 2.0], vec![2.0, 3.0]];
+        let lower = cholesky(&matrix).unwrap();
+        assert!(roughly_equals(lower[1][0], 1.0, 1e-12));
+        let x = cholesky_solve(&lower, &[2.0, 1.0]);
+        assert!(roughly_equals(4.0 * x[0] + 2.0 * x[1], 2.0, 1e-12));
+        assert!(roughly_equals(2.0 * x[0] + 3.0 * x[1], 1.0, 1e-12));
+        assert!(cholesky(&[vec![1.0, 2.0], vec![2.0, 1.0]]).is_err());
+    }
 }
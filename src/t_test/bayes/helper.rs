@@ -0,0 +1,293 @@
+use std::f64::consts::{FRAC_1_SQRT_2, PI, SQRT_2};
+
+use crate::{
+    core::{
+        errors::DatasetError, hypothesis::Alternative, integration::integrate_with_breakpoints,
+    },
+    distributions::{normal, tdist},
+};
+
+const TOLERANCE: f64 = 1e-10;
+
+// Scale r of the Cauchy prior on the effect size delta, named as in BayesFactor/JASP
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PriorScale {
+    Medium,
+    Wide,
+    UltraWide,
+    Custom(f64),
+}
+
+impl PriorScale {
+    pub fn value(&self) -> f64 {
+        match self {
+            PriorScale::Medium => FRAC_1_SQRT_2,
+            PriorScale::Wide => 1.0,
+            PriorScale::UltraWide => SQRT_2,
+            PriorScale::Custom(r) => *r,
+        }
+    }
+}
+
+// Prior on the effect size delta under H1. The default JZS prior is a Cauchy centered
+// at zero, informed priors (Gronau, Ly & Wagenmakers, 2020) may shift and reshape it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DeltaPrior {
+    Cauchy { location: f64, scale: f64 },
+    T { location: f64, scale: f64, df: f64 },
+    Normal { mean: f64, sd: f64 },
+}
+
+impl DeltaPrior {
+    pub fn jzs(scale: PriorScale) -> DeltaPrior {
+        DeltaPrior::Cauchy {
+            location: 0.0,
+            scale: scale.value(),
+        }
+    }
+
+    pub fn pdf(&self, delta: f64) -> f64 {
+        match *self {
+            DeltaPrior::Cauchy { location, scale } => {
+                scale / (PI * (scale * scale + (delta - location).powi(2)))
+            }
+            DeltaPrior::T {
+                location,
+                scale,
+                df,
+            } => tdist::pdf((delta - location) / scale, df) / scale,
+            DeltaPrior::Normal { mean, sd } => normal::pdf((delta - mean) / sd) / sd,
+        }
+    }
+
+    pub fn cdf(&self, delta: f64) -> f64 {
+        match *self {
+            DeltaPrior::Cauchy { location, scale } => {
+                0.5 + ((delta - location) / scale).atan() / PI
+            }
+            DeltaPrior::T {
+                location,
+                scale,
+                df,
+            } => tdist::cdf((delta - location) / scale, df),
+            DeltaPrior::Normal { mean, sd } => normal::cdf((delta - mean) / sd),
+        }
+    }
+
+    // Prior probability of the values of delta allowed by the alternative
+    pub fn mass(&self, alternative: Alternative) -> f64 {
+        match alternative {
+            Alternative::TwoSided => 1.0,
+            Alternative::Less => self.cdf(0.0),
+            Alternative::Greater => 1.0 - self.cdf(0.0),
+        }
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), DatasetError> {
+        let valid = match *self {
+            DeltaPrior::Cauchy { scale, .. } => scale > 0.0,
+            DeltaPrior::T { scale, df, .. } => scale > 0.0 && df > 0.0,
+            DeltaPrior::Normal { sd, .. } => sd > 0.0,
+        };
+        if !valid {
+            return Err(DatasetError::InvalidData(
+                "The prior on delta needs a positive scale (and degrees of freedom).".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct DeltaPosterior {
+    pub median: f64,
+    pub ci_lower: f64, // 95% credible interval
+    pub ci_upper: f64,
+}
+
+#[derive(Debug)]
+pub struct BayesFactorResult {
+    pub bf10: f64,
+    pub t: f64,
+    pub dof: f64,
+    pub n: usize,
+    pub prior_scale: f64,
+    pub alternative: Alternative,
+}
+
+impl BayesFactorResult {
+    pub fn bf01(&self) -> f64 {
+        1.0 / self.bf10
+    }
+}
+
+// Log likelihood of the observed t statistic given the effect size delta, i.e. the
+// noncentral t density with noncentrality delta * sqrt(n_eff), up to a constant that
+// only depends on t and dof. It is evaluated as the integral over y = sqrt(chi^2_dof) of
+// y^dof * exp(-y^2 / 2 - (t * y / sqrt(dof) - ncp)^2 / 2)
+pub(crate) fn log_likelihood(delta: f64, t: f64, n_eff: f64, dof: f64) -> f64 {
+    let a = t / dof.sqrt();
+    let ncp = delta * n_eff.sqrt();
+    let log_integrand = |y: f64| dof * y.ln() - y * y / 2.0 - (a * y - ncp).powi(2) / 2.0;
+
+    // The integrand is log-concave, integrate around its mode with Simpson's rule
+    let mode =
+        (a * ncp + (a * a * ncp * ncp + 4.0 * (1.0 + a * a) * dof).sqrt()) / (2.0 * (1.0 + a * a));
+    let width = 1.0 / (dof / (mode * mode) + 1.0 + a * a).sqrt();
+    let lower = (mode - 15.0 * width).max(0.0);
+    let upper = mode + 15.0 * width;
+
+    let panels = 400;
+    let h = (upper - lower) / panels as f64;
+    let peak = log_integrand(mode);
+    let mut sum = 0.0;
+    for i in 0..=panels {
+        let y = lower + i as f64 * h;
+        let weight = if i == 0 || i == panels {
+            1.0
+        } else if i % 2 == 1 {
+            4.0
+        } else {
+            2.0
+        };
+        if y > 0.0 {
+            sum += weight * (log_integrand(y) - peak).exp();
+        }
+    }
+
+    peak + (sum * h / 3.0).ln()
+}
+
+// Bayes factor of the prior on delta against delta = 0, with the prior truncated to
+// one sign of delta (and renormalized) for directional alternatives
+pub(crate) fn bayes_factor(
+    t: f64,
+    n_eff: f64,
+    dof: f64,
+    prior: &DeltaPrior,
+    alternative: Alternative,
+) -> f64 {
+    let frame = LikelihoodFrame::new(t, n_eff, dof);
+    let (lower, upper) = support(alternative);
+    let integral = integrate_with_breakpoints(
+        |delta| frame.scaled(delta) * prior.pdf(delta),
+        lower,
+        upper,
+        &frame.breakpoints(),
+        TOLERANCE,
+    );
+
+    integral * (frame.log_max - frame.log_null).exp() / prior.mass(alternative)
+}
+
+// Posterior median and central 95% credible interval of delta, obtained from the
+// cumulative posterior on a fine grid spanning the likelihood
+pub(crate) fn posterior(
+    t: f64,
+    n_eff: f64,
+    dof: f64,
+    prior: &DeltaPrior,
+    alternative: Alternative,
+) -> DeltaPosterior {
+    let frame = LikelihoodFrame::new(t, n_eff, dof);
+    let (lower, upper) = support(alternative);
+    let from = (frame.estimate - 12.0 * frame.spread).max(lower);
+    let to = (frame.estimate + 12.0 * frame.spread).min(upper);
+
+    let points = 4000;
+    let h = (to - from) / points as f64;
+    let grid: Vec<f64> = (0..=points).map(|i| from + i as f64 * h).collect();
+    let density: Vec<f64> = grid
+        .iter()
+        .map(|&delta| frame.scaled(delta) * prior.pdf(delta))
+        .collect();
+
+    let mut cumulative = vec![0.0; grid.len()];
+    for i in 1..grid.len() {
+        cumulative[i] = cumulative[i - 1] + (density[i - 1] + density[i]) * h / 2.0;
+    }
+    let total = cumulative[points];
+
+    let quantile = |p: f64| {
+        let target = p * total;
+        let i = cumulative.partition_point(|&c| c < target).clamp(1, points);
+        let step = cumulative[i] - cumulative[i - 1];
+        let fraction = if step > 0.0 {
+            (target - cumulative[i - 1]) / step
+        } else {
+            0.0
+        };
+        grid[i - 1] + fraction * h
+    };
+
+    DeltaPosterior {
+        median: quantile(0.5),
+        ci_lower: quantile(0.025),
+        ci_upper: quantile(0.975),
+    }
+}
+
+fn support(alternative: Alternative) -> (f64, f64) {
+    match alternative {
+        Alternative::TwoSided => (f64::NEG_INFINITY, f64::INFINITY),
+        Alternative::Less => (f64::NEG_INFINITY, 0.0),
+        Alternative::Greater => (0.0, f64::INFINITY),
+    }
+}
+
+// The likelihood of delta peaks near the observed effect t / sqrt(n_eff) with a width
+// of at most sqrt(1 + t^2 / dof) / sqrt(n_eff), it is scaled by its maximum to avoid
+// overflow for large t
+struct LikelihoodFrame {
+    t: f64,
+    n_eff: f64,
+    dof: f64,
+    estimate: f64,
+    spread: f64,
+    log_max: f64,
+    log_null: f64,
+}
+
+impl LikelihoodFrame {
+    fn new(t: f64, n_eff: f64, dof: f64) -> LikelihoodFrame {
+        let estimate = t / n_eff.sqrt();
+        LikelihoodFrame {
+            t,
+            n_eff,
+            dof,
+            estimate,
+            spread: (1.0 + t * t / dof).sqrt() / n_eff.sqrt(),
+            log_max: log_likelihood(estimate, t, n_eff, dof),
+            log_null: log_likelihood(0.0, t, n_eff, dof),
+        }
+    }
+
+    fn scaled(&self, delta: f64) -> f64 {
+        (log_likelihood(delta, self.t, self.n_eff, self.dof) - self.log_max).exp()
+    }
+
+    fn breakpoints(&self) -> Vec<f64> {
+        [-8.0, -4.0, -2.0, -1.0, 0.0, 1.0, 2.0, 4.0, 8.0]
+            .iter()
+            .map(|k| self.estimate + k * self.spread)
+            .chain([0.0])
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::helper::roughly_equals;
+
+    #[test]
+    fn test_log_likelihood() {
+        // At delta = 0 the likelihood ratio against the central t density is constant
+        let dof = 7.0;
+        let reference = log_likelihood(0.0, 0.0, 8.0, dof) - tdist::pdf(0.0, dof).ln();
+        for t in [-3.0, -0.5, 1.0, 2.5] {
+            let shifted = log_likelihood(0.0, t, 8.0, dof) - tdist::pdf(t, dof).ln();
+            assert!(roughly_equals(shifted, reference, 1e-9));
+        }
+    }
+}
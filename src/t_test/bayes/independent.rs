@@ -0,0 +1,106 @@
+use crate::{
+    core::{dataframe::DataFrame, errors::DatasetError, hypothesis::Alternative},
+    t_test::{classical::independent::independent_t, helper::split_two_groups},
+};
+
+use super::helper::{bayes_factor, posterior, DeltaPosterior, DeltaPrior};
+
+#[derive(Debug)]
+pub struct IndependentBayesResult {
+    pub bf10: f64,
+    pub t: f64,
+    pub dof: f64,
+    pub n1: usize,
+    pub n2: usize,
+    pub prior: DeltaPrior,
+    pub alternative: Alternative,
+    pub posterior: DeltaPosterior,
+}
+
+impl IndependentBayesResult {
+    pub fn bf01(&self) -> f64 {
+        1.0 / self.bf10
+    }
+}
+
+// Two-sample Bayes factor for the first against the second level (alphabetical) of
+// the grouping variable. With DeltaPrior::jzs this matches ttestBF(formula = ...) in
+// BayesFactor, informed t and normal priors follow Gronau, Ly & Wagenmakers (2020)
+// as in JASP. The posterior of delta is truncated to the sign of directional alternatives
+pub fn independent_bf(
+    df: &DataFrame,
+    grouping_var: &str,
+    dependent_var: &str,
+    prior: DeltaPrior,
+    alternative: Alternative,
+) -> Result<IndependentBayesResult, DatasetError> {
+    let (first, second) = split_two_groups(df, grouping_var, dependent_var)?;
+    independent_bf_t(&first.value, &second.value, prior, alternative)
+}
+
+pub fn independent_bf_t(
+    x: &[f64],
+    y: &[f64],
+    prior: DeltaPrior,
+    alternative: Alternative,
+) -> Result<IndependentBayesResult, DatasetError> {
+    prior.validate()?;
+    let classical = independent_t(x, y, true, Alternative::TwoSided)?;
+
+    let (n1, n2) = (x.len(), y.len());
+    let n_eff = (n1 * n2) as f64 / (n1 + n2) as f64;
+
+    Ok(IndependentBayesResult {
+        bf10: bayes_factor(classical.t, n_eff, classical.dof, &prior, alternative),
+        t: classical.t,
+        dof: classical.dof,
+        n1,
+        n2,
+        prior,
+        alternative,
+        posterior: posterior(classical.t, n_eff, classical.dof, &prior, alternative),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::{fixtures::sleep, helper::roughly_equals},
+        t_test::bayes::helper::PriorScale,
+    };
+
+    #[test]
+    fn test_independent_bf() {
+        // ttestBF(formula = extra ~ group, data = sleep) in BayesFactor
+        let prior = DeltaPrior::jzs(PriorScale::Medium);
+        let result =
+            independent_bf(&sleep(), "group", "extra", prior, Alternative::TwoSided).unwrap();
+        assert!(roughly_equals(result.bf10, 1.265925, 1e-6));
+        assert!(roughly_equals(result.posterior.median, -0.592672, 1e-4));
+        assert!(roughly_equals(result.posterior.ci_lower, -1.503320, 1e-4));
+        assert!(roughly_equals(result.posterior.ci_upper, 0.175880, 1e-4));
+
+        // Truncating to negative effects moves all posterior mass below zero
+        let result = independent_bf(&sleep(), "group", "extra", prior, Alternative::Less).unwrap();
+        assert!(result.posterior.ci_upper < 0.0);
+    }
+
+    #[test]
+    fn test_informed_prior() {
+        let prior = DeltaPrior::Normal {
+            mean: -0.5,
+            sd: 0.3,
+        };
+        let result =
+            independent_bf(&sleep(), "group", "extra", prior, Alternative::TwoSided).unwrap();
+        assert!(roughly_equals(result.bf10, 3.537314, 1e-5));
+
+        let invalid = DeltaPrior::T {
+            location: 0.0,
+            scale: 0.0,
+            df: 3.0,
+        };
+        assert!(independent_bf(&sleep(), "group", "extra", invalid, Alternative::Less).is_err());
+    }
+}
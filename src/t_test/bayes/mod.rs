@@ -1,3 +1,5 @@
+pub mod helper;
 pub mod independent;
 pub mod paired;
+pub mod robustness;
 pub mod single;
@@ -0,0 +1,44 @@
+use crate::{
+    core::{dataframe::DataFrame, errors::DatasetError, hypothesis::Alternative},
+    t_test::helper::paired_differences,
+};
+
+use super::{
+    helper::{BayesFactorResult, PriorScale},
+    single::one_sample_bf_t,
+};
+
+// JZS Bayes factor for paired samples, the one-sample test on first_var - second_var
+// as in ttestBF(x, y, paired = TRUE)
+pub fn paired_bf(
+    df: &DataFrame,
+    first_var: &str,
+    second_var: &str,
+    scale: PriorScale,
+    alternative: Alternative,
+) -> Result<BayesFactorResult, DatasetError> {
+    let differences = paired_differences(df, first_var, second_var)?;
+    one_sample_bf_t(&differences, 0.0, scale, alternative)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{fixtures::sleep_wide, helper::roughly_equals};
+
+    #[test]
+    fn test_paired_bf() {
+        let df = sleep_wide();
+
+        let result = paired_bf(
+            &df,
+            "drug_1",
+            "drug_2",
+            PriorScale::Medium,
+            Alternative::Greater,
+        )
+        .unwrap();
+        assert!(roughly_equals(result.bf10, 0.1008246, 1e-6));
+        assert!(roughly_equals(result.bf01(), 1.0 / 0.1008246, 1e-4));
+    }
+}
@@ -0,0 +1,281 @@
+use crate::{
+    core::{dataframe::DataFrame, errors::DatasetError, hypothesis::Alternative},
+    t_test::{
+        classical::{independent::independent_t, single::one_sample_t},
+        helper::paired_differences,
+    },
+};
+
+use super::helper::{bayes_factor, DeltaPrior, PriorScale};
+
+// The Bayesian t-test a robustness or sequential analysis is run for
+#[derive(Debug, Clone, Copy)]
+pub enum BayesTTest<'a> {
+    OneSample {
+        dependent_var: &'a str,
+        mu: f64,
+    },
+    Paired {
+        first_var: &'a str,
+        second_var: &'a str,
+    },
+    Independent {
+        grouping_var: &'a str,
+        dependent_var: &'a str,
+    },
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RobustnessPoint {
+    pub scale: f64,
+    pub bf10: f64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SequentialPoint {
+    pub n: usize,
+    pub bf10: f64,
+}
+
+// Evenly spaced Cauchy prior scales in (0, max_scale], as plotted by JASP
+pub fn prior_scale_grid(max_scale: f64, steps: usize) -> Vec<f64> {
+    (1..=steps)
+        .map(|i| max_scale * i as f64 / steps as f64)
+        .collect()
+}
+
+// Bayes factor as a function of the Cauchy prior scale
+pub fn robustness(
+    df: &DataFrame,
+    test: BayesTTest,
+    scales: &[f64],
+    alternative: Alternative,
+) -> Result<Vec<RobustnessPoint>, DatasetError> {
+    if scales.is_empty() || scales.iter().any(|&r| r <= 0.0 || !r.is_finite()) {
+        return Err(DatasetError::InvalidData(
+            "Prior scales must be positive and finite.".to_string(),
+        ));
+    }
+
+    let samples = Samples::from_dataframe(df, test)?;
+    let (t, n_eff, dof) = samples.statistics(samples.len())?.ok_or_else(|| {
+        DatasetError::InvalidData(
+            "The t statistic is undefined for these observations.".to_string(),
+        )
+    })?;
+
+    Ok(scales
+        .iter()
+        .map(|&scale| RobustnessPoint {
+            scale,
+            bf10: bayes_factor(
+                t,
+                n_eff,
+                dof,
+                &DeltaPrior::jzs(PriorScale::Custom(scale)),
+                alternative,
+            ),
+        })
+        .collect())
+}
+
+// Bayes factor after each observation (row of the data frame), starting from the
+// first n for which the test statistic is defined. Rows before it are skipped, any
+// other error such as a NaN observation is returned
+pub fn sequential(
+    df: &DataFrame,
+    test: BayesTTest,
+    scale: PriorScale,
+    alternative: Alternative,
+) -> Result<Vec<SequentialPoint>, DatasetError> {
+    let prior = DeltaPrior::jzs(scale);
+    prior.validate()?;
+
+    let samples = Samples::from_dataframe(df, test)?;
+    let mut points = Vec::new();
+    for n in 1..=samples.len() {
+        if let Some((t, n_eff, dof)) = samples.statistics(n)? {
+            points.push(SequentialPoint {
+                n,
+                bf10: bayes_factor(t, n_eff, dof, &prior, alternative),
+            });
+        }
+    }
+
+    if points.is_empty() {
+        return Err(DatasetError::InvalidData(
+            "Not enough observations for a sequential analysis.".to_string(),
+        ));
+    }
+    Ok(points)
+}
+
+// Observations in row order, so that the first n rows can be re-analysed
+enum Samples {
+    OneSample { values: Vec<f64>, mu: f64 },
+    Independent { values: Vec<f64>, first: Vec<bool> },
+}
+
+impl Samples {
+    fn from_dataframe(df: &DataFrame, test: BayesTTest) -> Result<Samples, DatasetError> {
+        match test {
+            BayesTTest::OneSample { dependent_var, mu } => Ok(Samples::OneSample {
+                values: df.get_column(dependent_var)?.get_values_as_f64()?,
+                mu,
+            }),
+            BayesTTest::Paired {
+                first_var,
+                second_var,
+            } => Ok(Samples::OneSample {
+                values: paired_differences(df, first_var, second_var)?,
+                mu: 0.0,
+            }),
+            BayesTTest::Independent {
+                grouping_var,
+                dependent_var,
+            } => {
+                let groups = df.get_column(grouping_var)?.get_values_as_str()?;
+                let values = df.get_column(dependent_var)?.get_values_as_f64()?;
+                if groups.len() != values.len() {
+                    return Err(DatasetError::InvalidData(format!(
+                        "Columns {} and {} must have the same number of observations",
+                        grouping_var, dependent_var
+                    )));
+                }
+
+                let mut levels = groups.clone();
+                levels.sort();
+                levels.dedup();
+                if levels.len() != 2 {
+                    return Err(DatasetError::InvalidData(format!(
+                        "Grouping variable {} must have exactly two levels, found {}",
+                        grouping_var,
+                        levels.len()
+                    )));
+                }
+
+                Ok(Samples::Independent {
+                    first: groups.iter().map(|g| *g == levels[0]).collect(),
+                    values,
+                })
+            }
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Samples::OneSample { values, .. } => values.len(),
+            Samples::Independent { values, .. } => values.len(),
+        }
+    }
+
+    // t statistic, effective sample size and degrees of freedom of the first n rows,
+    // None while they are too few or have no variance for the statistic to be defined
+    fn statistics(&self, n: usize) -> Result<Option<(f64, f64, f64)>, DatasetError> {
+        let (t, n_eff, dof) = match self {
+            Samples::OneSample { values, mu } => {
+                if n < 2 {
+                    return Ok(None);
+                }
+                let result = one_sample_t(&values[..n], *mu, Alternative::TwoSided)?;
+                (result.t, n as f64, result.dof)
+            }
+            Samples::Independent { values, first } => {
+                let (mut x, mut y) = (Vec::new(), Vec::new());
+                for (value, &is_first) in values[..n].iter().zip(first) {
+                    if is_first {
+                        x.push(*value);
+                    } else {
+                        y.push(*value);
+                    }
+                }
+                if x.len() < 2 || y.len() < 2 {
+                    return Ok(None);
+                }
+
+                let result = independent_t(&x, &y, true, Alternative::TwoSided)?;
+                let n_eff = (x.len() * y.len()) as f64 / n as f64;
+                (result.t, n_eff, result.dof)
+            }
+        };
+
+        // The observations are finite, so a non-finite t means zero variance so far
+        if !t.is_finite() {
+            return Ok(None);
+        }
+        Ok(Some((t, n_eff, dof)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::{fixtures::sleep_wide, helper::roughly_equals},
+        t_test::bayes::paired::paired_bf,
+    };
+
+    #[test]
+    fn test_robustness() {
+        let df = sleep_wide();
+        let test = BayesTTest::Paired {
+            first_var: "drug_1",
+            second_var: "drug_2",
+        };
+        let scales = prior_scale_grid(1.5, 15);
+        let points = robustness(&df, test, &scales, Alternative::TwoSided).unwrap();
+        assert_eq!(points.len(), 15);
+        assert!(roughly_equals(points[14].scale, 1.5, 1e-12));
+
+        // Every point equals the Bayes factor of the corresponding single analysis
+        let wide = paired_bf(
+            &df,
+            "drug_1",
+            "drug_2",
+            PriorScale::Wide,
+            Alternative::TwoSided,
+        )
+        .unwrap();
+        let point = points.iter().find(|p| roughly_equals(p.scale, 1.0, 1e-12));
+        assert!(roughly_equals(point.unwrap().bf10, wide.bf10, 1e-9));
+    }
+
+    #[test]
+    fn test_sequential() {
+        let df = sleep_wide();
+        let test = BayesTTest::Paired {
+            first_var: "drug_1",
+            second_var: "drug_2",
+        };
+        let points = sequential(&df, test, PriorScale::Medium, Alternative::TwoSided).unwrap();
+        assert_eq!(points.first().unwrap().n, 2);
+        assert_eq!(points.len(), 9);
+        assert!(roughly_equals(points.last().unwrap().bf10, 17.25888, 1e-4));
+
+        let mut df = DataFrame::new();
+        let groups = ["a", "b", "a", "b", "a", "b", "a", "b"];
+        df.add_categorical_column("group", groups.iter().map(|g| g.to_string()).collect());
+        df.add_numerical_column("score", vec![1.0, 2.1, 1.4, 2.6, 0.9, 2.2, 1.2, 2.8]);
+        let test = BayesTTest::Independent {
+            grouping_var: "group",
+            dependent_var: "score",
+        };
+        let points = sequential(&df, test, PriorScale::Medium, Alternative::TwoSided).unwrap();
+        assert_eq!(points.first().unwrap().n, 4);
+        assert_eq!(points.len(), 5);
+
+        // Ties at the start are skipped, a missing observation later on is an error
+        let mut df = DataFrame::new();
+        df.add_numerical_column("score", vec![1.0, 1.0, 1.0, 2.5, 0.5, 1.8]);
+        let test = BayesTTest::OneSample {
+            dependent_var: "score",
+            mu: 0.0,
+        };
+        let points = sequential(&df, test, PriorScale::Medium, Alternative::TwoSided).unwrap();
+        assert_eq!(points.first().unwrap().n, 4);
+
+        let mut df = DataFrame::new();
+        df.add_numerical_column("score", vec![1.0, 2.0, 1.5, f64::NAN, 0.5, 1.8]);
+        assert!(sequential(&df, test, PriorScale::Medium, Alternative::TwoSided).is_err());
+    }
+}
@@ -0,0 +1,62 @@
+use crate::{
+    core::{dataframe::DataFrame, errors::DatasetError, hypothesis::Alternative},
+    t_test::classical::single::one_sample_t,
+};
+
+use super::helper::{bayes_factor, BayesFactorResult, DeltaPrior, PriorScale};
+
+// JZS Bayes factor (Rouder et al., 2009) for a one-sample t-test of mu, equivalent to
+// ttestBF(x, mu = mu, rscale = scale) in the BayesFactor package. Directional
+// alternatives restrict the Cauchy prior to one sign of delta (nullInterval in ttestBF)
+pub fn one_sample_bf(
+    df: &DataFrame,
+    dependent_var: &str,
+    mu: f64,
+    scale: PriorScale,
+    alternative: Alternative,
+) -> Result<BayesFactorResult, DatasetError> {
+    let values = df.get_column(dependent_var)?.get_values_as_f64()?;
+    one_sample_bf_t(&values, mu, scale, alternative)
+}
+
+pub fn one_sample_bf_t(
+    values: &[f64],
+    mu: f64,
+    scale: PriorScale,
+    alternative: Alternative,
+) -> Result<BayesFactorResult, DatasetError> {
+    let prior = DeltaPrior::jzs(scale);
+    prior.validate()?;
+
+    let classical = one_sample_t(values, mu, Alternative::TwoSided)?;
+    let n = values.len();
+
+    Ok(BayesFactorResult {
+        bf10: bayes_factor(classical.t, n as f64, classical.dof, &prior, alternative),
+        t: classical.t,
+        dof: classical.dof,
+        n,
+        prior_scale: scale.value(),
+        alternative,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::helper::roughly_equals;
+
+    #[test]
+    fn test_one_sample_bf() {
+        // Differences of the sleep data, see the BayesFactor vignette
+        let differences = [-1.2, -2.4, -1.3, -1.3, 0.0, -1.0, -1.8, -0.8, -4.6, -1.4];
+
+        let result =
+            one_sample_bf_t(&differences, 0.0, PriorScale::Medium, Alternative::TwoSided).unwrap();
+        assert!(roughly_equals(result.bf10, 17.25888, 1e-4));
+
+        let result =
+            one_sample_bf_t(&differences, 0.0, PriorScale::Medium, Alternative::Less).unwrap();
+        assert!(roughly_equals(result.bf10, 34.41694, 1e-4));
+    }
+}
@@ -0,0 +1,418 @@
+use crate::{
+    core::{
+        dataframe::DataFrame,
+        errors::DatasetError,
+        helper::{mean, pearson, sample_variance},
+    },
+    distributions::{normal, tdist},
+    t_test::helper::{
+        mean_difference_error, paired_differences, pooled_standard_deviation, split_two_groups,
+    },
+};
+
+// Equivalence bounds, either in the units of the dependent variable or as
+// standardized effect sizes converted with the sample standard deviation
+#[derive(Debug, Clone, Copy)]
+pub enum EquivalenceBounds {
+    Raw { lower: f64, upper: f64 },
+    CohensD { lower: f64, upper: f64 },
+}
+
+#[derive(Debug)]
+pub struct OneSidedTest {
+    pub statistic: f64,
+    pub p_value: f64,
+}
+
+#[derive(Debug)]
+pub struct EquivalenceResult {
+    pub estimate: f64,
+    pub lower_bound: f64, // Bounds in raw units (or r for correlations)
+    pub upper_bound: f64,
+    pub lower_test: OneSidedTest, // H0: estimate <= lower_bound
+    pub upper_test: OneSidedTest, // H0: estimate >= upper_bound
+    pub dof: Option<f64>,         // None for the z-based correlation test
+    pub p_value: f64,             // Largest of the two one-sided p-values
+    pub ci_lower: f64,            // 1 - 2 * alpha confidence interval
+    pub ci_upper: f64,
+    pub equivalent: bool,
+}
+
+pub fn tost_one_sample(
+    df: &DataFrame,
+    dependent_var: &str,
+    mu: f64,
+    bounds: EquivalenceBounds,
+    alpha: f64,
+) -> Result<EquivalenceResult, DatasetError> {
+    let values = df.get_column(dependent_var)?.get_values_as_f64()?;
+    tost_one_sample_t(&values, mu, bounds, alpha)
+}
+
+pub fn tost_paired(
+    df: &DataFrame,
+    first_var: &str,
+    second_var: &str,
+    bounds: EquivalenceBounds,
+    alpha: f64,
+) -> Result<EquivalenceResult, DatasetError> {
+    let differences = paired_differences(df, first_var, second_var)?;
+    tost_one_sample_t(&differences, 0.0, bounds, alpha)
+}
+
+pub fn tost_independent(
+    df: &DataFrame,
+    grouping_var: &str,
+    dependent_var: &str,
+    equal_variances: bool,
+    bounds: EquivalenceBounds,
+    alpha: f64,
+) -> Result<EquivalenceResult, DatasetError> {
+    let (first, second) = split_two_groups(df, grouping_var, dependent_var)?;
+    tost_independent_t(&first.value, &second.value, equal_variances, bounds, alpha)
+}
+
+pub fn tost_correlation(
+    df: &DataFrame,
+    first_var: &str,
+    second_var: &str,
+    lower_r: f64,
+    upper_r: f64,
+    alpha: f64,
+) -> Result<EquivalenceResult, DatasetError> {
+    let x = df.get_column(first_var)?.get_values_as_f64()?;
+    let y = df.get_column(second_var)?.get_values_as_f64()?;
+    tost_correlation_r(&x, &y, lower_r, upper_r, alpha)
+}
+
+// Bounds in Cohen's d are converted with the standard deviation of the values
+// (d_z for paired differences)
+pub fn tost_one_sample_t(
+    values: &[f64],
+    mu: f64,
+    bounds: EquivalenceBounds,
+    alpha: f64,
+) -> Result<EquivalenceResult, DatasetError> {
+    validate_alpha(alpha)?;
+    if values.len() < 2 {
+        return Err(DatasetError::InvalidData(
+            "At least two observations are required.".to_string(),
+        ));
+    }
+    validate_finite(values)?;
+    if !mu.is_finite() {
+        return Err(DatasetError::InvalidData(
+            "The reference value mu must be finite.".to_string(),
+        ));
+    }
+
+    let n = values.len() as f64;
+    let sd = sample_variance(values).sqrt();
+    let (lower, upper) = raw_bounds(bounds, sd)?;
+
+    Ok(t_based(
+        mean(values) - mu,
+        sd / n.sqrt(),
+        n - 1.0,
+        lower,
+        upper,
+        alpha,
+    ))
+}
+
+// Bounds in Cohen's d are converted with the pooled standard deviation, or with
+// sqrt((s1^2 + s2^2) / 2) when Welch's correction is used
+pub fn tost_independent_t(
+    x: &[f64],
+    y: &[f64],
+    equal_variances: bool,
+    bounds: EquivalenceBounds,
+    alpha: f64,
+) -> Result<EquivalenceResult, DatasetError> {
+    validate_alpha(alpha)?;
+    if x.len() < 2 || y.len() < 2 {
+        return Err(DatasetError::InvalidData(
+            "Both groups need at least two observations.".to_string(),
+        ));
+    }
+    validate_finite(x)?;
+    validate_finite(y)?;
+
+    let (std_error, dof) = mean_difference_error(x, y, equal_variances);
+    let sd = if equal_variances {
+        pooled_standard_deviation(x, y)
+    } else {
+        ((sample_variance(x) + sample_variance(y)) / 2.0).sqrt()
+    };
+    let (lower, upper) = raw_bounds(bounds, sd)?;
+
+    Ok(t_based(
+        mean(x) - mean(y),
+        std_error,
+        dof,
+        lower,
+        upper,
+        alpha,
+    ))
+}
+
+// Equivalence test for Pearson's r using Fisher's z transformation
+pub fn tost_correlation_r(
+    x: &[f64],
+    y: &[f64],
+    lower_r: f64,
+    upper_r: f64,
+    alpha: f64,
+) -> Result<EquivalenceResult, DatasetError> {
+    validate_alpha(alpha)?;
+    if x.len() != y.len() || x.len() < 4 {
+        return Err(DatasetError::InvalidData(
+            "Correlations need two equally long samples of at least four observations.".to_string(),
+        ));
+    }
+    // Negated so that NaN bounds are rejected as well
+    if !(-1.0 < lower_r && lower_r < upper_r && upper_r < 1.0) {
+        return Err(DatasetError::InvalidData(
+            "Correlation bounds must satisfy -1 < lower < upper < 1.".to_string(),
+        ));
+    }
+    validate_finite(x)?;
+    validate_finite(y)?;
+
+    let r = pearson(x, y);
+
+    let z = r.atanh();
+    let std_error = 1.0 / (x.len() as f64 - 3.0).sqrt();
+    let lower_z = (z - lower_r.atanh()) / std_error;
+    let upper_z = (z - upper_r.atanh()) / std_error;
+    let margin = normal::quantile(1.0 - alpha) * std_error;
+
+    let lower_test = OneSidedTest {
+        statistic: lower_z,
+        p_value: normal::sf(lower_z),
+    };
+    let upper_test = OneSidedTest {
+        statistic: upper_z,
+        p_value: normal::cdf(upper_z),
+    };
+    let p_value = lower_test.p_value.max(upper_test.p_value);
+
+    Ok(EquivalenceResult {
+        estimate: r,
+        lower_bound: lower_r,
+        upper_bound: upper_r,
+        lower_test,
+        upper_test,
+        dof: None,
+        p_value,
+        ci_lower: (z - margin).tanh(),
+        ci_upper: (z + margin).tanh(),
+        equivalent: p_value < alpha,
+    })
+}
+
+fn t_based(
+    estimate: f64,
+    std_error: f64,
+    dof: f64,
+    lower: f64,
+    upper: f64,
+    alpha: f64,
+) -> EquivalenceResult {
+    let lower_t = (estimate - lower) / std_error;
+    let upper_t = (estimate - upper) / std_error;
+    let margin = tdist::quantile(1.0 - alpha, dof) * std_error;
+
+    let lower_test = OneSidedTest {
+        statistic: lower_t,
+        p_value: tdist::sf(lower_t, dof),
+    };
+    let upper_test = OneSidedTest {
+        statistic: upper_t,
+        p_value: tdist::cdf(upper_t, dof),
+    };
+    let p_value = lower_test.p_value.max(upper_test.p_value);
+
+    EquivalenceResult {
+        estimate,
+        lower_bound: lower,
+        upper_bound: upper,
+        lower_test,
+        upper_test,
+        dof: Some(dof),
+        p_value,
+        ci_lower: estimate - margin,
+        ci_upper: estimate + margin,
+        equivalent: p_value < alpha,
+    }
+}
+
+fn raw_bounds(bounds: EquivalenceBounds, sd: f64) -> Result<(f64, f64), DatasetError> {
+    let (lower, upper) = match bounds {
+        EquivalenceBounds::Raw { lower, upper } => (lower, upper),
+        EquivalenceBounds::CohensD { lower, upper } => (lower * sd, upper * sd),
+    };
+    if !lower.is_finite() || !upper.is_finite() {
+        return Err(DatasetError::InvalidData(
+            "Equivalence bounds must be finite.".to_string(),
+        ));
+    }
+    if lower >= upper {
+        return Err(DatasetError::InvalidData(
+            "The lower equivalence bound must be below the upper bound.".to_string(),
+        ));
+    }
+    Ok((lower, upper))
+}
+
+fn validate_finite(values: &[f64]) -> Result<(), DatasetError> {
+    if values.iter().any(|v| !v.is_finite()) {
+        return Err(DatasetError::InvalidData(
+            "Observations contain NaN or inf".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn validate_alpha(alpha: f64) -> Result<(), DatasetError> {
+    if !(alpha > 0.0 && alpha < 0.5) {
+        return Err(DatasetError::InvalidData(
+            "Alpha must lie between 0 and 0.5.".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{fixtures::sleep_wide, helper::roughly_equals};
+
+    #[test]
+    fn test_tost_one_sample() {
+        let df = sleep_wide();
+
+        let bounds = EquivalenceBounds::Raw {
+            lower: -2.0,
+            upper: 2.0,
+        };
+        let result = tost_one_sample(&df, "drug_1", 0.0, bounds, 0.05).unwrap();
+        let se = 1.789010 / 10f64.sqrt();
+        assert!(roughly_equals(result.lower_test.statistic, 2.75 / se, 1e-4));
+        assert!(roughly_equals(
+            result.upper_test.statistic,
+            -1.25 / se,
+            1e-4
+        ));
+        assert!(roughly_equals(result.ci_lower, 0.75 - 1.833113 * se, 1e-5));
+        assert!(roughly_equals(result.ci_upper, 0.75 + 1.833113 * se, 1e-5));
+        assert_eq!(result.p_value, result.upper_test.p_value);
+        assert!(result.equivalent);
+
+        // The same bounds expressed in Cohen's d
+        let bounds = EquivalenceBounds::CohensD {
+            lower: -2.0 / 1.789010,
+            upper: 2.0 / 1.789010,
+        };
+        let standardized = tost_one_sample(&df, "drug_1", 0.0, bounds, 0.05).unwrap();
+        assert!(roughly_equals(standardized.upper_bound, 2.0, 1e-5));
+        assert!(roughly_equals(standardized.p_value, result.p_value, 1e-5));
+    }
+
+    #[test]
+    fn test_tost_independent() {
+        let mut df = DataFrame::new();
+        let groups = (0..16)
+            .map(|i| if i < 8 { "a" } else { "b" }.to_string())
+            .collect();
+        df.add_categorical_column("group", groups);
+        df.add_numerical_column(
+            "score",
+            vec![
+                5.1, 4.9, 5.3, 5.0, 5.2, 4.8, 5.1, 5.0, 5.0, 5.1, 4.9, 5.2, 5.0, 4.9, 5.1, 5.0,
+            ],
+        );
+
+        let bounds = EquivalenceBounds::Raw {
+            lower: -0.2,
+            upper: 0.2,
+        };
+        let result = tost_independent(&df, "group", "score", true, bounds, 0.05).unwrap();
+        assert_eq!(result.dof, Some(14.0));
+        assert!(roughly_equals(result.lower_test.statistic, 3.334314, 1e-6));
+        assert!(roughly_equals(result.upper_test.statistic, -2.593355, 1e-6));
+        assert!(roughly_equals(result.lower_test.p_value, 0.002457498, 1e-8));
+        assert!(roughly_equals(result.p_value, 0.01062516, 1e-7));
+        assert!(result.equivalent);
+    }
+
+    #[test]
+    fn test_tost_correlation() {
+        let x = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 10.0];
+        let y = [2.0, 1.0, 4.0, 3.0, 6.0, 5.0, 8.0, 7.0, 10.0, 9.0];
+        let result = tost_correlation_r(&x, &y, -0.3, 0.3, 0.05).unwrap();
+
+        // TOSTr(n = 10, r = 0.9393939, low_eqbound_r = -0.3, high_eqbound_r = 0.3)
+        assert!(roughly_equals(result.estimate, 0.9393939394, 1e-9));
+        assert!(roughly_equals(
+            result.lower_test.statistic,
+            5.403649553,
+            1e-8
+        ));
+        assert!(roughly_equals(
+            result.lower_test.p_value,
+            3.264923170e-8,
+            1e-15
+        ));
+        assert!(roughly_equals(
+            result.upper_test.statistic,
+            3.765825756,
+            1e-8
+        ));
+        assert!(roughly_equals(
+            result.upper_test.p_value,
+            0.9999170002,
+            1e-9
+        ));
+        assert_eq!(result.p_value, result.upper_test.p_value);
+        assert!(roughly_equals(result.ci_lower, 0.8044761842, 1e-9));
+        assert!(roughly_equals(result.ci_upper, 0.9821357443, 1e-9));
+        assert!(!result.equivalent);
+    }
+
+    #[test]
+    fn test_invalid_input() {
+        let df = sleep_wide();
+        let values = df
+            .get_column("drug_1")
+            .unwrap()
+            .get_values_as_f64()
+            .unwrap();
+        let raw = |lower, upper| EquivalenceBounds::Raw { lower, upper };
+
+        // Bounds that are not finite or leave no interval
+        for bounds in [
+            raw(f64::NAN, 1.0),
+            raw(-1.0, f64::INFINITY),
+            raw(1.0, 1.0),
+            raw(1.0, -1.0),
+            EquivalenceBounds::CohensD {
+                lower: f64::NEG_INFINITY,
+                upper: 0.5,
+            },
+        ] {
+            assert!(tost_one_sample_t(&values, 0.0, bounds, 0.05).is_err());
+        }
+        assert!(tost_one_sample_t(&values, f64::NAN, raw(-1.0, 1.0), 0.05).is_err());
+        assert!(tost_one_sample_t(&values, 0.0, raw(-1.0, 1.0), f64::NAN).is_err());
+
+        // Observations that are not finite
+        let mut missing = values.clone();
+        missing[3] = f64::NAN;
+        assert!(tost_one_sample_t(&missing, 0.0, raw(-1.0, 1.0), 0.05).is_err());
+        assert!(tost_independent_t(&values, &missing, false, raw(-1.0, 1.0), 0.05).is_err());
+        assert!(tost_correlation_r(&values, &missing, -0.3, 0.3, 0.05).is_err());
+        assert!(tost_correlation_r(&values, &values, f64::NAN, 0.3, 0.05).is_err());
+        assert!(tost_correlation_r(&values, &values, 0.3, 0.3, 0.05).is_err());
+    }
+}
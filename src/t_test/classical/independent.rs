@@ -0,0 +1,76 @@
+use crate::{
+    core::{dataframe::DataFrame, errors::DatasetError, helper::mean, hypothesis::Alternative},
+    t_test::helper::{mean_difference_error, pooled_standard_deviation, split_two_groups},
+};
+
+use super::single::TTestResult;
+
+// Independent samples t-test of the first against the second level (alphabetical)
+// of the grouping variable, Welch's correction is applied unless equal_variances
+pub fn independent(
+    df: &DataFrame,
+    grouping_var: &str,
+    dependent_var: &str,
+    equal_variances: bool,
+    alternative: Alternative,
+) -> Result<TTestResult, DatasetError> {
+    let (first, second) = split_two_groups(df, grouping_var, dependent_var)?;
+    independent_t(&first.value, &second.value, equal_variances, alternative)
+}
+
+pub fn independent_t(
+    x: &[f64],
+    y: &[f64],
+    equal_variances: bool,
+    alternative: Alternative,
+) -> Result<TTestResult, DatasetError> {
+    if x.len() < 2 || y.len() < 2 {
+        return Err(DatasetError::InvalidData(
+            "Both groups need at least two observations.".to_string(),
+        ));
+    }
+    if x.iter().chain(y.iter()).any(|v| !v.is_finite()) {
+        return Err(DatasetError::InvalidData(
+            "Observations contain NaN or inf".to_string(),
+        ));
+    }
+
+    let difference = mean(x) - mean(y);
+    let pooled_sd = pooled_standard_deviation(x, y);
+    let (std_error, dof) = mean_difference_error(x, y, equal_variances);
+
+    Ok(TTestResult::new(
+        difference,
+        std_error,
+        dof,
+        difference / pooled_sd,
+        alternative,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{fixtures::sleep, helper::roughly_equals};
+
+    #[test]
+    fn test_welch() {
+        // t.test(extra ~ group, data = sleep) in R
+        let result = independent(&sleep(), "group", "extra", false, Alternative::TwoSided).unwrap();
+        assert!(roughly_equals(result.t, -1.860813, 1e-6));
+        assert!(roughly_equals(result.dof, 17.77647, 1e-5));
+        assert!(roughly_equals(result.p_value, 0.07939414, 1e-7));
+        assert!(roughly_equals(result.ci_lower, -3.3654832, 1e-6));
+        assert!(roughly_equals(result.ci_upper, 0.2054832, 1e-6));
+    }
+
+    #[test]
+    fn test_student() {
+        // t.test(extra ~ group, data = sleep, var.equal = TRUE) in R
+        let result = independent(&sleep(), "group", "extra", true, Alternative::TwoSided).unwrap();
+        assert!(roughly_equals(result.t, -1.860813, 1e-6));
+        assert_eq!(result.dof, 18.0);
+        assert!(roughly_equals(result.p_value, 0.07918671, 1e-7));
+        assert!(roughly_equals(result.ci_lower, -3.363874, 1e-6));
+    }
+}
@@ -0,0 +1,162 @@
+use crate::{
+    core::{
+        dataframe::DataFrame,
+        errors::DatasetError,
+        helper::{rank, tie_sizes},
+        hypothesis::Alternative,
+    },
+    distributions::normal,
+    t_test::helper::split_two_groups,
+};
+
+#[derive(Debug)]
+pub struct MannWhitneyResult {
+    pub w: f64, // U statistic of the first group (R reports this as W)
+    pub z: f64,
+    pub p_value: f64,
+    pub rank_biserial: f64,
+    pub n1: usize,
+    pub n2: usize,
+    pub exact: bool,
+}
+
+// Exact p-values are used below this group size when there are no ties,
+// otherwise the normal approximation with tie and continuity correction
+const EXACT_LIMIT: usize = 50;
+
+pub fn mann_whitney(
+    df: &DataFrame,
+    grouping_var: &str,
+    dependent_var: &str,
+    alternative: Alternative,
+) -> Result<MannWhitneyResult, DatasetError> {
+    let (first, second) = split_two_groups(df, grouping_var, dependent_var)?;
+    mann_whitney_u(&first.value, &second.value, alternative)
+}
+
+pub fn mann_whitney_u(
+    x: &[f64],
+    y: &[f64],
+    alternative: Alternative,
+) -> Result<MannWhitneyResult, DatasetError> {
+    let n1 = x.len();
+    let n2 = y.len();
+    if n1 == 0 || n2 == 0 {
+        return Err(DatasetError::InvalidData(
+            "Both groups need at least one observation.".to_string(),
+        ));
+    }
+
+    let combined: Vec<f64> = x.iter().chain(y.iter()).copied().collect();
+    if combined.iter().any(|v| !v.is_finite()) {
+        return Err(DatasetError::InvalidData(
+            "Observations contain NaN or inf".to_string(),
+        ));
+    }
+
+    let ranks = rank(&combined);
+    let rank_sum: f64 = ranks[..n1].iter().sum();
+    let w = rank_sum - (n1 * (n1 + 1)) as f64 / 2.0;
+
+    let n1f = n1 as f64;
+    let n2f = n2 as f64;
+    let n = n1f + n2f;
+    let ties = tie_sizes(&combined);
+    let tie_term: f64 = ties.iter().map(|&t| (t * t * t - t) as f64).sum::<f64>();
+
+    // Normal approximation, also reported alongside exact p-values
+    let centered = w - n1f * n2f / 2.0;
+    let sigma = ((n1f * n2f / 12.0) * ((n + 1.0) - tie_term / (n * (n - 1.0)))).sqrt();
+    let correction = match alternative {
+        Alternative::TwoSided => 0.5 * centered.signum(),
+        Alternative::Greater => 0.5,
+        Alternative::Less => -0.5,
+    };
+    let z = (centered - correction) / sigma;
+
+    let exact = n1 < EXACT_LIMIT && n2 < EXACT_LIMIT && ties.is_empty();
+    let p_value = if exact {
+        let distribution = u_distribution(n1, n2);
+        let u = w.round() as usize;
+        let lower: f64 = distribution[..=u].iter().sum();
+        let upper: f64 = distribution[u..].iter().sum();
+        alternative.p_value(lower, upper)
+    } else {
+        alternative.p_value(normal::cdf(z), normal::sf(z))
+    };
+
+    Ok(MannWhitneyResult {
+        w,
+        z,
+        p_value,
+        rank_biserial: 2.0 * w / (n1f * n2f) - 1.0,
+        n1,
+        n2,
+        exact,
+    })
+}
+
+// Null distribution of U, computed by counting the subsets of the ranks
+// 1..=n1 + n2 of size n1 that reach each rank sum
+fn u_distribution(n1: usize, n2: usize) -> Vec<f64> {
+    let n = n1 + n2;
+    let max_u = n1 * n2;
+    let max_sum = n1 * (n1 + 1) / 2 + max_u;
+
+    // counts[k][s]: number of size k subsets of the ranks seen so far summing to s
+    let mut counts = vec![vec![0.0; max_sum + 1]; n1 + 1];
+    counts[0][0] = 1.0;
+    for r in 1..=n {
+        for k in (1..=n1.min(r)).rev() {
+            for s in (r..=max_sum).rev() {
+                counts[k][s] += counts[k - 1][s - r];
+            }
+        }
+    }
+
+    let offset = n1 * (n1 + 1) / 2;
+    let total: f64 = counts[n1].iter().sum();
+    (0..=max_u)
+        .map(|u| counts[n1][u + offset] / total)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::helper::roughly_equals;
+
+    #[test]
+    fn test_mann_whitney_exact() {
+        // Example from R's wilcox.test documentation
+        let x = [0.80, 0.83, 1.89, 1.04, 1.45, 1.38, 1.91, 1.64, 0.73, 1.46];
+        let y = [1.15, 0.88, 0.90, 0.74, 1.21];
+
+        let result = mann_whitney_u(&x, &y, Alternative::Greater).unwrap();
+        assert!(result.exact);
+        assert_eq!(result.w, 35.0);
+        assert!(roughly_equals(result.p_value, 0.1272061, 1e-6));
+        assert!(roughly_equals(result.rank_biserial, 0.4, 1e-12));
+
+        let result = mann_whitney_u(&x, &y, Alternative::TwoSided).unwrap();
+        assert!(roughly_equals(result.p_value, 0.2544122, 1e-6));
+    }
+
+    #[test]
+    fn test_mann_whitney_ties() {
+        let mut df = DataFrame::new();
+        let groups = ["a", "a", "a", "a", "a", "b", "b", "b", "b", "b", "b"];
+        df.add_categorical_column("group", groups.iter().map(|g| g.to_string()).collect());
+        df.add_numerical_column(
+            "score",
+            vec![1.0, 2.0, 2.0, 3.0, 4.0, 2.0, 3.0, 4.0, 4.0, 5.0, 6.0],
+        );
+
+        let result = mann_whitney(&df, "group", "score", Alternative::TwoSided).unwrap();
+        assert!(!result.exact);
+        assert_eq!(result.w, 5.5);
+        assert!(roughly_equals(result.z, -1.677846, 1e-6));
+        assert!(roughly_equals(result.p_value, 0.0933772, 1e-6));
+        assert!(roughly_equals(result.rank_biserial, -0.633333, 1e-6));
+    }
+}
@@ -1,3 +1,6 @@
+pub mod equivalence;
 pub mod independent;
+pub mod mann_whitney;
 pub mod paired;
 pub mod single;
+pub mod wilcoxon;
@@ -0,0 +1,35 @@
+use crate::{
+    core::{dataframe::DataFrame, errors::DatasetError, hypothesis::Alternative},
+    t_test::helper::paired_differences,
+};
+
+use super::single::{one_sample_t, TTestResult};
+
+// Paired samples t-test on first_var - second_var, Cohen's d is reported as d_z
+pub fn paired(
+    df: &DataFrame,
+    first_var: &str,
+    second_var: &str,
+    alternative: Alternative,
+) -> Result<TTestResult, DatasetError> {
+    let differences = paired_differences(df, first_var, second_var)?;
+    one_sample_t(&differences, 0.0, alternative)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{fixtures::sleep_wide, helper::roughly_equals};
+
+    #[test]
+    fn test_paired() {
+        // t.test(extra ~ group, data = sleep, paired = TRUE) in R
+        let df = sleep_wide();
+
+        let result = paired(&df, "drug_1", "drug_2", Alternative::TwoSided).unwrap();
+        assert!(roughly_equals(result.t, -4.062128, 1e-6));
+        assert!(roughly_equals(result.p_value, 0.002832890, 1e-8));
+        assert!(roughly_equals(result.ci_lower, -2.4598858, 1e-6));
+        assert!(roughly_equals(result.ci_upper, -0.7001142, 1e-6));
+    }
+}
@@ -0,0 +1,118 @@
+use crate::{
+    core::{
+        dataframe::DataFrame,
+        errors::DatasetError,
+        helper::{mean, sample_variance},
+        hypothesis::Alternative,
+    },
+    distributions::tdist,
+};
+
+#[derive(Debug)]
+pub struct TTestResult {
+    pub t: f64,
+    pub dof: f64,
+    pub p_value: f64,
+    pub mean_difference: f64,
+    pub std_error: f64,
+    pub ci_lower: f64, // 95% confidence interval, one-sided for directional alternatives
+    pub ci_upper: f64,
+    pub cohens_d: f64,
+}
+
+impl TTestResult {
+    pub(crate) fn new(
+        mean_difference: f64,
+        std_error: f64,
+        dof: f64,
+        cohens_d: f64,
+        alternative: Alternative,
+    ) -> TTestResult {
+        let t = mean_difference / std_error;
+        let p_value = alternative.p_value(tdist::cdf(t, dof), tdist::sf(t, dof));
+
+        let (ci_lower, ci_upper) = match alternative {
+            Alternative::TwoSided => {
+                let margin = tdist::quantile(0.975, dof) * std_error;
+                (mean_difference - margin, mean_difference + margin)
+            }
+            Alternative::Less => (
+                f64::NEG_INFINITY,
+                mean_difference + tdist::quantile(0.95, dof) * std_error,
+            ),
+            Alternative::Greater => (
+                mean_difference - tdist::quantile(0.95, dof) * std_error,
+                f64::INFINITY,
+            ),
+        };
+
+        TTestResult {
+            t,
+            dof,
+            p_value,
+            mean_difference,
+            std_error,
+            ci_lower,
+            ci_upper,
+            cohens_d,
+        }
+    }
+}
+
+pub fn one_sample(
+    df: &DataFrame,
+    dependent_var: &str,
+    mu: f64,
+    alternative: Alternative,
+) -> Result<TTestResult, DatasetError> {
+    let values = df.get_column(dependent_var)?.get_values_as_f64()?;
+    one_sample_t(&values, mu, alternative)
+}
+
+pub fn one_sample_t(
+    values: &[f64],
+    mu: f64,
+    alternative: Alternative,
+) -> Result<TTestResult, DatasetError> {
+    if values.len() < 2 {
+        return Err(DatasetError::InvalidData(
+            "At least two observations are required.".to_string(),
+        ));
+    }
+    if values.iter().any(|v| !v.is_finite()) {
+        return Err(DatasetError::InvalidData(
+            "Observations contain NaN or inf".to_string(),
+        ));
+    }
+
+    let n = values.len() as f64;
+    let sd = sample_variance(values).sqrt();
+    let difference = mean(values) - mu;
+
+    Ok(TTestResult::new(
+        difference,
+        sd / n.sqrt(),
+        n - 1.0,
+        difference / sd,
+        alternative,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{fixtures::sleep_wide, helper::roughly_equals};
+
+    #[test]
+    fn test_one_sample() {
+        // t.test(sleep$extra[1:10]) in R
+        let df = sleep_wide();
+
+        let result = one_sample(&df, "drug_1", 0.0, Alternative::TwoSided).unwrap();
+        assert!(roughly_equals(result.t, 1.325710, 1e-6));
+        assert_eq!(result.dof, 9.0);
+        assert!(roughly_equals(result.p_value, 0.2175978, 1e-6));
+        assert!(roughly_equals(result.ci_lower, -0.5297804, 1e-6));
+        assert!(roughly_equals(result.ci_upper, 2.0297804, 1e-6));
+    }
+}
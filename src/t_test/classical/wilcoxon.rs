@@ -0,0 +1,159 @@
+use crate::{
+    core::{
+        dataframe::DataFrame,
+        errors::DatasetError,
+        helper::{rank, tie_sizes},
+        hypothesis::Alternative,
+    },
+    distributions::normal,
+    t_test::helper::paired_differences,
+};
+
+#[derive(Debug)]
+pub struct WilcoxonResult {
+    pub v: f64, // Sum of the ranks of the positive differences
+    pub z: f64,
+    pub p_value: f64,
+    pub rank_biserial: f64,
+    pub n: usize, // Number of non-zero differences
+    pub exact: bool,
+}
+
+// Exact p-values are used below this sample size when there are no ties or
+// zero differences, otherwise the normal approximation with corrections
+const EXACT_LIMIT: usize = 50;
+
+pub fn wilcoxon(
+    df: &DataFrame,
+    first_var: &str,
+    second_var: &str,
+    alternative: Alternative,
+) -> Result<WilcoxonResult, DatasetError> {
+    let differences = paired_differences(df, first_var, second_var)?;
+    signed_rank(&differences, 0.0, alternative)
+}
+
+// Signed-rank test of the values against mu, pass paired differences and mu = 0
+// for the paired test
+pub fn signed_rank(
+    values: &[f64],
+    mu: f64,
+    alternative: Alternative,
+) -> Result<WilcoxonResult, DatasetError> {
+    if values.iter().any(|v| !v.is_finite()) {
+        return Err(DatasetError::InvalidData(
+            "Observations contain NaN or inf".to_string(),
+        ));
+    }
+
+    let shifted: Vec<f64> = values.iter().map(|v| v - mu).collect();
+    let has_zeroes = shifted.contains(&0.0);
+    let differences: Vec<f64> = shifted.into_iter().filter(|&d| d != 0.0).collect();
+    let n = differences.len();
+    if n == 0 {
+        return Err(DatasetError::InvalidData(
+            "All differences are zero.".to_string(),
+        ));
+    }
+
+    let absolute: Vec<f64> = differences.iter().map(|d| d.abs()).collect();
+    let ranks = rank(&absolute);
+    let v: f64 = ranks
+        .iter()
+        .zip(&differences)
+        .filter(|(_, &d)| d > 0.0)
+        .map(|(r, _)| r)
+        .sum();
+
+    let nf = n as f64;
+    let total = nf * (nf + 1.0) / 2.0;
+    let ties = tie_sizes(&absolute);
+    let tie_term: f64 = ties.iter().map(|&t| (t * t * t - t) as f64).sum::<f64>();
+
+    let centered = v - total / 2.0;
+    let sigma = (nf * (nf + 1.0) * (2.0 * nf + 1.0) / 24.0 - tie_term / 48.0).sqrt();
+    let correction = match alternative {
+        Alternative::TwoSided => 0.5 * centered.signum(),
+        Alternative::Greater => 0.5,
+        Alternative::Less => -0.5,
+    };
+    let z = (centered - correction) / sigma;
+
+    let exact = n < EXACT_LIMIT && ties.is_empty() && !has_zeroes;
+    let p_value = if exact {
+        let distribution = signed_rank_distribution(n);
+        let v_index = v.round() as usize;
+        let lower: f64 = distribution[..=v_index].iter().sum();
+        let upper: f64 = distribution[v_index..].iter().sum();
+        alternative.p_value(lower, upper)
+    } else {
+        alternative.p_value(normal::cdf(z), normal::sf(z))
+    };
+
+    Ok(WilcoxonResult {
+        v,
+        z,
+        p_value,
+        rank_biserial: (2.0 * v - total) / total,
+        n,
+        exact,
+    })
+}
+
+// Null distribution of V: the number of subsets of the ranks 1..=n reaching
+// each sum, divided by the 2^n equally likely sign assignments
+fn signed_rank_distribution(n: usize) -> Vec<f64> {
+    let max_sum = n * (n + 1) / 2;
+    let mut counts = vec![0.0; max_sum + 1];
+    counts[0] = 1.0;
+    for r in 1..=n {
+        for s in (r..=max_sum).rev() {
+            counts[s] += counts[s - r];
+        }
+    }
+
+    let total = 2f64.powi(n as i32);
+    counts.iter().map(|c| c / total).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::helper::roughly_equals;
+
+    #[test]
+    #[allow(clippy::approx_constant)]
+    fn test_wilcoxon_exact() {
+        // Example from R's wilcox.test documentation
+        let mut df = DataFrame::new();
+        df.add_numerical_column(
+            "x",
+            vec![1.83, 0.50, 1.62, 2.48, 1.68, 1.88, 1.55, 3.06, 1.30],
+        );
+        df.add_numerical_column(
+            "y",
+            vec![0.878, 0.647, 0.598, 2.05, 1.06, 1.29, 1.06, 3.14, 1.29],
+        );
+
+        let result = wilcoxon(&df, "x", "y", Alternative::Greater).unwrap();
+        assert!(result.exact);
+        assert_eq!(result.v, 40.0);
+        assert!(roughly_equals(result.p_value, 0.01953125, 1e-8));
+        assert!(roughly_equals(result.rank_biserial, 35.0 / 45.0, 1e-12));
+
+        let result = wilcoxon(&df, "x", "y", Alternative::TwoSided).unwrap();
+        assert!(roughly_equals(result.p_value, 0.0390625, 1e-8));
+    }
+
+    #[test]
+    fn test_signed_rank_ties() {
+        let values = [1.5, -0.5, 2.0, 2.0, 3.5, 0.0, -1.0, 4.0];
+        let result = signed_rank(&values, 0.0, Alternative::TwoSided).unwrap();
+
+        assert!(!result.exact);
+        assert_eq!(result.n, 7);
+        assert_eq!(result.v, 25.0);
+        assert!(roughly_equals(result.z, 1.778002, 1e-6));
+        assert!(roughly_equals(result.p_value, 0.0754036, 1e-6));
+    }
+}
@@ -0,0 +1,99 @@
+use crate::core::{dataframe::DataFrame, errors::DatasetError, helper::sample_variance};
+
+#[derive(Debug, Clone)]
+pub struct GroupScores {
+    pub name: String,
+    pub value: Vec<f64>,
+}
+
+// Splits the dependent variable on a categorical grouping variable with exactly
+// two levels, ordered alphabetically so the direction of the test is stable
+pub fn split_two_groups(
+    df: &DataFrame,
+    grouping_var: &str,
+    dependent_var: &str,
+) -> Result<(GroupScores, GroupScores), DatasetError> {
+    let levels = df.cat_iv_levels(&[grouping_var], dependent_var)?;
+    if levels.len() != 2 {
+        return Err(DatasetError::InvalidData(format!(
+            "Grouping variable {} must have exactly two levels, found {}",
+            grouping_var,
+            levels.len()
+        )));
+    }
+
+    let prefix = format!("{}_", grouping_var);
+    let mut groups: Vec<GroupScores> = levels
+        .into_iter()
+        .map(|(name, value)| GroupScores {
+            name: name.strip_prefix(&prefix).unwrap_or(&name).to_owned(),
+            value,
+        })
+        .collect();
+    groups.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let second = groups.pop().unwrap();
+    let first = groups.pop().unwrap();
+    Ok((first, second))
+}
+
+// Pairs two numerical columns and returns their differences (first - second)
+pub fn paired_differences(
+    df: &DataFrame,
+    first_var: &str,
+    second_var: &str,
+) -> Result<Vec<f64>, DatasetError> {
+    let first = df.get_column(first_var)?.get_values_as_f64()?;
+    let second = df.get_column(second_var)?.get_values_as_f64()?;
+    if first.len() != second.len() {
+        return Err(DatasetError::InvalidData(format!(
+            "Columns {} and {} must have the same number of observations",
+            first_var, second_var
+        )));
+    }
+
+    Ok(first.iter().zip(&second).map(|(a, b)| a - b).collect())
+}
+
+// Pooled standard deviation of two groups, the standardizer of Cohen's d
+pub fn pooled_standard_deviation(x: &[f64], y: &[f64]) -> f64 {
+    let (n1, n2) = (x.len() as f64, y.len() as f64);
+    let (var1, var2) = (sample_variance(x), sample_variance(y));
+    (((n1 - 1.0) * var1 + (n2 - 1.0) * var2) / (n1 + n2 - 2.0)).sqrt()
+}
+
+// Standard error and degrees of freedom of the difference between two group means,
+// from the pooled variance or with the Welch-Satterthwaite approximation
+pub fn mean_difference_error(x: &[f64], y: &[f64], equal_variances: bool) -> (f64, f64) {
+    let (n1, n2) = (x.len() as f64, y.len() as f64);
+    if equal_variances {
+        let pooled_sd = pooled_standard_deviation(x, y);
+        (pooled_sd * (1.0 / n1 + 1.0 / n2).sqrt(), n1 + n2 - 2.0)
+    } else {
+        let (var1, var2) = (sample_variance(x), sample_variance(y));
+        let (a, b) = (var1 / n1, var2 / n2);
+        let dof = (a + b).powi(2) / (a * a / (n1 - 1.0) + b * b / (n2 - 1.0));
+        ((a + b).sqrt(), dof)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_two_groups() {
+        let mut df = DataFrame::new();
+        df.add_categorical_column(
+            "group",
+            vec!["b".to_string(), "a".to_string(), "b".to_string()],
+        );
+        df.add_numerical_column("score", vec![1.0, 2.0, 3.0]);
+
+        let (first, second) = split_two_groups(&df, "group", "score").unwrap();
+        assert_eq!(first.name, "a");
+        assert_eq!(first.value, vec![2.0]);
+        assert_eq!(second.name, "b");
+        assert_eq!(second.value, vec![1.0, 3.0]);
+    }
+}
@@ -1,2 +1,3 @@
 pub mod bayes;
 pub mod classical;
+pub mod helper;